@@ -0,0 +1,188 @@
+use chrono::{DateTime, Local, NaiveDateTime, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::schema::encode_tasks;
+use rusqlite::Connection;
+use crate::store::{SqliteBackend, validate_json_file, write_atomic};
+use crate::task::{Task, TaskError};
+
+// File name timestamp format (UTC, millisecond precision so saves never collide)
+const STAMP_FORMAT: &str = "%Y%m%dT%H%M%S%3f";
+
+// How many backup generations to keep
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct BackupSettings {
+    // Maximum number of generations kept
+    #[serde(default = "default_keep")]
+    pub keep: usize,
+    // Generations older than this are pruned (the newest one is always kept)
+    #[serde(default = "default_max_age_days")]
+    pub max_age_days: Option<u32>,
+    // SQLite stores are copied at most this often instead of on every save
+    #[serde(default = "default_sqlite_interval_minutes")]
+    pub sqlite_interval_minutes: u32,
+}
+
+fn default_keep() -> usize {
+    10
+}
+
+fn default_max_age_days() -> Option<u32> {
+    Some(30)
+}
+
+fn default_sqlite_interval_minutes() -> u32 {
+    60
+}
+
+impl Default for BackupSettings {
+    fn default() -> Self {
+        BackupSettings { keep: default_keep(), max_age_days: default_max_age_days(), sqlite_interval_minutes: default_sqlite_interval_minutes() }
+    }
+}
+
+// One saved generation of todos (generation 1 is the newest): a todos.json copy, or a
+// todos.db copy for the SQLite backend
+#[derive(Debug, Clone)]
+pub struct Backup {
+    pub generation: usize,
+    pub path: PathBuf,
+    pub created: DateTime<Utc>,
+}
+
+impl Backup {
+    // Creation time formatted in local time
+    pub fn created_local(&self) -> String {
+        self.created.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S").to_string()
+    }
+
+    // Load the tasks stored in this generation
+    pub fn load(&self) -> Result<Vec<Task>, TaskError> {
+        if self.path.extension().is_some_and(|extension| extension == "db") {
+            return SqliteBackend::read_tasks(&self.path);
+        }
+        validate_json_file(&self.path)
+    }
+}
+
+// Directory holding backup generations
+fn backups_dir(dir: &Path) -> PathBuf {
+    dir.join("backups")
+}
+
+// List backup generations, newest first
+pub fn list_backups(dir: &Path) -> Result<Vec<Backup>, TaskError> {
+    let backups_dir = backups_dir(dir);
+    if !backups_dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut backups = Vec::new();
+    for entry in fs::read_dir(&backups_dir)? {
+        let path = entry?.path();
+        let Some(name) = path.file_name().and_then(|name| name.to_str()) else { continue };
+        let Some(stamp) = name.strip_prefix("todos-")
+            .and_then(|rest| rest.strip_suffix(".json").or_else(|| rest.strip_suffix(".db"))) else { continue };
+        let Ok(created) = NaiveDateTime::parse_from_str(stamp, STAMP_FORMAT) else { continue };
+
+        backups.push(Backup { generation: 0, path, created: created.and_utc() });
+    }
+
+    backups.sort_by_key(|backup| Reverse(backup.created));
+    for (index, backup) in backups.iter_mut().enumerate() {
+        backup.generation = index + 1;
+    }
+
+    Ok(backups)
+}
+
+// Find a generation by number
+pub fn find_backup(dir: &Path, generation: usize) -> Result<Backup, TaskError> {
+    list_backups(dir)?
+        .into_iter()
+        .find(|backup| backup.generation == generation)
+        .ok_or_else(|| TaskError::InvalidInput(format!("Backup generation {} not found", generation)))
+}
+
+// Path for a new generation with the given extension
+fn new_backup_path(dir: &Path, extension: &str) -> Result<PathBuf, TaskError> {
+    let backups_dir = backups_dir(dir);
+    if !backups_dir.exists() {
+        fs::create_dir_all(&backups_dir)?;
+    }
+
+    let mut now = Utc::now();
+    let mut path = backups_dir.join(format!("todos-{}.{}", now.format(STAMP_FORMAT), extension));
+    // Two saves within the same millisecond still get separate generations
+    while path.exists() {
+        now += TimeDelta::milliseconds(1);
+        path = backups_dir.join(format!("todos-{}.{}", now.format(STAMP_FORMAT), extension));
+    }
+    Ok(path)
+}
+
+// Save tasks as the newest generation, then prune old ones
+pub fn create_backup(dir: &Path, tasks: &[Task], settings: &BackupSettings) -> Result<(), TaskError> {
+    let path = new_backup_path(dir, "json")?;
+    let json = encode_tasks(tasks, None)?;
+    write_atomic(&path, json.as_bytes())?;
+
+    prune_quietly(dir, settings);
+    Ok(())
+}
+
+// Copy todos.db into a new generation with VACUUM INTO, then prune old ones. Unless `force`
+// is set, nothing is copied while the newest generation is younger than the configured interval.
+pub fn create_sqlite_backup(dir: &Path, settings: &BackupSettings, force: bool) -> Result<(), TaskError> {
+    let db = dir.join("todos.db");
+    if !db.exists() {
+        return Ok(());
+    }
+    if !force {
+        let interval = TimeDelta::minutes(i64::from(settings.sqlite_interval_minutes));
+        if list_backups(dir)?.first().is_some_and(|newest| Utc::now() - newest.created < interval) {
+            return Ok(());
+        }
+    }
+
+    let conn = Connection::open(&db)?;
+    conn.busy_timeout(std::time::Duration::from_secs(2))?;
+    let count: u32 = conn.query_row("SELECT COUNT(*) FROM tasks", [], |row| row.get(0))?;
+    if count == 0 {
+        return Ok(());
+    }
+    let path = new_backup_path(dir, "db")?;
+    conn.execute("VACUUM INTO ?1", [path.to_string_lossy()])?;
+
+    prune_quietly(dir, settings);
+    Ok(())
+}
+
+// Prune after a new generation was written; the new backup stands even if old ones stay
+fn prune_quietly(dir: &Path, settings: &BackupSettings) {
+    if let Err(err) = prune_backups(dir, settings) {
+        eprintln!("Warning: Could not prune old backups: {}", err);
+    }
+}
+
+// Remove generations beyond the configured count or age. A generation that cannot be
+// removed does not stop the others; the first error is returned at the end.
+pub fn prune_backups(dir: &Path, settings: &BackupSettings) -> Result<(), TaskError> {
+    let cutoff = settings.max_age_days.map(|days| Utc::now() - TimeDelta::days(i64::from(days)));
+
+    let mut result = Ok(());
+    for backup in list_backups(dir)? {
+        let too_many = backup.generation > settings.keep.max(1);
+        let too_old = backup.generation > 1 && cutoff.is_some_and(|cutoff| backup.created < cutoff);
+        if (too_many || too_old)
+            && let Err(err) = fs::remove_file(&backup.path)
+            && result.is_ok()
+        {
+            result = Err(err.into());
+        }
+    }
+
+    result
+}
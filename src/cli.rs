@@ -1,10 +1,18 @@
 use clap::{Parser, Subcommand};
+use std::path::PathBuf;
+use crate::config::BackendKind;
+use crate::task::{ChildPolicy, Priority, TaskRef};
+use crate::view::GroupBy;
 
 #[derive(Parser)]
 #[command(name = "todo")]
 #[command(about = "🦀 Your Super Fast Todo Tool")]
 #[command(version)]
 pub struct Cli {
+    /// Directory holding todos.json (overrides TODO_DIR and XDG_DATA_HOME)
+    #[arg(long = "data-dir", visible_alias = "store", global = true, value_name = "DIR")]
+    pub data_dir: Option<PathBuf>,
+
     #[command(subcommand)]
     pub command: Commands,
 }
@@ -17,6 +25,24 @@ pub enum Commands {
         /// Set due date (days from today, default: 0 = today)
         #[arg(default_value = "0")]
         days: i32,
+        /// Due date: tomorrow, fri, next monday, in 2 weeks, eom, dec 3 or YYYY-MM-DD
+        #[arg(short, long, value_name = "DATE", conflicts_with = "days")]
+        due: Option<String>,
+        /// Priority: H, M, L or 1-5 (1 = highest)
+        #[arg(short, long)]
+        priority: Option<Priority>,
+        /// Project, e.g. work.infra.ci
+        #[arg(short = 'P', long)]
+        project: Option<String>,
+        /// Make this a subtask of another task (ID or UUID)
+        #[arg(long, value_name = "ID")]
+        parent: Option<TaskRef>,
+        /// Date work can start on (same expressions as --due)
+        #[arg(long, value_name = "DATE")]
+        scheduled: Option<String>,
+        /// Hide the task until this date (same expressions as --due)
+        #[arg(long, value_name = "DATE")]
+        wait: Option<String>,
     },
     /// Show all tasks
     List {
@@ -26,11 +52,95 @@ pub enum Commands {
         /// Show today's tasks only
         #[arg(short, long)]
         today: bool,
+        /// Sort order, e.g. due,-priority,id ("-" for descending; also urgency, text, project, status,
+        /// scheduled, wait, created, modified, completed, removed or an attribute)
+        #[arg(long, value_name = "KEYS", allow_hyphen_values = true)]
+        sort: Option<String>,
+        /// Group tasks by due month, week or day, project, tag or status, or not at all
+        #[arg(long, value_enum, value_name = "GROUP")]
+        group_by: Option<GroupBy>,
+        /// Only tasks created on or after this date (YYYY-MM-DD)
+        #[arg(long, value_name = "DATE")]
+        created_since: Option<String>,
+        /// Only tasks modified on or after this date (YYYY-MM-DD)
+        #[arg(long, value_name = "DATE")]
+        modified_since: Option<String>,
+        /// Only tasks completed on or after this date (YYYY-MM-DD)
+        #[arg(long, value_name = "DATE")]
+        completed_since: Option<String>,
+        /// Only tasks in this project and its subprojects
+        #[arg(short = 'P', long)]
+        project: Option<String>,
+        /// Hide tasks blocked by open dependencies
+        #[arg(short, long)]
+        ready: bool,
+        /// Include tasks that are waiting (hidden until their wait date)
+        #[arg(short, long)]
+        waiting: bool,
+        /// Filter by a user-defined attribute: name=value, name!=value, name>value, name~text, name= (unset)
+        #[arg(long = "where", value_name = "FILTER")]
+        attr_filters: Vec<String>,
+        /// Filter, e.g. +work -someday pri>=M due.before:friday (text~"deploy" or text~release)
+        #[arg(allow_hyphen_values = true, value_name = "FILTER")]
+        filters: Vec<String>,
     },
     /// Mark task as done
-    Done { id: u32 },
+    Done {
+        id: TaskRef,
+        /// What to do with subtasks: cascade, refuse (while open) or orphan (leave them)
+        #[arg(long, value_enum, default_value = "orphan")]
+        children: ChildPolicy,
+    },
+    /// Mark task as in progress
+    Start { id: TaskRef },
+    /// Mark task as blocked
+    Block {
+        id: TaskRef,
+        /// What the task is waiting on
+        #[arg(short, long)]
+        reason: Option<String>,
+    },
+    /// Put task off without a date
+    Defer { id: TaskRef },
+    /// Cancel task (a recurring task continues with its next instance)
+    Cancel {
+        id: TaskRef,
+        /// What to do with subtasks: cascade, refuse (while open) or orphan (leave them)
+        #[arg(long, value_enum, default_value = "orphan")]
+        children: ChildPolicy,
+    },
+    /// Move task back to pending
+    Reopen {
+        id: TaskRef,
+        /// cascade also reopens completed subtasks; refuse and orphan leave them
+        #[arg(long, value_enum, default_value = "orphan")]
+        children: ChildPolicy,
+    },
+    /// Set user-defined attributes declared in config.json: name=value ("name=" clears it)
+    Set {
+        id: TaskRef,
+        #[arg(required = true, value_name = "NAME=VALUE")]
+        assignments: Vec<String>,
+    },
+    /// Show all details of a task, including timestamps
+    Show { id: TaskRef },
+    /// Edit the multi-line notes of a task in $VISUAL / $EDITOR
+    Note {
+        id: TaskRef,
+        /// Replace the notes without opening an editor ("" clears them)
+        #[arg(short, long, conflicts_with = "append", allow_hyphen_values = true)]
+        message: Option<String>,
+        /// Add a line to the end of the notes
+        #[arg(short, long, allow_hyphen_values = true)]
+        append: Option<String>,
+    },
     /// Delete a task
-    Delete { id: u32 },
+    Delete {
+        id: TaskRef,
+        /// What to do with subtasks: cascade, refuse or orphan (make them top-level)
+        #[arg(long, value_enum, default_value = "refuse")]
+        children: ChildPolicy,
+    },
     /// Remove all tasks from a specific date
     Remove {
         /// Days ago from today (default: 0 = today)
@@ -38,21 +148,229 @@ pub enum Commands {
         days: i32,
     },
     /// Show removed tasks
-    Removed,
-    /// Edit a task
-    Edit { id: u32, text: String },
-    /// Set due date for a task
-    Due { id: u32, date: String },
+    Removed {
+        /// Sort order, e.g. due,-priority,id ("-" for descending; also urgency, text, project, status,
+        /// scheduled, wait, created, modified, completed, removed or an attribute)
+        #[arg(long, value_name = "KEYS", allow_hyphen_values = true)]
+        sort: Option<String>,
+        /// Group tasks by due month, week or day, project, tag or status, or not at all
+        #[arg(long, value_enum, value_name = "GROUP")]
+        group_by: Option<GroupBy>,
+    },
+    /// Replace a task's text and tags (+tag words in TEXT)
+    Edit { id: TaskRef, text: String },
+    /// Set due date for a task (tomorrow, fri, next monday, in 2 weeks, eom, dec 3, YYYY-MM-DD)
+    Due {
+        id: TaskRef,
+        #[arg(required = true, num_args = 1.., value_name = "DATE")]
+        date: Vec<String>,
+    },
+    /// Set the date a task can start on ("none" to clear)
+    Schedule {
+        id: TaskRef,
+        #[arg(required = true, num_args = 1.., value_name = "DATE")]
+        date: Vec<String>,
+    },
+    /// Hide a task until a date ("none" to clear)
+    Wait {
+        id: TaskRef,
+        #[arg(required = true, num_args = 1.., value_name = "DATE")]
+        date: Vec<String>,
+    },
+    /// Show what a date expression resolves to
+    Date {
+        #[arg(required = true, num_args = 1.., allow_hyphen_values = true, value_name = "EXPR")]
+        expr: Vec<String>,
+    },
+    /// Set task priority (H, M, L, 1-5, or "none" to clear)
+    Priority { id: TaskRef, level: String },
+    /// Make a task depend on another: `todo depend 5 on 3`
+    Depend {
+        id: TaskRef,
+        #[arg(value_name = "on", value_parser = ["on"], hide_possible_values = true)]
+        on: String,
+        /// Task that has to be done first
+        dependency: TaskRef,
+        /// Remove the dependency instead
+        #[arg(long)]
+        remove: bool,
+    },
+    /// Make a task repeat: daily, weekly [mon,thu], monthly [15], every N days; or pause, resume, end
+    Recur {
+        id: TaskRef,
+        #[arg(value_name = "RULE")]
+        rule: Vec<String>,
+        /// Last date the series may be due on (YYYY-MM-DD)
+        #[arg(long, value_name = "DATE")]
+        until: Option<String>,
+    },
+    /// Print the dependency graph as Graphviz DOT
+    Graph {
+        /// Include tasks without dependencies
+        #[arg(short, long)]
+        all: bool,
+    },
     /// Sync to GitHub Gist
     Sync,
     /// Party time!
     Party,
-    /// Search tasks
-    Search { query: String },
+    /// Search tasks: words match the task text, filters like +tag or due.before:friday narrow it down
+    Search {
+        /// Sort order, e.g. due,-priority,id ("-" for descending; also urgency, text, project, status,
+        /// scheduled, wait, created, modified, completed, removed or an attribute)
+        #[arg(long, value_name = "KEYS", allow_hyphen_values = true)]
+        sort: Option<String>,
+        /// Group tasks by due month, week or day, project, tag or status, or not at all
+        #[arg(long, value_enum, value_name = "GROUP")]
+        group_by: Option<GroupBy>,
+        #[arg(required = true, num_args = 1.., allow_hyphen_values = true, value_name = "QUERY")]
+        query: Vec<String>,
+    },
+    /// Change every task matching a filter at once, e.g. `todo bulk done +sprint due.before:today`
+    Bulk {
+        /// Apply without asking for confirmation
+        #[arg(short, long)]
+        yes: bool,
+        #[command(subcommand)]
+        action: BulkAction,
+    },
+    /// Save, run and list named filters
+    Query {
+        #[command(subcommand)]
+        action: QueryAction,
+    },
+    /// Apply a saved query to every listing, search and the TUI; new tasks get its tags
+    Context {
+        #[command(subcommand)]
+        action: ContextAction,
+    },
     /// Launch TUI mode
     Tui,
     /// Show data storage location
     Info,
-    /// Restore from backup file
-    Restore,
-}
\ No newline at end of file
+    /// Restore tasks from a backup generation
+    Restore {
+        /// Backup generation (1 = newest, see `todo backup list`)
+        #[arg(default_value = "1")]
+        generation: usize,
+    },
+    /// Undo the last change(s)
+    Undo {
+        /// Number of changes to undo
+        #[arg(default_value = "1")]
+        steps: usize,
+    },
+    /// Redo the last undone change(s)
+    Redo {
+        /// Number of changes to redo
+        #[arg(default_value = "1")]
+        steps: usize,
+    },
+    /// Show the undo/redo history
+    History,
+    /// Inspect backup generations
+    Backup {
+        #[command(subcommand)]
+        action: BackupAction,
+    },
+    /// Show pending/done counts per project
+    Projects,
+    /// Show how many tasks use each tag
+    Tags,
+    /// Manage tags
+    Tag {
+        #[command(subcommand)]
+        action: TagAction,
+    },
+    /// Copy all tasks to another storage backend and switch to it
+    Migrate {
+        /// Backend to migrate to
+        #[arg(long, value_enum)]
+        to: BackendKind,
+    },
+}
+#[derive(Subcommand)]
+pub enum BackupAction {
+    /// List backup generations (1 = newest)
+    List,
+    /// Show the tasks stored in a generation
+    Show { generation: usize },
+    /// Compare a generation with the current tasks
+    Diff { generation: usize },
+}
+
+#[derive(Subcommand)]
+pub enum BulkAction {
+    /// Mark matching tasks done
+    Done { #[arg(required = true, allow_hyphen_values = true, value_name = "FILTER")] filter: Vec<String> },
+    /// Mark matching tasks as in progress
+    Start { #[arg(required = true, allow_hyphen_values = true, value_name = "FILTER")] filter: Vec<String> },
+    /// Put matching tasks off without a date
+    Defer { #[arg(required = true, allow_hyphen_values = true, value_name = "FILTER")] filter: Vec<String> },
+    /// Cancel matching tasks
+    Cancel { #[arg(required = true, allow_hyphen_values = true, value_name = "FILTER")] filter: Vec<String> },
+    /// Move matching tasks back to pending
+    Reopen { #[arg(required = true, allow_hyphen_values = true, value_name = "FILTER")] filter: Vec<String> },
+    /// Move matching tasks to removed storage (their other subtasks become top-level tasks)
+    Delete { #[arg(required = true, allow_hyphen_values = true, value_name = "FILTER")] filter: Vec<String> },
+    /// Set the priority of matching tasks (H, M, L, 1-5 or "none")
+    Priority {
+        level: String,
+        #[arg(required = true, allow_hyphen_values = true, value_name = "FILTER")]
+        filter: Vec<String>,
+    },
+    /// Add (+tag) or remove (-tag) a tag on matching tasks
+    Tag {
+        #[arg(allow_hyphen_values = true, value_name = "+TAG|-TAG")]
+        change: String,
+        #[arg(required = true, allow_hyphen_values = true, value_name = "FILTER")]
+        filter: Vec<String>,
+    },
+    /// Move matching tasks to a project ("none" to clear)
+    Project {
+        project: String,
+        #[arg(required = true, allow_hyphen_values = true, value_name = "FILTER")]
+        filter: Vec<String>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum QueryAction {
+    /// Save a filter under a name (replaces a query with the same name)
+    Save {
+        name: String,
+        #[arg(required = true, allow_hyphen_values = true, value_name = "FILTER")]
+        filter: Vec<String>,
+    },
+    /// List the tasks matching a saved query, optionally narrowed further
+    Run {
+        name: String,
+        #[arg(allow_hyphen_values = true, value_name = "FILTER")]
+        filter: Vec<String>,
+    },
+    /// Show saved queries
+    List,
+    /// Forget a saved query
+    Delete { name: String },
+}
+
+#[derive(Subcommand)]
+pub enum ContextAction {
+    /// Make a saved query the context; with a filter, save it under that name first
+    Set {
+        name: String,
+        #[arg(allow_hyphen_values = true, value_name = "FILTER")]
+        filter: Vec<String>,
+    },
+    /// Stop applying a context
+    #[command(visible_alias = "none")]
+    Clear,
+    /// Show the active context
+    Show,
+}
+
+#[derive(Subcommand)]
+pub enum TagAction {
+    /// Rename a tag on all tasks, including removed ones
+    Rename { old: String, new: String },
+}
@@ -0,0 +1,79 @@
+use chrono::{DateTime, Local, NaiveDate, TimeZone};
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use crate::task::TaskError;
+
+// Source of the current local time. Everything that needs "today" asks the active clock,
+// so tests (and embedders) can pin the date with `set_clock`.
+pub trait Clock {
+    fn now(&self) -> Result<DateTime<Local>, TaskError>;
+}
+
+// The system clock in the local timezone
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Result<DateTime<Local>, TaskError> {
+        // A clock set before 1970 is broken rather than "early"
+        SystemTime::now().duration_since(UNIX_EPOCH)
+            .map_err(|_| TaskError::ClockUnavailable("system time is before 1970-01-01".to_string()))?;
+        Ok(Local::now())
+    }
+}
+
+// A clock that always reports the same instant
+#[derive(Debug, Clone, Copy)]
+pub struct FixedClock(pub DateTime<Local>);
+
+impl FixedClock {
+    // Noon local time on the given date, so the date survives small offsets either way
+    pub fn on(date: NaiveDate) -> FixedClock {
+        let noon = date.and_hms_opt(12, 0, 0).unwrap_or_default();
+        FixedClock(Local.from_local_datetime(&noon).earliest().unwrap_or_else(|| Local.from_utc_datetime(&noon)))
+    }
+}
+
+impl Clock for FixedClock {
+    fn now(&self) -> Result<DateTime<Local>, TaskError> {
+        Ok(self.0)
+    }
+}
+
+thread_local! {
+    // Clock override for the current thread; None uses SystemClock
+    static CLOCK: RefCell<Option<Rc<dyn Clock>>> = const { RefCell::new(None) };
+}
+
+// Restores the previous clock when dropped
+#[must_use = "the clock is reset as soon as the guard is dropped"]
+pub struct ClockGuard {
+    previous: Option<Rc<dyn Clock>>,
+}
+
+impl Drop for ClockGuard {
+    fn drop(&mut self) {
+        let previous = self.previous.take();
+        CLOCK.with(|clock| *clock.borrow_mut() = previous);
+    }
+}
+
+// Use `clock` on this thread until the returned guard is dropped
+pub fn set_clock(clock: impl Clock + 'static) -> ClockGuard {
+    let previous = CLOCK.with(|current| current.borrow_mut().replace(Rc::new(clock)));
+    ClockGuard { previous }
+}
+
+// Current local time from the active clock
+pub fn now() -> Result<DateTime<Local>, TaskError> {
+    match CLOCK.with(|clock| clock.borrow().clone()) {
+        Some(clock) => clock.now(),
+        None => SystemClock.now(),
+    }
+}
+
+// Today's local date from the active clock
+pub fn today() -> Result<NaiveDate, TaskError> {
+    Ok(now()?.date_naive())
+}
@@ -1,20 +1,57 @@
 use colored::*;
-use crate::task::{load_tasks, save_tasks_safe, Task, get_data_location, get_today, get_date_with_offset, validate_date, load_removed_tasks, add_to_removed};
+use crate::config::{BackendKind, Config};
+use crate::dates::{DueSpec, days_from_today, resolve_date, resolve_due};
+use crate::journal::Operation;
+use crate::recur::{RecurRule, Recurrence, spawn_next};
+use crate::schema::encode_tasks;
+use crate::store::{StoreLock, TaskStore, open_backend};
+use crate::query::Query;
+use crate::saved::validate_query_name;
+use crate::view::{GroupBy, ListView, LIST_DEFAULTS};
+use crate::uda::{UdaFilter, find_def};
+use crate::task::{DateField, DueState, Priority, Status, Task, TaskError, TaskRef, TimestampField, ChildPolicy, add_dependency, extract_tags, is_blocked, open_dependencies, open_dependents, normalize_project, normalize_tag, project_paths, remove_with_children, set_done_with_children, set_status_with_children, subtask_progress, tree_order, format_timestamp, timestamp_date, get_today, get_date_with_offset, validate_date};
 
-// Helper function to validate task ID exists
-fn validate_task_id(id: u32, tasks: &[Task]) -> Result<usize, String> {
-    if id == 0 {
+// Helper function to find a task by short ID or UUID
+fn validate_task_id(reference: &TaskRef, tasks: &[Task]) -> Result<usize, String> {
+    if *reference == TaskRef::Short(0) {
         return Err("Task ID cannot be 0".to_string());
     }
     
-    match tasks.iter().position(|t| t.id == id) {
-        Some(index) => Ok(index),
-        None => Err(format!("Task {} not found", id)),
+    let mut matches = tasks.iter().enumerate().filter(|(_, t)| t.matches(reference));
+    match (matches.next(), matches.next()) {
+        (Some((index, _)), None) => Ok(index),
+        (Some(_), Some(_)) => Err(format!("UUID prefix {} matches more than one task", reference)),
+        (None, _) => Err(format!("Task {} not found", reference)),
     }
 }
 
-// Helper function to format date with calendar emoji and month abbreviation
-fn format_date_with_emoji(date: &str) -> String {
+// Helper function to lock the store for a read-modify-write cycle
+fn lock_store(store: &TaskStore) -> Option<StoreLock> {
+    match store.lock() {
+        Ok(lock) => Some(lock),
+        Err(err) => {
+            println!("{} {}", "❌".red(), err);
+            None
+        }
+    }
+}
+
+// Helper function to parse the filter of the active context, if one is set
+fn active_context(store: &TaskStore) -> Result<Option<Query>, String> {
+    let saved = store.saved_queries().map_err(|err| err.to_string())?;
+    saved.context_query(&store.config().udas).map_err(|err| err.to_string())
+}
+
+// Helper function to describe a task on one line without colors
+fn task_summary(task: &Task) -> String {
+    let status = task.status.emoji();
+    let due_str = DueSpec::of(task).map_or("No date".to_string(), |due| due.to_string());
+    format!("{} [{}] {} ({})", task.id, status, task.text, due_str)
+}
+
+// Helper function to format date with calendar emoji and month abbreviation,
+// followed by the due time (and its timezone when it is not local time)
+fn format_date_with_emoji(date: &str, time: Option<&str>, tz: Option<&str>) -> String {
     // Extract month and day from date (YYYY-MM-DD format)
     let parts: Vec<&str> = date.split('-').collect();
     let month = parts.get(1).unwrap_or(&"01");
@@ -22,18 +59,125 @@ fn format_date_with_emoji(date: &str) -> String {
     let day_num: u32 = day.parse().unwrap_or(1);
     
     // Month abbreviations
-    let month_emoji = match month.as_ref() {
+    let month_emoji = match *month {
         "01" => "Jan", "02" => "Feb", "03" => "Mar", "04" => "Apr",
         "05" => "May", "06" => "Jun", "07" => "Jul", "08" => "Aug",
         "09" => "Sep", "10" => "Oct", "11" => "Nov", "12" => "Dec",
         _ => "???",
     };
 
-    format!(" {}/{}", day_num.to_string().bright_yellow().bold(), month_emoji.bright_yellow().bold())
+    let time_info = match (time, tz) {
+        (Some(time), Some(tz)) => format!(" {} {}", time.bright_yellow().bold(), tz.bright_black()),
+        (Some(time), None) => format!(" {}", time.bright_yellow().bold()),
+        _ => String::new(),
+    };
+    format!(" {}/{}{}", day_num.to_string().bright_yellow().bold(), month_emoji.bright_yellow().bold(), time_info)
+}
+
+// Options for list_tasks
+#[derive(Debug, Clone, Default)]
+pub struct ListOptions {
+    // Include completed tasks
+    pub all: bool,
+    // Only tasks due today
+    pub today: bool,
+    // Sort order like "due,-priority,id" (see view.rs); defaults to config.json, then urgency
+    pub sort: Option<String>,
+    // How to group the listing; defaults to config.json, then month of the due date
+    pub group_by: Option<GroupBy>,
+    // Only tasks created/modified/completed on or after these dates (YYYY-MM-DD)
+    pub created_since: Option<String>,
+    pub modified_since: Option<String>,
+    pub completed_since: Option<String>,
+    // Filter expression words, e.g. ["+work", "pri>=M", "due.before:friday"] (see query.rs)
+    pub filter: Vec<String>,
+    // Only tasks in this project or its subprojects
+    pub project: Option<String>,
+    // Hide tasks that are blocked by open dependencies
+    pub ready: bool,
+    // Include tasks whose wait date has not come yet (always included with `all`)
+    pub waiting: bool,
+    // User-defined attribute filters like "estimate>=3" or "customer=acme"
+    pub attr_filters: Vec<String>,
+}
+
+// Sort and grouping options of search and removed
+#[derive(Debug, Clone, Default)]
+pub struct ViewOptions {
+    pub sort: Option<String>,
+    pub group_by: Option<GroupBy>,
+}
+
+// Resolve the sort and grouping of a command-line listing against the list defaults in config.json
+fn list_view(store: &TaskStore, sort: Option<&str>, group_by: Option<GroupBy>) -> Result<ListView, TaskError> {
+    let config = store.config();
+    config.list.resolve(sort, group_by, LIST_DEFAULTS, &config.udas)
+}
+
+impl ListOptions {
+    // True when any timestamp, tag or readiness filter is set
+    fn has_filters(&self) -> bool {
+        self.created_since.is_some() || self.modified_since.is_some() || self.completed_since.is_some()
+            || !self.filter.is_empty() || self.project.is_some() || self.ready || !self.attr_filters.is_empty()
+    }
+}
+
+// Helper function to show user-defined attributes as " name=value" columns
+fn attribute_list(task: &Task) -> String {
+    task.udas.iter().map(|(name, value)| format!(" {}", format!("{}={}", name, value).bright_black())).collect()
+}
+
+// Helper function to show a coloured priority marker
+fn priority_marker(priority: Priority) -> ColoredString {
+    let marker = format!("({})", priority);
+    match priority {
+        Priority::High => marker.red().bold(),
+        Priority::Medium => marker.yellow().bold(),
+        Priority::Low => marker.blue(),
+    }
+}
+
+// Helper function to show a task's tags after its text
+fn tag_list(task: &Task) -> String {
+    task.tags.iter().map(|tag| format!(" {}", format!("+{}", tag).magenta())).collect()
+}
+
+// Short IDs of tasks, comma separated
+fn id_list(tasks: &[&Task]) -> String {
+    tasks.iter().map(|task| task.id.to_string()).collect::<Vec<_>>().join(", ")
+}
+
+// " [blocked by 3, 4]" for open tasks with open dependencies and " [blocked: reason]" for
+// tasks marked as blocked, empty otherwise
+fn blocked_marker(all: &[Task], task: &Task) -> String {
+    if task.is_closed() {
+        return String::new();
+    }
+    let mut marker = String::new();
+    let open = open_dependencies(all, task);
+    if !open.is_empty() {
+        marker.push_str(&format!(" {}", format!("[blocked by {}]", id_list(&open)).red()));
+    }
+    if let Some(reason) = task.block_reason.as_deref().filter(|_| task.status == Status::Blocked) {
+        marker.push_str(&format!(" {}", format!("[blocked: {}]", reason).red()));
+    }
+    marker
+}
+
+// " 💤 until 2025-11-10" for waiting tasks, " ⏳ from 2025-11-10" for tasks scheduled later
+fn hold_marker(task: &Task, today: &str) -> String {
+    if task.is_waiting(today) {
+        format!(" {}", format!("💤 until {}", task.wait.as_deref().unwrap_or_default()).bright_black())
+    } else if !task.is_closed() && task.scheduled.as_deref().is_some_and(|scheduled| scheduled > today) {
+        format!(" {}", format!("⏳ from {}", task.scheduled.as_deref().unwrap_or_default()).bright_black())
+    } else {
+        String::new()
+    }
 }
 
-// Helper function to display a list of tasks grouped by month
-fn display_task_list(tasks: &[&Task], header: &str, dimmed: bool, header_color: &str) {
+// Helper function to display a list of tasks sorted and grouped as `view` says, with
+// subtasks under their parent. `all` is the full list the tasks came from, used for subtask roll-ups.
+fn display_task_list(tasks: &[&Task], all: &[Task], header: &str, dimmed: bool, header_color: &str, view: &ListView) {
     if tasks.is_empty() {
         return;
     }
@@ -45,96 +189,93 @@ fn display_task_list(tasks: &[&Task], header: &str, dimmed: bool, header_color:
         _ => println!("{}", header.blue().bold()), // default to blue
     }
     
-    // Group tasks by year-month and sort
+    let now = match crate::clock::now() {
+        Ok(now) => now,
+        Err(err) => {
+            println!("{} {}", "❌".red(), err);
+            return;
+        }
+    };
+    let today = now.format("%Y-%m-%d").to_string();
+
+    // Sort tasks, keeping subtasks right after their parent
+    let mut sorted = tasks.to_vec();
+    view.sort.sort(&mut sorted, &today);
+
+    // Group tasks by their top-level task; a task with several tags is listed under each
     use std::collections::BTreeMap;
-    let mut grouped_tasks: BTreeMap<String, Vec<&Task>> = BTreeMap::new();
-    
-    for task in tasks {
-        if let Some(due_date) = &task.due_date {
-            let parts: Vec<&str> = due_date.split('-').collect();
-            let year = parts.get(0).unwrap_or(&"0000");
-            let month = parts.get(1).unwrap_or(&"00");
-            
-            let group_key = format!("{}-{}", year, month); // For sorting
-            
-            grouped_tasks.entry(group_key).or_insert_with(Vec::new).push(*task);
-        } else {
-            // Tasks without dates go to a special group
-            grouped_tasks.entry("0000-00".to_string()).or_insert_with(Vec::new).push(*task);
+    let mut grouped_tasks: BTreeMap<String, Vec<(&Task, usize)>> = BTreeMap::new();
+    let mut labels: BTreeMap<String, String> = BTreeMap::new();
+    let mut groups = Vec::new();
+    for (task, depth) in tree_order(&sorted) {
+        if depth == 0 {
+            groups = view.group_by.groups(task);
+        }
+        for (key, label) in &groups {
+            labels.entry(key.clone()).or_insert_with(|| label.clone());
+            grouped_tasks.entry(key.clone()).or_default().push((task, depth));
         }
     }
-    
+    let stamp = view.sort.timestamp();
+
     // Display each group
-    for (group_key, mut group_tasks) in grouped_tasks {
-        // Sort tasks within each group by due date, then by id
-        group_tasks.sort_by(|a, b| {
-            match (&a.due_date, &b.due_date) {
-                (Some(date_a), Some(date_b)) => date_a.cmp(date_b),
-                (Some(_), None) => std::cmp::Ordering::Less,
-                (None, Some(_)) => std::cmp::Ordering::Greater,
-                (None, None) => a.id.cmp(&b.id),
+    for (group_key, group_tasks) in grouped_tasks {
+        let label = &labels[&group_key];
+        // Display group header; tasks missing the grouped field have an empty key
+        match view.group_by {
+            GroupBy::None => {}
+            _ if group_key.is_empty() => println!("\n{}", label.bright_black().bold()),
+            GroupBy::Month => {
+                println!("╔══════════════════════════════════╗");
+                println!("║            {}              ║", label.bright_cyan().bold());
+                println!("╚══════════════════════════════════╝");
             }
-        });
-        
-        // Display group header
-        if group_key == "0000-00" {
-            println!("\n{}", "[No Due Date]".bright_black().bold());
-        } else {
-            let parts: Vec<&str> = group_key.split('-').collect();
-            let year = parts.get(0).unwrap_or(&"0000");
-            let month = parts.get(1).unwrap_or(&"00");
-            let month_name = match month.as_ref() {
-                "01" => "Jan", "02" => "Feb", "03" => "Mar", "04" => "Apr",
-                "05" => "May", "06" => "Jun", "07" => "Jul", "08" => "Aug",
-                "09" => "Sep", "10" => "Oct", "11" => "Nov", "12" => "Dec",
-                _ => "???",
-            };
-            println!("╔══════════════════════════════════╗");
-            println!("║          {}", format!("  {} {}              ║", month_name.bright_cyan().bold(), year.bright_cyan().bold()));
-            println!("╚══════════════════════════════════╝");
+            _ => println!("\n{}", label.bright_cyan().bold()),
         }
-        
+
         // Display tasks in this group
-        for task in group_tasks {
-            let status = if task.done { "✅" } else { "⬜" };
+        for (task, depth) in group_tasks {
+            let status = task.status.emoji();
             let due_info = match &task.due_date {
-                Some(date) => format_date_with_emoji(date),
+                Some(date) => format_date_with_emoji(date, task.due_time.as_deref(), task.due_tz.as_deref()),
                 None => String::new(),
             };
             
-            // Check task status for color coding
-            let today = crate::task::get_today();
-            let task_text = if !task.done {
-                if let Some(due_date) = &task.due_date {
-                    if due_date < &today {
-                        // Overdue tasks - red
-                        task.text.red().to_string()
-                    } else if due_date > &today {
-                        // Future tasks - check if tomorrow or later
-                        let tomorrow = crate::task::get_date_with_offset(1);
-                        if due_date == &tomorrow {
-                            // Tomorrow tasks - light green
-                            task.text.bright_blue().to_string()
-                        } else {
-                            // Tasks after tomorrow - green
-                            task.text.green().to_string()
-                        }
-                    } else {
-                        // Today's tasks - normal color
-                        task.text.bold().to_string()
-                    }
-                } else {
+            // Check task status for color coding (tasks with a due time are overdue once it passes)
+            let task_text = if !task.is_closed() {
+                match task.due_state(now) {
+                    // Overdue tasks - red
+                    Some(DueState::Overdue) => task.text.red().to_string(),
+                    // Today's tasks - bold
+                    Some(DueState::Today) => task.text.bold().to_string(),
+                    // Tomorrow tasks - light blue
+                    Some(DueState::Tomorrow) => task.text.bright_blue().to_string(),
+                    // Tasks after tomorrow - green
+                    Some(DueState::Later) => task.text.green().to_string(),
                     // No due date - normal color
-                    task.text.to_string()
+                    None => task.text.to_string(),
                 }
             } else {
                 // Completed tasks - normal color
                 task.text.to_string()
             };
             
-            let line = format!("{} [{}] {}{}", task.id.to_string().bright_green(), status, task_text, due_info);
+            let priority_info = task.priority.map_or(String::new(), |priority| format!("{} ", priority_marker(priority)));
+            let progress_info = subtask_progress(all, task.uuid)
+                .map_or(String::new(), |(done, total)| format!(" {}", format!("[{}/{} done]", done, total).cyan()));
+            let blocked_info = blocked_marker(all, task);
+            let recur_info = if task.recur.is_some() { " 🔁" } else { "" };
+            let notes_info = if task.notes.is_some() { " 🗒️" } else { "" };
+            let hold_info = hold_marker(task, &today);
+            let stamp_info = stamp.map_or(String::new(), |field| {
+                let at = field.of(task).map_or("-".to_string(), |at| format_timestamp(&at));
+                format!(" {}", format!("🕒 {}", at).bright_black())
+            });
+            let indent = if depth > 0 { format!("{}└ ", "  ".repeat(depth - 1)) } else { String::new() };
+            let line = format!("{}{} [{}] {}{}{}{}{}{}{}{}{}{}{}", indent, task.id.to_string().bright_green(), status, priority_info,
+                task_text, recur_info, notes_info, tag_list(task), attribute_list(task), progress_info, blocked_info, hold_info, due_info, stamp_info);
             
-            if task.done {
+            if task.is_closed() {
                 println!("{}", line.strikethrough().dimmed());
             } else if dimmed {
                 println!("{}", line.dimmed());
@@ -145,8 +286,49 @@ fn display_task_list(tasks: &[&Task], header: &str, dimmed: bool, header_color:
     }
 }
 
+// Options for add_task_with
+#[derive(Debug, Clone, Default)]
+pub struct AddOptions {
+    // Due date relative to today
+    pub days_offset: i32,
+    // Due date expression like "fri" or "in 2 weeks"; takes precedence over days_offset
+    pub due: Option<String>,
+    pub priority: Option<Priority>,
+    pub project: Option<String>,
+    // Make the new task a subtask of this one
+    pub parent: Option<TaskRef>,
+    // Scheduled and wait date expressions
+    pub scheduled: Option<String>,
+    pub wait: Option<String>,
+}
+
+// "due tomorrow: 2025-11-08" style description of a due date
+fn relative_due(due: &DueSpec, days_offset: i64) -> String {
+    let date = due.to_string();
+    match days_offset {
+        0 => format!("due today: {}", date.yellow()),
+        1 => format!("due tomorrow: {}", date.yellow()),
+        -1 => format!("due yesterday: {}", date.yellow()),
+        days if days > 0 => format!("due in {} days: {}", days, date.yellow()),
+        days => format!("due {} days ago: {}", days.abs(), date.yellow()),
+    }
+}
+
 // Add new task
-pub fn add_task(text: String, days_offset: i32) {
+pub fn add_task(store: &TaskStore, text: String, days_offset: i32) {
+    add_task_with(store, text, AddOptions { days_offset, ..AddOptions::default() });
+}
+
+// Add new task with extra attributes
+pub fn add_task_with(store: &TaskStore, text: String, options: AddOptions) {
+    let (text, tags) = extract_tags(&text);
+    let project = match options.project.as_deref().map(normalize_project).transpose() {
+        Ok(project) => project,
+        Err(err) => {
+            println!("{} {}", "❌".red(), err);
+            return;
+        }
+    };
     // Validate input text
     if text.trim().is_empty() {
         println!("{} Task text cannot be empty!", "❌".red());
@@ -158,101 +340,337 @@ pub fn add_task(text: String, days_offset: i32) {
         return;
     }
     
-    let mut tasks = load_tasks();
-    let id = tasks.iter().map(|t| t.id).max().unwrap_or(0) + 1;
-    let due_date = get_date_with_offset(days_offset);
-    
-    tasks.push(Task { 
-        id, 
-        text: text.clone(), 
-        done: false, 
-        due_date: Some(due_date.clone()) 
-    });
+    let due = match &options.due {
+        Some(expr) => resolve_due(expr),
+        None => get_date_with_offset(options.days_offset).map(|date| DueSpec { date, time: None, tz: None }),
+    };
+    let (due, days_offset) = match due.and_then(|due| Ok((days_from_today(&due.date)?, due))) {
+        Ok((days_offset, due)) => (due, days_offset),
+        Err(err) => {
+            println!("{} {}", "❌".red(), err);
+            return;
+        }
+    };
+    let resolve = |expr: &Option<String>| expr.as_deref().map(resolve_date).transpose();
+    let (scheduled, wait) = match resolve(&options.scheduled).and_then(|scheduled| Ok((scheduled, resolve(&options.wait)?))) {
+        Ok(dates) => dates,
+        Err(err) => {
+            println!("{} {}", "❌".red(), err);
+            return;
+        }
+    };
     
-    save_tasks_safe(&tasks);
+    // Tasks added in a context get its tags, and its project unless they have one
+    let context = match active_context(store) {
+        Ok(context) => context,
+        Err(err) => {
+            println!("{} {}", "❌".red(), err);
+            return;
+        }
+    };
+    let mut tags = tags;
+    if let Some(context) = &context {
+        tags.extend(context.implied_tags());
+    }
+
+    let Some(_lock) = lock_store(store) else { return };
+    let mut tasks = store.load();
+    let before = tasks.clone();
+    let id = store.next_id(&tasks);
+
+    // Subtasks inherit the parent's project unless one is given
+    let mut project = project;
+    let parent = match &options.parent {
+        Some(reference) => match validate_task_id(reference, &tasks) {
+            Ok(index) => {
+                project = project.or_else(|| tasks[index].project.clone());
+                Some(&tasks[index])
+            }
+            Err(err) => {
+                println!("{} Parent {}", "❌".red(), err.to_lowercase());
+                return;
+            }
+        },
+        None => None,
+    };
+    let parent_info = parent.map_or(String::new(), |parent| format!(" under {}", parent.id.to_string().cyan()));
+    let parent = parent.map(|parent| parent.uuid);
+    let project = project.or_else(|| context.as_ref().and_then(Query::implied_project));
     
-    let date_info = if days_offset == 0 {
-        format!(" (due today: {})", due_date.yellow())
-    } else if days_offset == 1 {
-        format!(" (due tomorrow: {})", due_date.yellow())
-    } else if days_offset > 0 {
-        format!(" (due in {} days: {})", days_offset, due_date.yellow())
-    } else if days_offset == -1 {
-        format!(" (due yesterday: {})", due_date.yellow())
-    } else {
-        format!(" (due {} days ago: {})", days_offset.abs(), due_date.yellow())
+    let date_info = format!(" ({})", relative_due(&due, days_offset));
+    let mut task = Task {
+        priority: options.priority, tags, project: project.clone(), parent, scheduled, wait,
+        ..Task::new(id, text.clone(), None)
     };
+    due.apply(&mut task);
+    tasks.push(task);
+    
+    store.save_recorded_safe(&tasks, Operation::new(format!("add {}: {}", id, text), &before, &tasks));
     
-    println!("{} {}{}", "✅ Added!".green(), id.to_string().cyan(), date_info);
+    let priority_info = options.priority.map_or(String::new(), |priority| format!(" {}", priority_marker(priority)));
+    let project_info = project.map_or(String::new(), |project| format!(" in {}", project.cyan()));
+    println!("{} {}{}{}{}{}", "✅ Added!".green(), id.to_string().cyan(), date_info, priority_info, project_info, parent_info);
 }
 
 // Show all tasks
-pub fn list_tasks(show_all: bool, today_only: bool) {
-    let tasks = load_tasks();
+pub fn list_tasks(store: &TaskStore, options: &ListOptions) {
+    let show_all = options.all;
+    let today_only = options.today;
+    for since in [&options.created_since, &options.modified_since, &options.completed_since].into_iter().flatten() {
+        if let Err(err) = validate_date(since) {
+            println!("{} {}", "❌".red(), err);
+            return;
+        }
+    }
+
+    let udas = &store.config().udas;
+    let query = match Query::parse(&options.filter.join(" "), udas) {
+        Ok(query) => query,
+        Err(err) => {
+            println!("{} {}", "❌".red(), err);
+            return;
+        }
+    };
+    let context = match active_context(store) {
+        Ok(context) => context,
+        Err(err) => {
+            println!("{} {}", "❌".red(), err);
+            return;
+        }
+    };
+    let in_context = context.is_some();
+    let query = match context {
+        Some(context) => context.and(query),
+        None => query,
+    };
+    // Closed and waiting tasks stay hidden unless the filter asks about them
+    let show_closed = show_all || options.completed_since.is_some() || query.mentions("status") || query.mentions("completed");
+    let show_waiting = show_all || options.waiting || query.mentions("wait");
+
+    let project = match options.project.as_deref().map(normalize_project).transpose() {
+        Ok(project) => project,
+        Err(err) => {
+            println!("{} {}", "❌".red(), err);
+            return;
+        }
+    };
+
+    let attr_filters = match options.attr_filters.iter().map(|filter| UdaFilter::parse(filter, udas)).collect::<Result<Vec<_>, _>>() {
+        Ok(filters) => filters,
+        Err(err) => {
+            println!("{} {}", "❌".red(), err);
+            return;
+        }
+    };
+    let view = match list_view(store, options.sort.as_deref(), options.group_by) {
+        Ok(view) => view,
+        Err(err) => {
+            println!("{} {}", "❌".red(), err);
+            return;
+        }
+    };
+
+    let tasks = store.load();
     if tasks.is_empty() {
         println!("{}", "📭 No tasks! Add some tasks.".yellow());
         return;
     }
 
-    let today = get_today();
+    let today = match get_today() {
+        Ok(today) => today,
+        Err(err) => {
+            println!("{} {}", "❌".red(), err);
+            return;
+        }
+    };
 
     // Filter tasks based on parameters
     let mut filtered_tasks: Vec<&Task> = if today_only {
         // Show today's tasks (with due_date = today)
         tasks.iter()
-            .filter(|task| task.due_date.as_ref() == Some(&today))
+            .filter(|task| task.local_due_date().as_ref() == Some(&today))
             .collect()
-    } else if show_all {
-        // Show all tasks
+    } else if show_closed {
+        // Show all tasks (completed tasks are needed to filter by completion or status)
         tasks.iter().collect()
     } else {
         // Show only pending tasks
-        tasks.iter().filter(|task| !task.done).collect()
+        tasks.iter().filter(|task| !task.is_closed()).collect()
     };
 
+    // Keep tasks whose timestamps fall on or after the given dates
+    let since_filters = [
+        (TimestampField::Created, &options.created_since),
+        (TimestampField::Modified, &options.modified_since),
+        (TimestampField::Completed, &options.completed_since),
+    ];
+    for (field, since) in since_filters {
+        if let Some(since) = since {
+            filtered_tasks.retain(|task| field.of(task).is_some_and(|at| timestamp_date(&at) >= *since));
+        }
+    }
+    filtered_tasks.retain(|task| query.matches(task, &tasks));
+    filtered_tasks.retain(|task| attr_filters.iter().all(|filter| filter.matches(task, udas)));
+    if let Some(project) = &project {
+        filtered_tasks.retain(|task| task.in_project(project));
+    }
+    if options.ready {
+        filtered_tasks.retain(|task| !is_blocked(&tasks, task) && task.status != Status::Blocked);
+    }
+    let before_waiting = filtered_tasks.len();
+    if !show_waiting {
+        filtered_tasks.retain(|task| !task.is_waiting(&today));
+    }
+    let waiting = before_waiting - filtered_tasks.len();
+
     if filtered_tasks.is_empty() {
         if today_only {
             println!("{} No tasks due today ({})!", "📅".yellow(), today.cyan());
-        } else if show_all {
+        } else if waiting > 0 {
+            println!("{} Nothing to do right now; {} task(s) waiting (see 'todo list --waiting')", "💤".yellow(), waiting);
+        } else if show_all || options.has_filters() {
             println!("{}", "📭 No tasks found.".yellow());
         } else {
             println!("{}", "🎉 All tasks completed! Use 'todo list -a' to see completed tasks.".green());
         }
+        if in_context {
+            println!("   {}", "(a context is active; 'todo context clear' shows all tasks)".dimmed());
+        }
         return;
     }
 
-    // Sort tasks: undone first, then done (for both -a and --today)
-    if show_all || today_only {
-        filtered_tasks.sort_by_key(|task| task.done);
-    } else {
-        // Sort pending tasks by due date
-        filtered_tasks.sort_by(|a, b| {
-            match (&a.due_date, &b.due_date) {
-                (Some(date_a), Some(date_b)) => date_a.cmp(date_b),
-                (Some(_), None) => std::cmp::Ordering::Less,
-                (None, Some(_)) => std::cmp::Ordering::Greater,
-                (None, None) => a.id.cmp(&b.id),
-            }
-        });
-    }
-
     let header = if today_only {
         format!("📅 Today's Tasks ({}):", today)
+    } else if let Some(project) = &project {
+        format!("📁 Project {}:", project)
     } else if show_all {
         "📋 Your Complete Task List:".to_string()
+    } else if options.ready {
+        "🚦 Ready Tasks:".to_string()
+    } else if options.has_filters() {
+        "📋 Matching Tasks:".to_string()
     } else {
         "📋 Your Pending Tasks:".to_string()
     };
-    
-    display_task_list(&filtered_tasks, &header, false, "blue");
+
+    // Grouping by status replaces the status sections
+    if view.group_by == GroupBy::Status {
+        display_task_list(&filtered_tasks, &tasks, &header, false, "blue", &view);
+        return;
+    }
+
+    display_status_sections(&filtered_tasks, &tasks, &header, &today, &view);
 }
 
-// Toggle task completion
-pub fn toggle_task(id: u32) {
-    let mut tasks = load_tasks();
-    
-    // Validate task ID
-    let index = match validate_task_id(id, &tasks) {
+// Display tasks in one section per status, each sorted and grouped as `view` says. Pending
+// tasks whose scheduled date has come get their own section; the rest go under `header`.
+fn display_status_sections(tasks: &[&Task], all: &[Task], header: &str, today: &str, view: &ListView) {
+    let in_status = |status: Status| -> Vec<&Task> { tasks.iter().copied().filter(|task| task.status == status).collect() };
+    let (can_start, pending): (Vec<&Task>, Vec<&Task>) = in_status(Status::Pending).into_iter().partition(|task| task.can_start(today));
+
+    let sections = [
+        (in_status(Status::Started), "🚧 In progress:", false, "blue"),
+        (can_start, "▶️ Can start:", false, "blue"),
+        (pending, header, false, "blue"),
+        (in_status(Status::Blocked), "⛔ Blocked:", false, "red"),
+        (in_status(Status::Deferred), "⏸️ Deferred:", true, "blue"),
+        (in_status(Status::Done), "✅ Done:", false, "blue"),
+        (in_status(Status::Cancelled), "🚫 Cancelled:", false, "blue"),
+    ];
+    let mut first = true;
+    for (section, title, dimmed, color) in sections.iter().filter(|(section, ..)| !section.is_empty()) {
+        if !first {
+            println!();
+        }
+        first = false;
+        display_task_list(section, all, title, *dimmed, color, view);
+    }
+}
+
+// Show every recorded detail of one task (live or removed)
+pub fn show_task(store: &TaskStore, id: impl Into<TaskRef>) {
+    let reference = id.into();
+    let tasks = store.load();
+
+    let (task, removed) = match validate_task_id(&reference, &tasks) {
+        Ok(index) => (tasks[index].clone(), false),
+        Err(err) => {
+            // Fall back to the most recently removed match
+            match store.load_removed().into_iter().rev().find(|task| task.matches(&reference)) {
+                Some(task) => (task, true),
+                None => {
+                    println!("{} {}", "❌".red(), err);
+                    return;
+                }
+            }
+        }
+    };
+
+    let status = format!("{} {}", task.status.emoji(), task.status);
+    let status = match task.status {
+        Status::Done => status.green(),
+        Status::Blocked | Status::Cancelled => status.red(),
+        Status::Started => status.cyan(),
+        _ => status.yellow(),
+    };
+    let timestamp = |at: Option<chrono::DateTime<chrono::Utc>>| at.map_or("-".to_string(), |at| format_timestamp(&at));
+
+    println!("{} {} {}", "📝".blue(), task.id.to_string().bright_green(), task.text.bold());
+    if removed {
+        println!("   {}", "(removed)".red());
+    }
+    println!("   UUID:      {}", task.uuid);
+    println!("   Status:    {}", status);
+    if let Some(reason) = &task.block_reason {
+        println!("   Reason:    {}", reason);
+    }
+    println!("   Due:       {}", DueSpec::of(&task).map_or("-".to_string(), |due| due.to_string()));
+    if task.due_tz.is_some()
+        && let Some(at) = task.due_at()
+    {
+        println!("              ({} local time)", at.format("%Y-%m-%d %H:%M"));
+    }
+    println!("   Scheduled: {}", task.scheduled.as_deref().unwrap_or("-"));
+    println!("   Wait:      {}", task.wait.as_deref().unwrap_or("-"));
+    println!("   Priority:  {}", task.priority.map_or("-".to_string(), |priority| priority_marker(priority).to_string()));
+    println!("   Project:   {}", task.project.as_deref().unwrap_or("-"));
+    println!("   Tags:      {}", if task.tags.is_empty() { "-".to_string() } else { tag_list(&task).trim_start().to_string() });
+    if let Some(recur) = &task.recur {
+        println!("   Recurs:    {}", recur);
+    }
+    let dependencies: Vec<&Task> = tasks.iter().filter(|other| task.depends_on.contains(&other.uuid)).collect();
+    if !dependencies.is_empty() {
+        let state = if is_blocked(&tasks, &task) { "blocked".red() } else { "ready".green() };
+        println!("   Depends:   {} ({})", id_list(&dependencies), state);
+    }
+    let dependents: Vec<&Task> = tasks.iter().filter(|other| other.depends_on.contains(&task.uuid)).collect();
+    if !dependents.is_empty() {
+        println!("   Blocks:    {}", id_list(&dependents));
+    }
+    println!("   Created:   {}", timestamp(task.created_at));
+    println!("   Modified:  {}", timestamp(task.modified_at));
+    println!("   Completed: {}", timestamp(task.completed_at));
+    if removed {
+        println!("   Removed:   {}", timestamp(task.removed_at));
+    }
+    for (name, value) in &task.udas {
+        let label = store.config().udas.get(name).map_or(name.as_str(), |def| def.label(name));
+        println!("   {:<11}{}", format!("{}:", label), value);
+    }
+    if let Some(notes) = &task.notes {
+        println!();
+        println!("   {}", "Notes:".bold());
+        for line in notes.lines() {
+            println!("   {}", line);
+        }
+    }
+}
+
+// Edit the notes of a task in $VISUAL / $EDITOR
+pub fn edit_notes(store: &TaskStore, id: impl Into<TaskRef>) {
+    let reference = id.into();
+    let tasks = store.load();
+    let index = match validate_task_id(&reference, &tasks) {
         Ok(idx) => idx,
         Err(err) => {
             println!("{} {}", "❌".red(), err);
@@ -260,101 +678,529 @@ pub fn toggle_task(id: u32) {
         }
     };
 
-    tasks[index].done = !tasks[index].done;
-    let new_done = tasks[index].done;
+    // The store stays unlocked while the editor is open; the UUID finds the task again afterwards
+    let uuid = tasks[index].uuid;
+    let notes = match crate::editor::edit_text(tasks[index].notes.as_deref().unwrap_or("")) {
+        Ok(notes) => notes,
+        Err(err) => {
+            println!("{} {}", "❌".red(), err);
+            return;
+        }
+    };
+    set_notes(store, TaskRef::Uuid(uuid), notes);
+}
+
+// Replace the notes of a task; blank notes clear them
+pub fn set_notes(store: &TaskStore, id: impl Into<TaskRef>, notes: String) {
+    update_notes(store, id.into(), |_| notes);
+}
 
-    save_tasks_safe(&tasks);
-    println!("{} Task {} {}", "🎉".green(), id, if new_done { "completed!".green() } else { "reopened!".yellow() });
+// Add a line to the end of a task's notes
+pub fn append_notes(store: &TaskStore, id: impl Into<TaskRef>, line: String) {
+    update_notes(store, id.into(), |current| match current {
+        Some(current) => format!("{}\n{}", current, line),
+        None => line,
+    });
 }
 
-// Delete task
-pub fn delete_task(id: u32) {
-    let mut tasks = load_tasks();
-    
+fn update_notes(store: &TaskStore, reference: TaskRef, update: impl FnOnce(Option<&str>) -> String) {
+    let Some(_lock) = lock_store(store) else { return };
+    let mut tasks = store.load();
+
     // Validate task ID
-    let _index = match validate_task_id(id, &tasks) {
+    let index = match validate_task_id(&reference, &tasks) {
         Ok(idx) => idx,
         Err(err) => {
             println!("{} {}", "❌".red(), err);
             return;
         }
     };
-    
-    let old_len = tasks.len();
-    tasks.retain(|t| t.id != id);
-    
-    if tasks.len() < old_len {
-        save_tasks_safe(&tasks);
-        println!("{} Task {} deleted!", "🗑️".red(), id);
-    }
-}
+    let id = tasks[index].id;
 
-// Remove all tasks older than specified number of days ago
-pub fn remove_tasks_by_date(days_ago: i32) {
-    use std::io::{self, Write};
-    
-    let tasks = load_tasks();
-    // Calculate cutoff date: remove tasks older than this date
-    // days_ago=1 means remove tasks older than 1 day ago (2+ days old)
-    // days_ago=3 means remove tasks older than 3 days ago (4+ days old)
-    let cutoff_days = days_ago + 1;
-    let cutoff_date = get_date_with_offset(-cutoff_days);
-    
-    // Find tasks older than cutoff date (due_date < cutoff_date)
-    let matching_tasks: Vec<&Task> = tasks.iter()
-        .filter(|task| {
-            if let Some(due_date) = &task.due_date {
-                due_date < &cutoff_date
-            } else {
-                false
-            }
-        })
-        .collect();
-    
-    if matching_tasks.is_empty() {
-        let date_desc = if days_ago == 0 {
-            "older than today".to_string()
-        } else {
-            format!("older than {}", cutoff_date)
-        };
-        
-        println!("{} No tasks found {}!", "📅".yellow(), date_desc);
+    let before = tasks.clone();
+    let notes = update(tasks[index].notes.as_deref());
+    tasks[index].set_notes(&notes);
+    if tasks[index].notes == before[index].notes {
+        println!("{} Notes of task {} unchanged", "ℹ️".blue(), id);
         return;
     }
-    
-    // Show confirmation prompt
-    let date_desc = if days_ago == 0 {
-        "older than today".to_string()
-    } else if days_ago == 1 {
-        "older than 1 day ago".to_string()
+    store.save_recorded_safe(&tasks, Operation::new(format!("note {}", id), &before, &tasks));
+    if tasks[index].notes.is_some() {
+        println!("{} Notes of task {} updated!", "🗒️".green(), id);
     } else {
-        format!("older than {} days ago", days_ago)
-    };
-    
-    println!("\n{} Tasks to be removed ({}):", "🗑️".red().bold(), date_desc.cyan());
-    for task in &matching_tasks {
-        let status = if task.done { "✅" } else { "⬜" };
-        let due_str = task.due_date.as_ref().map_or("No date".to_string(), |d| d.clone());
-        let line = format!("{} [{}] {} ({})", task.id, status, task.text, due_str);
-        if task.done {
-            println!("  {}", line.strikethrough().dimmed());
-        } else {
-            println!("  {}", line);
-        }
+        println!("{} Notes of task {} cleared", "🗒️".green(), id);
     }
+}
+
+// Toggle task completion, leaving subtasks as they are
+pub fn toggle_task(store: &TaskStore, id: impl Into<TaskRef>) {
+    toggle_task_with(store, id, ChildPolicy::Orphan);
+}
+
+// Toggle task completion, applying the policy to subtasks
+pub fn toggle_task_with(store: &TaskStore, id: impl Into<TaskRef>, children: ChildPolicy) {
+    let reference = id.into();
+    let Some(_lock) = lock_store(store) else { return };
+    let mut tasks = store.load();
     
-    print!("\n{} Do you want to remove all {} task(s) {}? [y/N]: ", 
-        "❓".yellow(), matching_tasks.len(), date_desc);
-    io::stdout().flush().unwrap();
-    
-    let mut input = String::new();
-    io::stdin().read_line(&mut input).unwrap();
-    let input = input.trim().to_lowercase();
-    
-    if input == "y" || input == "yes" {
-        // Collect tasks to be removed (older than cutoff date)
-        let tasks_to_remove: Vec<Task> = tasks.iter()
-            .filter(|task| {
+    // Validate task ID
+    let index = match validate_task_id(&reference, &tasks) {
+        Ok(idx) => idx,
+        Err(err) => {
+            println!("{} {}", "❌".red(), err);
+            return;
+        }
+    };
+    let id = tasks[index].id;
+    let today = match get_today() {
+        Ok(today) => today,
+        Err(err) => {
+            println!("{} {}", "❌".red(), err);
+            return;
+        }
+    };
+
+    let before = tasks.clone();
+    let new_done = !tasks[index].is_closed();
+    let uuid = tasks[index].uuid;
+    let waiting = if new_done { open_dependents(&tasks, uuid).len() } else { 0 };
+    let open = open_dependencies(&tasks, &tasks[index]);
+    if new_done && !open.is_empty() {
+        println!("{} Task {} still depends on open task(s) {}", "⚠️".yellow(), id, id_list(&open));
+    }
+    let changed = match set_done_with_children(&mut tasks, index, new_done, children) {
+        Ok(changed) => changed,
+        Err(err) => {
+            println!("{} {}", "❌".red(), err);
+            return;
+        }
+    };
+
+    // Completing a recurring task brings up its next instance
+    let next_id = store.next_id(&tasks);
+    let spawned = if new_done { spawn_next(&mut tasks, index, next_id, &today) } else { None };
+
+    let description = format!("{} {}", if new_done { "done" } else { "reopen" }, id);
+    store.save_recorded_safe(&tasks, Operation::new(description, &before, &tasks));
+    println!("{} Task {} {}", "🎉".green(), id, if new_done { "completed!".green() } else { "reopened!".yellow() });
+    if let Some(next) = spawned {
+        let next = &tasks[next];
+        println!("   {} Next: task {} due {}", "🔁".cyan(), next.id.to_string().cyan(), next.due_date.as_deref().unwrap_or("-"));
+    } else if new_done && before[index].recur.as_ref().is_some_and(|recur| !recur.paused) {
+        println!("   {} Series ended", "🔁".cyan());
+    }
+    if changed > 0 {
+        println!("   {} subtask(s) {} too", changed, if new_done { "completed" } else { "reopened" });
+    }
+    if waiting > 0 {
+        let ready: Vec<&Task> = open_dependents(&tasks, uuid).into_iter().filter(|task| !is_blocked(&tasks, task)).collect();
+        if !ready.is_empty() {
+            println!("   {} Now ready: {}", "🚦".green(), id_list(&ready));
+        }
+    }
+}
+
+// Set user-defined attributes from "name=value" assignments; "name=" clears one
+pub fn set_attributes(store: &TaskStore, id: impl Into<TaskRef>, assignments: Vec<String>) {
+    let reference = id.into();
+    let udas = &store.config().udas;
+
+    // Check every assignment before changing anything
+    let mut changes = Vec::new();
+    for assignment in &assignments {
+        let Some((name, input)) = assignment.split_once('=') else {
+            println!("{} Expected name=value, got '{}'", "❌".red(), assignment);
+            return;
+        };
+        let name = name.trim().to_lowercase();
+        let value = match find_def(udas, &name) {
+            Ok(_) if input.trim().is_empty() => None,
+            Ok(def) => match def.parse(&name, input) {
+                Ok(value) => Some(value),
+                Err(err) => {
+                    println!("{} {}", "❌".red(), err);
+                    return;
+                }
+            },
+            Err(err) => {
+                println!("{} {}", "❌".red(), err);
+                return;
+            }
+        };
+        changes.push((name, value));
+    }
+
+    let Some(_lock) = lock_store(store) else { return };
+    let mut tasks = store.load();
+
+    // Validate task ID
+    let index = match validate_task_id(&reference, &tasks) {
+        Ok(idx) => idx,
+        Err(err) => {
+            println!("{} {}", "❌".red(), err);
+            return;
+        }
+    };
+    let id = tasks[index].id;
+
+    let before = tasks.clone();
+    let mut summary = Vec::new();
+    for (name, value) in changes {
+        match value {
+            Some(value) => {
+                summary.push(format!("{}={}", name, value));
+                tasks[index].udas.insert(name, value);
+            }
+            None => {
+                summary.push(format!("{} cleared", name));
+                tasks[index].udas.remove(&name);
+            }
+        }
+    }
+    if tasks[index].udas == before[index].udas {
+        println!("{} Task {} unchanged", "ℹ️".blue(), id);
+        return;
+    }
+    tasks[index].touch();
+    store.save_recorded_safe(&tasks, Operation::new(format!("set {}", id), &before, &tasks));
+    println!("{} Task {}: {}", "🧩".green(), id, summary.join(", "));
+}
+
+// Move a task to another status (start, block, defer, cancel or reopen). Blocking takes an
+// optional reason; cancelling an occurrence of a recurring task brings up the next one.
+pub fn set_task_status(store: &TaskStore, id: impl Into<TaskRef>, status: Status, reason: Option<String>) {
+    set_task_status_with(store, id, status, reason, ChildPolicy::Orphan);
+}
+
+// Change a task's status; closing or reopening it applies the policy to subtasks
+pub fn set_task_status_with(store: &TaskStore, id: impl Into<TaskRef>, status: Status, reason: Option<String>, children: ChildPolicy) {
+    let reference = id.into();
+    let Some(_lock) = lock_store(store) else { return };
+    let mut tasks = store.load();
+
+    // Validate task ID
+    let index = match validate_task_id(&reference, &tasks) {
+        Ok(idx) => idx,
+        Err(err) => {
+            println!("{} {}", "❌".red(), err);
+            return;
+        }
+    };
+    let id = tasks[index].id;
+    let today = match get_today() {
+        Ok(today) => today,
+        Err(err) => {
+            println!("{} {}", "❌".red(), err);
+            return;
+        }
+    };
+
+    let reason = reason.map(|reason| reason.trim().to_string()).filter(|reason| !reason.is_empty());
+    if tasks[index].status == status && (status != Status::Blocked || reason.is_none() || tasks[index].block_reason == reason) {
+        println!("{} Task {} is already {}", "ℹ️".blue(), id, status);
+        return;
+    }
+
+    let before = tasks.clone();
+    let uuid = tasks[index].uuid;
+    let was_closed = tasks[index].is_closed();
+    let open = open_dependencies(&tasks, &tasks[index]);
+    if status == Status::Done && !open.is_empty() {
+        println!("{} Task {} still depends on open task(s) {}", "⚠️".yellow(), id, id_list(&open));
+    }
+    let changed = match set_status_with_children(&mut tasks, index, status, children) {
+        Ok(changed) => changed,
+        Err(err) => {
+            println!("{} {}", "❌".red(), err);
+            return;
+        }
+    };
+    if status == Status::Blocked {
+        tasks[index].block_reason = reason.or(before[index].block_reason.clone());
+    }
+    let next_id = store.next_id(&tasks);
+    let spawned = if status.is_closed() && !was_closed { spawn_next(&mut tasks, index, next_id, &today) } else { None };
+
+    let verb = match status {
+        Status::Pending => "reopen",
+        Status::Started => "start",
+        Status::Blocked => "block",
+        Status::Deferred => "defer",
+        Status::Done => "done",
+        Status::Cancelled => "cancel",
+    };
+    store.save_recorded_safe(&tasks, Operation::new(format!("{} {}", verb, id), &before, &tasks));
+    match &tasks[index].block_reason {
+        Some(reason) => println!("{} Task {} {}: {}", status.emoji(), id, status, reason),
+        None => println!("{} Task {} {}", status.emoji(), id, status),
+    }
+    if let Some(next) = spawned {
+        let next = &tasks[next];
+        println!("   {} Next: task {} due {}", "🔁".cyan(), next.id.to_string().cyan(), next.due_date.as_deref().unwrap_or("-"));
+    } else if status.is_closed() && !was_closed && before[index].recur.as_ref().is_some_and(|recur| !recur.paused) {
+        println!("   {} Series ended", "🔁".cyan());
+    }
+    if changed > 0 {
+        println!("   {} subtask(s) {} too", changed, if status.is_closed() { status.to_string() } else { "reopened".to_string() });
+    }
+    if status.is_closed() && !was_closed {
+        let ready: Vec<&Task> = open_dependents(&tasks, uuid).into_iter().filter(|task| !is_blocked(&tasks, task)).collect();
+        if !ready.is_empty() {
+            println!("   {} Now ready: {}", "🚦".green(), id_list(&ready));
+        }
+    }
+}
+
+// Delete task, refusing while it has subtasks
+pub fn delete_task(store: &TaskStore, id: impl Into<TaskRef>) {
+    delete_task_with(store, id, ChildPolicy::Refuse);
+}
+
+// Delete task, applying the policy to subtasks
+pub fn delete_task_with(store: &TaskStore, id: impl Into<TaskRef>, children: ChildPolicy) {
+    let reference = id.into();
+    let Some(_lock) = lock_store(store) else { return };
+    let mut tasks = store.load();
+    
+    // Validate task ID
+    let index = match validate_task_id(&reference, &tasks) {
+        Ok(idx) => idx,
+        Err(err) => {
+            println!("{} {}", "❌".red(), err);
+            return;
+        }
+    };
+    
+    let before = tasks.clone();
+    let id = tasks[index].id;
+    let removed = match remove_with_children(&mut tasks, index, children) {
+        Ok(removed) => removed,
+        Err(err) => {
+            println!("{} {}", "❌".red(), err);
+            return;
+        }
+    };
+    
+    store.save_recorded_safe(&tasks, Operation::new(format!("delete {}", id), &before, &tasks));
+    println!("{} Task {} deleted!", "🗑️".red(), id);
+    if removed.len() > 1 {
+        println!("   {} subtask(s) deleted too", removed.len() - 1);
+    }
+}
+
+// Make one task depend on another (or drop that link with `remove`)
+pub fn depend_task(store: &TaskStore, id: impl Into<TaskRef>, on: impl Into<TaskRef>, remove: bool) {
+    let (reference, dependency) = (id.into(), on.into());
+    let Some(_lock) = lock_store(store) else { return };
+    let mut tasks = store.load();
+
+    let (index, dependency_index) = match validate_task_id(&reference, &tasks)
+        .and_then(|index| Ok((index, validate_task_id(&dependency, &tasks)?)))
+    {
+        Ok(indexes) => indexes,
+        Err(err) => {
+            println!("{} {}", "❌".red(), err);
+            return;
+        }
+    };
+    let (id, dependency_id, dependency_uuid) = (tasks[index].id, tasks[dependency_index].id, tasks[dependency_index].uuid);
+
+    let before = tasks.clone();
+    let changed = if remove {
+        Ok(tasks[index].depends_on.remove(&dependency_uuid))
+    } else {
+        add_dependency(&mut tasks, index, dependency_uuid)
+    };
+    match changed {
+        Ok(true) => {}
+        Ok(false) => {
+            let state = if remove { "does not depend" } else { "already depends" };
+            println!("{} Task {} {} on task {}", "ℹ️".blue(), id, state, dependency_id);
+            return;
+        }
+        Err(err) => {
+            println!("{} {}", "❌".red(), err);
+            return;
+        }
+    }
+    tasks[index].touch();
+
+    let description = format!("{} {} {}", if remove { "undepend" } else { "depend" }, id, dependency_id);
+    store.save_recorded_safe(&tasks, Operation::new(description, &before, &tasks));
+    if remove {
+        println!("{} Task {} no longer depends on task {}", "🔗".green(), id, dependency_id);
+    } else {
+        println!("{} Task {} now depends on task {}", "🔗".green(), id, dependency_id);
+    }
+    if is_blocked(&tasks, &tasks[index]) {
+        println!("   {}", "(blocked until its dependencies are done)".bright_black());
+    }
+}
+
+// Render the dependency graph as Graphviz DOT. Only tasks with links are included
+// unless `all` is set; edges point from a dependency to the task waiting on it.
+pub fn dependency_graph(tasks: &[Task], all: bool) -> String {
+    let linked = |task: &Task| !task.depends_on.is_empty() || tasks.iter().any(|other| other.depends_on.contains(&task.uuid));
+    let mut dot = String::from("digraph todo {\n    rankdir=LR;\n    node [shape=box];\n");
+
+    for task in tasks.iter().filter(|task| all || linked(task)) {
+        let label = format!("{}: {}", task.id, task.text).replace('\\', "\\\\").replace('"', "\\\"");
+        let style = if task.is_closed() {
+            ", style=dashed, color=gray"
+        } else if is_blocked(tasks, task) {
+            ", color=red"
+        } else {
+            ", color=green"
+        };
+        dot.push_str(&format!("    t{} [label=\"{}\"{}];\n", task.id, label, style));
+    }
+    for task in tasks {
+        for dependency in tasks.iter().filter(|other| task.depends_on.contains(&other.uuid)) {
+            dot.push_str(&format!("    t{} -> t{};\n", dependency.id, task.id));
+        }
+    }
+
+    dot.push_str("}\n");
+    dot
+}
+
+// Print the dependency graph as Graphviz DOT (pipe into `dot -Tsvg`)
+pub fn show_graph(store: &TaskStore, all: bool) {
+    print!("{}", dependency_graph(&store.load(), all));
+}
+
+// Set, pause, resume or end the recurrence of a task. `rule` is a rule like
+// "weekly mon,thu" or one of pause/resume/end; `until` ends the series after a date.
+pub fn recur_task(store: &TaskStore, id: impl Into<TaskRef>, rule: &str, until: Option<String>) {
+    let reference = id.into();
+    if let Some(until) = &until
+        && let Err(err) = validate_date(until)
+    {
+        println!("{} {}", "❌".red(), err);
+        return;
+    }
+
+    let Some(_lock) = lock_store(store) else { return };
+    let mut tasks = store.load();
+    let index = match validate_task_id(&reference, &tasks) {
+        Ok(idx) => idx,
+        Err(err) => {
+            println!("{} {}", "❌".red(), err);
+            return;
+        }
+    };
+    let id = tasks[index].id;
+    let before = tasks.clone();
+    let task = &mut tasks[index];
+
+    let rule = rule.trim().to_lowercase();
+    match rule.as_str() {
+        "end" | "stop" | "none" => task.recur = None,
+        "pause" | "resume" | "" => {
+            let Some(recur) = task.recur.as_mut() else {
+                println!("{} Task {} does not recur (set a rule like 'weekly' first)", "❌".red(), id);
+                return;
+            };
+            match rule.as_str() {
+                "pause" => recur.paused = true,
+                "resume" => recur.paused = false,
+                _ if until.is_none() => {
+                    println!("{} Give a rule, pause, resume, end or --until", "❌".red());
+                    return;
+                }
+                _ => {}
+            }
+        }
+        _ => match rule.parse::<RecurRule>() {
+            // A new rule keeps the series' end date unless a new one is given
+            Ok(rule) => {
+                let until = task.recur.take().and_then(|recur| recur.until);
+                task.recur = Some(Recurrence { until, ..Recurrence::new(rule) });
+            }
+            Err(err) => {
+                println!("{} {}", "❌".red(), err);
+                return;
+            }
+        },
+    }
+    if let Some(recur) = task.recur.as_mut()
+        && until.is_some()
+    {
+        recur.until = until;
+    }
+    task.touch();
+
+    let summary = task.recur.as_ref().map_or("no longer recurs".to_string(), |recur| format!("recurs {}", recur));
+    store.save_recorded_safe(&tasks, Operation::new(format!("recur {}", id), &before, &tasks));
+    println!("{} Task {} {}", "🔁".cyan(), id, summary);
+}
+
+// Remove all tasks older than specified number of days ago
+pub fn remove_tasks_by_date(store: &TaskStore, days_ago: i32) {
+    let tasks = store.load();
+    // Calculate cutoff date: remove tasks older than this date
+    // days_ago=1 means remove tasks older than 1 day ago (2+ days old)
+    // days_ago=3 means remove tasks older than 3 days ago (4+ days old)
+    let cutoff_days = days_ago + 1;
+    let cutoff_date = match get_date_with_offset(-cutoff_days) {
+        Ok(date) => date,
+        Err(err) => {
+            println!("{} {}", "❌".red(), err);
+            return;
+        }
+    };
+    
+    // Find tasks older than cutoff date (due_date < cutoff_date)
+    let matching_tasks: Vec<&Task> = tasks.iter()
+        .filter(|task| {
+            if let Some(due_date) = &task.due_date {
+                due_date < &cutoff_date
+            } else {
+                false
+            }
+        })
+        .collect();
+    
+    if matching_tasks.is_empty() {
+        let date_desc = if days_ago == 0 {
+            "older than today".to_string()
+        } else {
+            format!("older than {}", cutoff_date)
+        };
+        
+        println!("{} No tasks found {}!", "📅".yellow(), date_desc);
+        return;
+    }
+    
+    // Show confirmation prompt
+    let date_desc = if days_ago == 0 {
+        "older than today".to_string()
+    } else if days_ago == 1 {
+        "older than 1 day ago".to_string()
+    } else {
+        format!("older than {} days ago", days_ago)
+    };
+    
+    println!("\n{} Tasks to be removed ({}):", "🗑️".red().bold(), date_desc.cyan());
+    for task in &matching_tasks {
+        let line = task_summary(task);
+        if task.is_closed() {
+            println!("  {}", line.strikethrough().dimmed());
+        } else {
+            println!("  {}", line);
+        }
+    }
+    
+    print!("\n{} Do you want to remove all {} task(s) {}? [y/N]: ", 
+        "❓".yellow(), matching_tasks.len(), date_desc);
+    if confirm() {
+        // Reload under the lock so changes made while prompting are kept
+        let Some(_lock) = lock_store(store) else { return };
+        let tasks = store.load();
+
+        // Collect tasks to be removed (older than cutoff date)
+        let tasks_to_remove: Vec<Task> = tasks.iter()
+            .filter(|task| {
                 if let Some(due_date) = &task.due_date {
                     due_date < &cutoff_date
                 } else {
@@ -365,10 +1211,10 @@ pub fn remove_tasks_by_date(days_ago: i32) {
             .collect();
         
         // Add to removed storage
-        add_to_removed(tasks_to_remove);
+        store.trash(tasks_to_remove.clone());
         
         // Remove from active tasks
-        let mut updated_tasks = tasks;
+        let mut updated_tasks = tasks.clone();
         let original_count = updated_tasks.len();
         updated_tasks.retain(|task| {
             if let Some(due_date) = &task.due_date {
@@ -379,7 +1225,9 @@ pub fn remove_tasks_by_date(days_ago: i32) {
         });
         let removed_count = original_count - updated_tasks.len();
         
-        save_tasks_safe(&updated_tasks);
+        let operation = Operation::new(format!("remove {} task(s) {}", removed_count, date_desc), &tasks, &updated_tasks)
+            .with_trashed(tasks_to_remove);
+        store.save_recorded_safe(&updated_tasks, operation);
         println!("{} Successfully removed {} task(s) {}!", 
             "✅".green(), removed_count, date_desc);
     } else {
@@ -388,7 +1236,9 @@ pub fn remove_tasks_by_date(days_ago: i32) {
 }
 
 // Edit task
-pub fn edit_task(id: u32, new_text: String) {
+pub fn edit_task(store: &TaskStore, id: impl Into<TaskRef>, new_text: String) {
+    let reference = id.into();
+    let (new_text, new_tags) = extract_tags(&new_text);
     // Validate input text
     if new_text.trim().is_empty() {
         println!("{} Task text cannot be empty!", "❌".red());
@@ -400,123 +1250,782 @@ pub fn edit_task(id: u32, new_text: String) {
         return;
     }
     
-    let mut tasks = load_tasks();
+    let Some(_lock) = lock_store(store) else { return };
+    let mut tasks = store.load();
     
     // Validate task ID
-    let index = match validate_task_id(id, &tasks) {
+    let index = match validate_task_id(&reference, &tasks) {
         Ok(idx) => idx,
         Err(err) => {
             println!("{} {}", "❌".red(), err);
             return;
         }
     };
+    let id = tasks[index].id;
 
+    let before = tasks.clone();
+    // The new text names the full tag set, so tags left out are dropped
     tasks[index].text = new_text.clone();
-    save_tasks_safe(&tasks);
+    tasks[index].tags = new_tags;
+    tasks[index].touch();
+    store.save_recorded_safe(&tasks, Operation::new(format!("edit {}", id), &before, &tasks));
     println!("{} Task {} updated!", "✏️".green(), id);
 }
 
 // Set due date
-pub fn set_due_date(id: u32, date: String) {
-    // Validate the date format first
-    if let Err(err) = validate_date(&date) {
-        println!("{} {}", "❌".red(), err);
-        return;
-    }
+pub fn set_due_date(store: &TaskStore, id: impl Into<TaskRef>, date: String) {
+    let reference = id.into();
+    // Resolve expressions like "next monday 15:00" first
+    let due = match resolve_due(&date) {
+        Ok(due) => due,
+        Err(err) => {
+            println!("{} {}", "❌".red(), err);
+            return;
+        }
+    };
     
-    let mut tasks = load_tasks();
+    let Some(_lock) = lock_store(store) else { return };
+    let mut tasks = store.load();
     
     // Validate task ID
-    let index = match validate_task_id(id, &tasks) {
+    let index = match validate_task_id(&reference, &tasks) {
         Ok(idx) => idx,
         Err(err) => {
             println!("{} {}", "❌".red(), err);
             return;
         }
     };
+    let id = tasks[index].id;
 
-    tasks[index].due_date = Some(date.clone());
-    save_tasks_safe(&tasks);
+    let before = tasks.clone();
+    let date = due.to_string();
+    due.apply(&mut tasks[index]);
+    tasks[index].touch();
+    store.save_recorded_safe(&tasks, Operation::new(format!("due {} {}", id, date), &before, &tasks));
     println!("{} Due date set for task {}: {}", "📅".green(), id, date.yellow());
 }
 
-// Sync to GitHub Gist
-pub fn sync_tasks() {
-    let tasks = load_tasks();
-    let json = serde_json::to_string_pretty(&tasks).unwrap();
+// Set or clear ("none") the scheduled or wait date of a task
+pub fn set_task_date(store: &TaskStore, id: impl Into<TaskRef>, field: DateField, date: String) {
+    let reference = id.into();
+    let date = match date.trim().to_lowercase().as_str() {
+        "none" | "clear" | "" => None,
+        _ => match resolve_date(&date) {
+            Ok(date) => Some(date),
+            Err(err) => {
+                println!("{} {}", "❌".red(), err);
+                return;
+            }
+        },
+    };
     
-    println!("{}", "🔄 Syncing...".cyan());
-    println!("{}", "ℹ️  GitHub Gist sync feature coming soon!".yellow());
-    println!("{}", "📋 Current task data:".blue());
-    println!("{}", json.dimmed());
-    println!("\n{}", "💡 Tip: For now, you can manually upload the todos.json file to Gist!".green());
+    let Some(_lock) = lock_store(store) else { return };
+    let mut tasks = store.load();
+    let index = match validate_task_id(&reference, &tasks) {
+        Ok(idx) => idx,
+        Err(err) => {
+            println!("{} {}", "❌".red(), err);
+            return;
+        }
+    };
+    let id = tasks[index].id;
+
+    let before = tasks.clone();
+    *field.slot(&mut tasks[index]) = date.clone();
+    tasks[index].touch();
+    let shown = date.as_deref().unwrap_or("none");
+    store.save_recorded_safe(&tasks, Operation::new(format!("{} {} {}", field.name(), id, shown), &before, &tasks));
+    match (&date, field) {
+        (Some(date), DateField::Scheduled) => println!("{} Task {} can start on {}", "⏳".green(), id, date.yellow()),
+        (Some(date), DateField::Wait) => println!("{} Task {} hidden until {}", "💤".green(), id, date.yellow()),
+        (None, _) => println!("{} Cleared {} date of task {}", "📅".green(), field.name(), id),
+    }
 }
 
-// Party time!
-pub fn party() {
-    let confetti = vec!["🎉", "🎊", "🥳", "🎈", "🎆", "✨", "🌟", "💫", "🎇", "🎁"];
-    println!("\n{}", "🎉 Party Time! 🎉".green().bold());
-    
-    for _ in 0..3 {
-        print!("   ");
-        for _ in 0..20 {
-            let emoji = confetti[rand() % confetti.len()];
-            print!("{} ", emoji);
+// Preview what a date expression resolves to
+pub fn preview_date(expr: &str) {
+    match resolve_due(expr).and_then(|due| Ok((days_from_today(&due.date)?, due))) {
+        Ok((days_offset, due)) => {
+            let weekday = chrono::NaiveDate::parse_from_str(&due.date, "%Y-%m-%d").map_or(String::new(), |date| date.format("%A").to_string());
+            println!("{} {} → {} ({})", "📅".blue(), expr.bold(), weekday, relative_due(&due, days_offset));
         }
-        println!();
+        Err(err) => println!("{} {}", "❌".red(), err),
     }
-    
+}
+
+// Set or clear task priority
+pub fn set_priority(store: &TaskStore, id: impl Into<TaskRef>, level: &str) {
+    let reference = id.into();
+    let priority = match Priority::parse_level(level) {
+        Ok(priority) => priority,
+        Err(err) => {
+            println!("{} {}", "❌".red(), err);
+            return;
+        }
+    };
+
+    let Some(_lock) = lock_store(store) else { return };
+    let mut tasks = store.load();
+
+    // Validate task ID
+    let index = match validate_task_id(&reference, &tasks) {
+        Ok(idx) => idx,
+        Err(err) => {
+            println!("{} {}", "❌".red(), err);
+            return;
+        }
+    };
+    let id = tasks[index].id;
+
+    let before = tasks.clone();
+    tasks[index].priority = priority;
+    tasks[index].touch();
+    let level = priority.map_or("none".to_string(), |priority| priority.to_string());
+    store.save_recorded_safe(&tasks, Operation::new(format!("priority {} {}", id, level), &before, &tasks));
+
+    match priority {
+        Some(priority) => println!("{} Priority set for task {}: {}", "🚩".green(), id, priority_marker(priority)),
+        None => println!("{} Priority cleared for task {}", "🚩".green(), id),
+    }
+}
+
+// Show every project with pending/done counts, including subprojects
+pub fn show_projects(store: &TaskStore) {
+    let tasks = store.load();
+    let paths = project_paths(&tasks);
+
+    if paths.is_empty() {
+        println!("{}", "📁 No projects yet. Add one with 'todo add \"text\" --project work'.".yellow());
+        return;
+    }
+
+    let count = |matches: &dyn Fn(&Task) -> bool| {
+        let pending = tasks.iter().filter(|task| matches(task) && !task.is_closed()).count();
+        let done = tasks.iter().filter(|task| matches(task) && task.is_closed()).count();
+        (pending, done)
+    };
+
+    println!("{}", "📁 Projects:".blue().bold());
+    for path in &paths {
+        let depth = path.matches('.').count();
+        let name = path.rsplit('.').next().unwrap_or(path);
+        let (pending, done) = count(&|task| task.in_project(path));
+        let width = 20usize.saturating_sub(depth * 2);
+        println!("  {}{:<width$} {} pending, {} done", "  ".repeat(depth), name.cyan(), pending.to_string().yellow(), done.to_string().green());
+    }
+
+    let (pending, done) = count(&|task| task.project.is_none());
+    if pending + done > 0 {
+        println!("  {:<20} {} pending, {} done", "(no project)".bright_black(), pending.to_string().yellow(), done.to_string().green());
+    }
+}
+
+// Show every tag with the number of tasks using it
+pub fn show_tags(store: &TaskStore) {
+    use std::collections::BTreeMap;
+
+    let tasks = store.load();
+    // tag -> (total, pending)
+    let mut counts: BTreeMap<&str, (usize, usize)> = BTreeMap::new();
+    for task in &tasks {
+        for tag in &task.tags {
+            let entry = counts.entry(tag).or_default();
+            entry.0 += 1;
+            if !task.is_closed() {
+                entry.1 += 1;
+            }
+        }
+    }
+
+    if counts.is_empty() {
+        println!("{}", "🏷️ No tags yet. Add some with +tag in the task text.".yellow());
+        return;
+    }
+
+    let mut counts: Vec<_> = counts.into_iter().collect();
+    counts.sort_by(|(tag_a, (total_a, _)), (tag_b, (total_b, _))| total_b.cmp(total_a).then(tag_a.cmp(tag_b)));
+
+    println!("{}", "🏷️ Tags:".blue().bold());
+    for (tag, (total, pending)) in counts {
+        println!("  {} {} ({} pending)", format!("+{}", tag).magenta(), total.to_string().cyan(), pending);
+    }
+}
+
+// Rename a tag across tasks and removed tasks
+pub fn rename_tag(store: &TaskStore, old: &str, new: &str) {
+    let (old, new) = match (normalize_tag(old), normalize_tag(new)) {
+        (Ok(old), Ok(new)) => (old, new),
+        (Err(err), _) | (_, Err(err)) => {
+            println!("{} {}", "❌".red(), err);
+            return;
+        }
+    };
+
+    let Some(_lock) = lock_store(store) else { return };
+    let mut tasks = store.load();
+    let mut removed = store.load_removed();
+    let before = tasks.clone();
+    let removed_before = removed.clone();
+
+    let rename = |task: &mut Task| {
+        if task.tags.remove(&old) {
+            task.tags.insert(new.clone());
+            true
+        } else {
+            false
+        }
+    };
+    let mut task_count = 0;
+    for task in tasks.iter_mut() {
+        if rename(task) {
+            task.touch();
+            task_count += 1;
+        }
+    }
+    let mut removed_count = 0;
+    for task in removed.iter_mut() {
+        if rename(task) {
+            removed_count += 1;
+        }
+    }
+
+    if task_count + removed_count == 0 {
+        println!("{} No tasks tagged {}", "❌".red(), format!("+{}", old).magenta());
+        return;
+    }
+
+    if removed_count > 0
+        && let Err(err) = store.save_removed(&removed)
+    {
+        println!("{} Could not update removed tasks: {}", "❌".red(), err);
+        return;
+    }
+    // Put removed tasks back if the tasks cannot be saved, so no half-rename is left behind.
+    // The active list is only written (and backed up) when one of its tasks changed.
+    if task_count > 0
+        && let Err(err) = store.save(&tasks)
+    {
+        if removed_count > 0 && let Err(rollback) = store.save_removed(&removed_before) {
+            println!("{} Could not restore removed tasks: {}", "❌".red(), rollback);
+        }
+        println!("{} Could not save tasks: {}", "❌".red(), err);
+        return;
+    }
+    let operation = Operation::new(format!("tag rename {} {}", old, new), &before, &tasks)
+        .with_removed_edits(&removed_before, &removed);
+    if let Err(err) = store.record(operation) {
+        println!("{} Renamed, but could not record it for undo: {}", "⚠️".yellow(), err);
+    }
+
+    println!("{} Renamed {} to {} on {} task(s) and {} removed task(s)",
+        "🏷️".green(), format!("+{}", old).magenta(), format!("+{}", new).magenta(), task_count, removed_count);
+}
+
+// Sync to GitHub Gist
+pub fn sync_tasks(store: &TaskStore) {
+    let tasks = store.load();
+    let json = match encode_tasks(&tasks, None) {
+        Ok(json) => json,
+        Err(err) => {
+            println!("{} {}", "❌".red(), err);
+            return;
+        }
+    };
+
+
+    println!("{}", "🔄 Syncing...".cyan());
+    println!("{}", "ℹ️  GitHub Gist sync feature coming soon!".yellow());
+    println!("{}", "📋 Current task data:".blue());
+    println!("{}", json.dimmed());
+    println!("\n{}", "💡 Tip: For now, you can manually upload the todos.json file to Gist!".green());
+}
+
+// Party time!
+pub fn party() {
+    let confetti = vec!["🎉", "🎊", "🥳", "🎈", "🎆", "✨", "🌟", "💫", "🎇", "🎁"];
+    println!("\n{}", "🎉 Party Time! 🎉".green().bold());
+    
+    for _ in 0..3 {
+        print!("   ");
+        for _ in 0..20 {
+            let emoji = confetti[rand() % confetti.len()];
+            print!("{} ", emoji);
+        }
+        println!();
+    }
+    
     println!("\n{}", "  🎊 Awesome work! You're amazing! 🎊".cyan().bold());
     println!("{}", "  ✨ All tasks done! Time to relax! ✨\n".yellow().bold());
 }
 
 // Search tasks
-pub fn search(query: String) {
+pub fn search(store: &TaskStore, query: String, options: &ViewOptions) {
     // Validate search query
     if query.trim().is_empty() {
         println!("{} Search query cannot be empty!", "❌".red());
         return;
     }
-    
-    if query.len() > 100 {
-        println!("{} Search query is too long (max 100 characters)!", "❌".red());
-        return;
-    }
-    
-    let tasks = load_tasks();
+
+    // Plain words match the task text; fields like +tag or due.before:friday narrow further
+    let query = match Query::parse_search(&query, &store.config().udas) {
+        Ok(query) => query,
+        Err(err) => {
+            println!("{} {}", "❌".red(), err);
+            return;
+        }
+    };
+    let query = match active_context(store) {
+        Ok(Some(context)) => context.and(query),
+        Ok(None) => query,
+        Err(err) => {
+            println!("{} {}", "❌".red(), err);
+            return;
+        }
+    };
+    let view = match list_view(store, options.sort.as_deref(), options.group_by) {
+        Ok(view) => view,
+        Err(err) => {
+            println!("{} {}", "❌".red(), err);
+            return;
+        }
+    };
+
+    let tasks = store.load();
     let results: Vec<&Task> = tasks.iter()
-        .filter(|task| task.text.to_lowercase().contains(&query.to_lowercase()))
+        .filter(|task| query.matches(task, &tasks))
         .collect();
 
     if results.is_empty() {
         println!("{} No tasks match '{}'!", "🔍".yellow(), query);
     } else {
-        display_task_list(&results, "🔍 Search Results:", false, "blue");
+        display_task_list(&results, &tasks, "🔍 Search Results:", false, "blue", &view);
+    }
+}
+
+// Change applied to every matching task by `todo bulk`
+#[derive(Debug, Clone, PartialEq)]
+pub enum BulkChange {
+    Status(Status),
+    Priority(Option<Priority>),
+    AddTag(String),
+    RemoveTag(String),
+    Project(Option<String>),
+    Delete,
+}
+
+impl BulkChange {
+    // What the change does, for the prompt and the journal
+    fn describe(&self) -> String {
+        match self {
+            BulkChange::Status(Status::Pending) => "reopen".to_string(),
+            BulkChange::Status(status) => format!("mark {}", status),
+            BulkChange::Priority(Some(priority)) => format!("set priority {} on", priority),
+            BulkChange::Priority(None) => "clear the priority of".to_string(),
+            BulkChange::AddTag(tag) => format!("tag +{}", tag),
+            BulkChange::RemoveTag(tag) => format!("untag +{}", tag),
+            BulkChange::Project(Some(project)) => format!("move to {}", project),
+            BulkChange::Project(None) => "clear the project of".to_string(),
+            BulkChange::Delete => "delete".to_string(),
+        }
+    }
+
+    // Normalize tag and project names, rejecting invalid ones
+    fn normalized(self) -> Result<BulkChange, TaskError> {
+        Ok(match self {
+            BulkChange::AddTag(tag) => BulkChange::AddTag(normalize_tag(&tag)?),
+            BulkChange::RemoveTag(tag) => BulkChange::RemoveTag(normalize_tag(&tag)?),
+            BulkChange::Project(project) => BulkChange::Project(project.as_deref().map(normalize_project).transpose()?),
+            change => change,
+        })
+    }
+
+    // True when applying the change would leave the task as it is
+    fn is_noop(&self, task: &Task) -> bool {
+        match self {
+            BulkChange::Status(status) => task.status == *status,
+            BulkChange::Priority(priority) => task.priority == *priority,
+            BulkChange::AddTag(tag) => task.tags.contains(tag),
+            BulkChange::RemoveTag(tag) => !task.tags.contains(tag),
+            BulkChange::Project(project) => task.project == *project,
+            BulkChange::Delete => false,
+        }
+    }
+}
+
+// Apply one change to every task matching a filter, after showing them and asking
+// for confirmation (unless `assume_yes`). Recorded as a single undoable change.
+pub fn bulk_update(store: &TaskStore, filter: &str, change: BulkChange, assume_yes: bool) {
+    use std::collections::HashSet;
+
+    let query = match Query::parse(filter, &store.config().udas) {
+        Ok(query) if query.is_empty() => {
+            println!("{} Give a filter to choose the tasks, e.g. '+sprint' or 'status:open'", "❌".red());
+            return;
+        }
+        Ok(query) => query,
+        Err(err) => {
+            println!("{} {}", "❌".red(), err);
+            return;
+        }
+    };
+    let change = match change.normalized() {
+        Ok(change) => change,
+        Err(err) => {
+            println!("{} {}", "❌".red(), err);
+            return;
+        }
+    };
+    let today = match get_today() {
+        Ok(today) => today,
+        Err(err) => {
+            println!("{} {}", "❌".red(), err);
+            return;
+        }
+    };
+    let describe = change.describe();
+
+    let tasks = store.load();
+    let matching: Vec<&Task> = tasks.iter()
+        .filter(|task| query.matches(task, &tasks) && !change.is_noop(task))
+        .collect();
+    if matching.is_empty() {
+        println!("{} No tasks match '{}' (or they already have that change)", "🔍".yellow(), query);
+        return;
+    }
+
+    if !assume_yes {
+        println!("\n{} Tasks matching '{}':", "📋".blue().bold(), query.to_string().cyan());
+        for task in &matching {
+            println!("  {}", task_summary(task));
+        }
+        print!("\n{} Do you want to {} {} task(s)? [y/N]: ", "❓".yellow(), describe, matching.len());
+        if !confirm() {
+            println!("{} Cancelled, nothing changed", "↩️".yellow());
+            return;
+        }
+    }
+    // Only touch the tasks that were shown, as they are now
+    let chosen: HashSet<_> = matching.iter().map(|task| task.uuid).collect();
+
+    let Some(_lock) = lock_store(store) else { return };
+    let mut tasks = store.load();
+    let before = tasks.clone();
+    let mut changed = Vec::new();
+    let mut spawned = Vec::new();
+    let mut trashed = Vec::new();
+    for uuid in tasks.iter().map(|task| task.uuid).filter(|uuid| chosen.contains(uuid)).collect::<Vec<_>>() {
+        let Some(index) = tasks.iter().position(|task| task.uuid == uuid) else { continue };
+        if change.is_noop(&tasks[index]) {
+            continue;
+        }
+        changed.push(tasks[index].id);
+        let task = &mut tasks[index];
+        match &change {
+            BulkChange::Status(status) => {
+                let was_closed = task.is_closed();
+                let open = open_dependencies(&tasks, &tasks[index]);
+                if *status == Status::Done && !open.is_empty() {
+                    println!("{} Task {} still depends on open task(s) {}", "⚠️".yellow(), tasks[index].id, id_list(&open));
+                }
+                // Subtasks keep their status unless they match the filter too, as with `todo done`
+                if let Err(err) = set_status_with_children(&mut tasks, index, *status, ChildPolicy::Orphan) {
+                    println!("{} {}", "❌".red(), err);
+                    changed.pop();
+                    continue;
+                }
+                let next_id = store.next_id(&tasks);
+                if status.is_closed() && !was_closed && let Some(next) = spawn_next(&mut tasks, index, next_id, &today) {
+                    spawned.push(tasks[next].id);
+                }
+            }
+            BulkChange::Priority(priority) => {
+                task.priority = *priority;
+                task.touch();
+            }
+            BulkChange::AddTag(tag) => {
+                task.tags.insert(tag.clone());
+                task.touch();
+            }
+            BulkChange::RemoveTag(tag) => {
+                task.tags.remove(tag);
+                task.touch();
+            }
+            BulkChange::Project(project) => {
+                task.project = project.clone();
+                task.touch();
+            }
+            BulkChange::Delete => {
+                // Subtasks that are not deleted too become top-level tasks
+                match remove_with_children(&mut tasks, index, ChildPolicy::Orphan) {
+                    Ok(removed) => trashed.extend(removed),
+                    Err(err) => {
+                        println!("{} {}", "❌".red(), err);
+                        changed.pop();
+                    }
+                }
+            }
+        }
+    }
+    if changed.is_empty() {
+        println!("{} Nothing left to change", "ℹ️".blue());
+        return;
+    }
+
+    // Deleted tasks go to removed storage, where `todo removed` lists them
+    if !trashed.is_empty()
+        && let Err(err) = store.trash_result(trashed.clone())
+    {
+        println!("{} Could not move tasks to removed storage, nothing changed: {}", "❌".red(), err);
+        return;
+    }
+    let operation = Operation::new(format!("bulk {}: {}", describe, query), &before, &tasks).with_trashed(trashed);
+    store.save_recorded_safe(&tasks, operation);
+    let ids: Vec<String> = changed.iter().map(u32::to_string).collect();
+    println!("{} {} {} task(s): {}", "✅".green(), capitalize(&describe), changed.len(), ids.join(", ").cyan());
+    if !spawned.is_empty() {
+        let ids: Vec<String> = spawned.iter().map(u32::to_string).collect();
+        println!("   {} Next: {}", "🔁".cyan(), ids.join(", ").cyan());
+    }
+}
+
+// Read a yes/no answer after a prompt; an answer that cannot be read counts as no
+fn confirm() -> bool {
+    use std::io::{self, Write};
+    let mut input = String::new();
+    if io::stdout().flush().is_err() || io::stdin().read_line(&mut input).is_err() {
+        return false;
+    }
+    matches!(input.trim().to_lowercase().as_str(), "y" | "yes")
+}
+
+// Helper function to capitalize the first letter of a message
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    chars.next().map_or(String::new(), |first| first.to_uppercase().chain(chars).collect())
+}
+
+// Helper function to check a name and filter before saving them as a query
+fn check_query(store: &TaskStore, name: &str, filter: &str) -> Result<(String, String), String> {
+    let name = name.trim().to_lowercase();
+    validate_query_name(&name).map_err(|err| err.to_string())?;
+    match Query::parse(filter, &store.config().udas) {
+        Ok(query) if query.is_empty() => Err("A saved query needs a filter, e.g. 'todo query save work +work status:open'".to_string()),
+        Ok(_) => Ok((name, filter.trim().to_string())),
+        Err(err) => Err(err.to_string()),
+    }
+}
+
+// Save a filter under a name, replacing any query with that name
+pub fn save_query(store: &TaskStore, name: &str, filter: String) {
+    let (name, filter) = match check_query(store, name, &filter) {
+        Ok(checked) => checked,
+        Err(err) => {
+            println!("{} {}", "❌".red(), err);
+            return;
+        }
+    };
+
+    let Some(_lock) = lock_store(store) else { return };
+    let result = store.saved_queries().and_then(|mut saved| {
+        let replaced = saved.queries.insert(name.clone(), filter.clone()).is_some();
+        store.save_queries(&saved).map(|_| replaced)
+    });
+    match result {
+        Ok(replaced) => {
+            let verb = if replaced { "Updated" } else { "Saved" };
+            println!("{} {} query {}: {}", "🔖".green(), verb, name.cyan(), filter);
+        }
+        Err(err) => println!("{} {}", "❌".red(), err),
+    }
+}
+
+// List the tasks matching a saved query, narrowed by any extra filter words
+pub fn run_query(store: &TaskStore, name: &str, extra: Vec<String>) {
+    let saved = match store.saved_queries() {
+        Ok(saved) => saved,
+        Err(err) => {
+            println!("{} {}", "❌".red(), err);
+            return;
+        }
+    };
+    let Some(filter) = saved.queries.get(&name.trim().to_lowercase()) else {
+        println!("{} No saved query named '{}' (see 'todo query list')", "❌".red(), name);
+        return;
+    };
+
+    let mut words = vec![format!("({})", filter)];
+    words.extend(extra);
+    list_tasks(store, &ListOptions { filter: words, ..ListOptions::default() });
+}
+
+// Show saved queries, marking the active context
+pub fn list_queries(store: &TaskStore) {
+    let saved = match store.saved_queries() {
+        Ok(saved) => saved,
+        Err(err) => {
+            println!("{} {}", "❌".red(), err);
+            return;
+        }
+    };
+    if saved.queries.is_empty() {
+        println!("{}", "🔖 No saved queries. Save one with 'todo query save <name> <filter>'.".yellow());
+        return;
+    }
+
+    println!("{}", "🔖 Saved Queries:".blue().bold());
+    for (name, filter) in &saved.queries {
+        let marker = if saved.context.as_ref() == Some(name) { format!(" {}", "(context)".green()) } else { String::new() };
+        println!("  {} {}{}", name.cyan(), filter, marker);
+    }
+}
+
+// Forget a saved query; clears the context if it was the active one
+pub fn delete_query(store: &TaskStore, name: &str) {
+    let name = name.trim().to_lowercase();
+    let Some(_lock) = lock_store(store) else { return };
+    let result = store.saved_queries().and_then(|mut saved| {
+        if saved.queries.remove(&name).is_none() {
+            return Ok(None);
+        }
+        let was_context = saved.context.as_ref() == Some(&name);
+        if was_context {
+            saved.context = None;
+        }
+        store.save_queries(&saved).map(|_| Some(was_context))
+    });
+    match result {
+        Ok(Some(was_context)) => {
+            println!("{} Deleted query {}", "🗑️".red(), name.cyan());
+            if was_context {
+                println!("   {} Context cleared", "🎯".yellow());
+            }
+        }
+        Ok(None) => println!("{} No saved query named '{}'", "❌".red(), name),
+        Err(err) => println!("{} {}", "❌".red(), err),
     }
 }
 
-// Simple random function
+// Make a saved query the context applied to listings, searches and the TUI.
+// With a filter, the query is saved (or replaced) first.
+pub fn set_context(store: &TaskStore, name: &str, filter: Vec<String>) {
+    let mut name = name.trim().to_lowercase();
+    let mut new_filter = None;
+    if !filter.is_empty() {
+        match check_query(store, &name, &filter.join(" ")) {
+            Ok((checked, filter)) => {
+                name = checked;
+                new_filter = Some(filter);
+            }
+            Err(err) => {
+                println!("{} {}", "❌".red(), err);
+                return;
+            }
+        }
+    }
+
+    let Some(_lock) = lock_store(store) else { return };
+    let result = store.saved_queries().and_then(|mut saved| {
+        if let Some(filter) = new_filter {
+            saved.queries.insert(name.clone(), filter);
+        }
+        let Some(filter) = saved.queries.get(&name).cloned() else {
+            return Ok(None);
+        };
+        saved.context = Some(name.clone());
+        store.save_queries(&saved).map(|_| Some(filter))
+    });
+    match result {
+        Ok(Some(filter)) => println!("{} Context {} set: {}", "🎯".green(), name.cyan(), filter),
+        Ok(None) => println!("{} No saved query named '{}' (save one with 'todo context set {} <filter>')", "❌".red(), name, name),
+        Err(err) => println!("{} {}", "❌".red(), err),
+    }
+}
+
+// Stop applying a context
+pub fn clear_context(store: &TaskStore) {
+    let Some(_lock) = lock_store(store) else { return };
+    let result = store.saved_queries().and_then(|mut saved| {
+        let previous = saved.context.take();
+        store.save_queries(&saved).map(|_| previous)
+    });
+    match result {
+        Ok(Some(name)) => println!("{} Context {} cleared", "🎯".green(), name.cyan()),
+        Ok(None) => println!("{} No context is set", "ℹ️".blue()),
+        Err(err) => println!("{} {}", "❌".red(), err),
+    }
+}
+
+// Show the active context
+pub fn show_context(store: &TaskStore) {
+    match store.saved_queries() {
+        Ok(saved) => match saved.context.as_ref().and_then(|name| Some((name, saved.queries.get(name)?))) {
+            Some((name, filter)) => println!("{} Context {}: {}", "🎯".green(), name.cyan(), filter),
+            None => println!("{} No context is set", "ℹ️".blue()),
+        },
+        Err(err) => println!("{} {}", "❌".red(), err),
+    }
+}
+
+// Simple random function (a clock error just picks the first item)
 fn rand() -> usize {
-    use std::time::{SystemTime, UNIX_EPOCH};
-    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
-    (now.as_nanos() % 10) as usize
+    crate::clock::now().map_or(0, |now| now.timestamp_subsec_nanos() as usize % 10)
 }
 
 // Show information about data storage location
-pub fn show_info() {
-    let data_location = get_data_location();
-    let tasks = load_tasks();
+pub fn show_info(store: &TaskStore) {
+    let data_location = store.location();
+    let tasks = store.load();
     
     println!("{}", "📊 Todo CLI Information".blue().bold());
     println!("{} {}", "📁 Data stored at:".green(), data_location.cyan());
+    println!("{} {}", "🗄️ Backend:".green(), store.backend_name().cyan());
+    if let Some(source) = store.source() {
+        println!("{} {}", "🧭 Location from:".green(), source.to_string().cyan());
+    }
+    let udas = &store.config().udas;
+    if !udas.is_empty() {
+        let declared: Vec<String> = udas.iter().map(|(name, def)| format!("{} ({})", name, def.kind)).collect();
+        println!("{} {}", "🧩 Attributes:".green(), declared.join("; ").cyan());
+    }
+    if let Ok(saved) = store.saved_queries() {
+        if !saved.queries.is_empty() {
+            let names: Vec<&str> = saved.queries.keys().map(String::as_str).collect();
+            println!("{} {}", "🔖 Saved queries:".green(), names.join(", ").cyan());
+        }
+        if let Some(name) = &saved.context {
+            let filter = saved.queries.get(name).map_or(String::new(), |filter| format!(" ({})", filter));
+            println!("{} {}{}", "🎯 Context:".green(), name.cyan(), filter);
+        }
+    }
     println!("{} {}", "📋 Total tasks:".green(), tasks.len().to_string().cyan());
-    println!("{} {}", "✅ Completed:".green(), tasks.iter().filter(|t| t.done).count().to_string().cyan());
-    println!("{} {}", "⬜ Pending:".green(), tasks.iter().filter(|t| !t.done).count().to_string().cyan());
+    for status in Status::ALL {
+        let count = tasks.iter().filter(|t| t.status == status).count();
+        // Pending and completed counts are always shown, the others only when in use
+        if count == 0 && !matches!(status, Status::Pending | Status::Done) {
+            continue;
+        }
+        let name = match status {
+            Status::Pending => "Pending",
+            Status::Started => "In progress",
+            Status::Blocked => "Blocked",
+            Status::Deferred => "Deferred",
+            Status::Done => "Completed",
+            Status::Cancelled => "Cancelled",
+        };
+        println!("{} {}", format!("{} {}:", status.emoji(), name).green(), count.to_string().cyan());
+    }
 }
 
 // Show removed tasks
-pub fn show_removed_tasks() {
-    let removed_tasks = load_removed_tasks();
+pub fn show_removed_tasks(store: &TaskStore, options: &ViewOptions) {
+    let view = match list_view(store, options.sort.as_deref(), options.group_by) {
+        Ok(view) => view,
+        Err(err) => {
+            println!("{} {}", "❌".red(), err);
+            return;
+        }
+    };
+    let removed_tasks = store.load_removed();
     
     if removed_tasks.is_empty() {
         println!("{}", "🗑️ No removed tasks found.".yellow());
@@ -524,7 +2033,224 @@ pub fn show_removed_tasks() {
     }
     
     let task_refs: Vec<&Task> = removed_tasks.iter().collect();
-    display_task_list(&task_refs, "🗑️ Removed Tasks:", true, "red"); // Use dimmed style for removed tasks
+    display_task_list(&task_refs, &removed_tasks, "🗑️ Removed Tasks:", true, "red", &view); // Use dimmed style for removed tasks
     
     println!("\n{} Total removed tasks: {}", "📊".blue(), removed_tasks.len().to_string().cyan());
-}
\ No newline at end of file
+}
+
+// Copy tasks and removed tasks to another backend, then switch config.json to it
+pub fn migrate(store: &TaskStore, to: BackendKind) {
+    match migrate_result(store, to) {
+        Ok((task_count, removed_count)) => println!("{} Migrated {} task(s) and {} removed task(s) to {}!",
+            "✅".green(), task_count, removed_count, to.to_string().cyan()),
+        Err(err) => println!("{} Migration failed: {}", "❌".red(), err),
+    }
+}
+
+// Migrate with error handling, returning the number of tasks and removed tasks copied
+pub fn migrate_result(store: &TaskStore, to: BackendKind) -> Result<(usize, usize), TaskError> {
+    let dir = store.dir()
+        .ok_or_else(|| TaskError::InvalidInput("This store has no data directory to migrate".to_string()))?;
+
+    let _lock = store.lock()?;
+    let mut config = Config::load(dir)?;
+    if config.backend == to {
+        return Err(TaskError::InvalidInput(format!("Already using the {} backend", to)));
+    }
+
+    let tasks = store.load_result()?;
+    let removed = store.load_removed_result()?;
+
+    let target = open_backend(dir, to)?;
+    target.save_tasks(&tasks)?;
+    target.save_removed(&removed)?;
+
+    // Verify the copy before switching over
+    let same = |a: &[Task], b: &[Task]| -> Result<bool, TaskError> {
+        let mut a = a.iter().map(serde_json::to_string).collect::<Result<Vec<_>, _>>()?;
+        let mut b = b.iter().map(serde_json::to_string).collect::<Result<Vec<_>, _>>()?;
+        a.sort();
+        b.sort();
+        Ok(a == b)
+    };
+    if !same(&tasks, &target.load_tasks()?)? || !same(&removed, &target.load_removed()?)? {
+        return Err(TaskError::InvalidInput(format!("Data read back from the {} backend does not match", to)));
+    }
+
+    config.backend = to;
+    config.save(dir)?;
+    Ok((tasks.len(), removed.len()))
+}
+
+// Restore tasks from a backup generation
+pub fn restore_backup(store: &TaskStore, generation: usize) {
+    let Some(_lock) = lock_store(store) else { return };
+
+    match store.restore_generation(generation) {
+        Ok(tasks) => {
+            println!("{} Restored {} task(s) from backup {}!", "✅".green(), tasks.len(), generation);
+            println!("{} The previous tasks were saved as backup 1 ('todo undo' brings them back).", "💡".yellow());
+        }
+        Err(err) => println!("{} Failed to restore from backup: {}", "❌".red(), err),
+    }
+}
+
+// List backup generations
+pub fn backup_list(store: &TaskStore) {
+    let backups = match store.backups() {
+        Ok(backups) => backups,
+        Err(err) => {
+            println!("{} {}", "❌".red(), err);
+            return;
+        }
+    };
+
+    if backups.is_empty() {
+        println!("{}", "💾 No backups yet. A backup is made before every save.".yellow());
+        return;
+    }
+
+    println!("{}", "💾 Backup Generations:".blue().bold());
+    for backup in &backups {
+        let count = match backup.load() {
+            Ok(tasks) => format!("{} task(s)", tasks.len()),
+            Err(_) => "unreadable".red().to_string(),
+        };
+        println!("{} {}  {}", format!("{:>3}", backup.generation).bright_green(), backup.created_local().cyan(), count);
+    }
+}
+
+// Show the tasks stored in a backup generation
+pub fn backup_show(store: &TaskStore, generation: usize) {
+    let (backup, tasks) = match store.backup(generation).and_then(|backup| backup.load().map(|tasks| (backup, tasks))) {
+        Ok(result) => result,
+        Err(err) => {
+            println!("{} {}", "❌".red(), err);
+            return;
+        }
+    };
+
+    if tasks.is_empty() {
+        println!("{} Backup {} has no tasks.", "💾".yellow(), generation);
+        return;
+    }
+
+    let view = match list_view(store, None, None) {
+        Ok(view) => view,
+        Err(err) => {
+            println!("{} {}", "❌".red(), err);
+            return;
+        }
+    };
+    let task_refs: Vec<&Task> = tasks.iter().collect();
+    let header = format!("💾 Backup {} ({}):", backup.generation, backup.created_local());
+    display_task_list(&task_refs, &tasks, &header, false, "blue", &view);
+}
+
+// Compare a backup generation with the current tasks
+pub fn backup_diff(store: &TaskStore, generation: usize) {
+    let (backup, old_tasks) = match store.backup(generation).and_then(|backup| backup.load().map(|tasks| (backup, tasks))) {
+        Ok(result) => result,
+        Err(err) => {
+            println!("{} {}", "❌".red(), err);
+            return;
+        }
+    };
+    let current_tasks = store.load();
+
+    println!("{}", format!("🔍 Changes since backup {} ({}):", backup.generation, backup.created_local()).blue().bold());
+
+    // Tasks are matched by UUID, so renumbered tasks are not reported as changed;
+    // generations written before tasks had UUIDs fall back to the short ID
+    let same = |old: &Task, task: &Task| if old.uuid.is_nil() { old.id == task.id } else { old.uuid == task.uuid };
+    let mut changes = 0;
+    for task in &current_tasks {
+        match old_tasks.iter().find(|old| same(old, task)) {
+            None => {
+                println!("{}", format!("+ {}", task_summary(task)).green());
+                changes += 1;
+            }
+            // A new short ID alone is not a change
+            Some(old) if task_summary(&Task { id: task.id, ..old.clone() }) != task_summary(task) => {
+                println!("{}", format!("~ {}", task_summary(task)).yellow());
+                println!("{}", format!("    was: {}", task_summary(old)).dimmed());
+                changes += 1;
+            }
+            Some(_) => {}
+        }
+    }
+    for old in old_tasks.iter().filter(|old| !current_tasks.iter().any(|task| same(old, task))) {
+        println!("{}", format!("- {}", task_summary(old)).red());
+        changes += 1;
+    }
+
+    if changes == 0 {
+        println!("{} No differences.", "✅".green());
+    }
+}
+
+// Undo the last `steps` operations
+pub fn undo(store: &TaskStore, steps: usize) {
+    let Some(_lock) = lock_store(store) else { return };
+
+    for _ in 0..steps.max(1) {
+        match store.undo() {
+            Ok(Some(operation)) => println!("{} Undid: {}", "↩️".green(), operation.description.cyan()),
+            Ok(None) => {
+                println!("{}", "📭 Nothing left to undo.".yellow());
+                return;
+            }
+            Err(err) => {
+                println!("{} Undo failed: {}", "❌".red(), err);
+                return;
+            }
+        }
+    }
+}
+
+// Redo the last `steps` undone operations
+pub fn redo(store: &TaskStore, steps: usize) {
+    let Some(_lock) = lock_store(store) else { return };
+
+    for _ in 0..steps.max(1) {
+        match store.redo() {
+            Ok(Some(operation)) => println!("{} Redid: {}", "↪️".green(), operation.description.cyan()),
+            Ok(None) => {
+                println!("{}", "📭 Nothing left to redo.".yellow());
+                return;
+            }
+            Err(err) => {
+                println!("{} Redo failed: {}", "❌".red(), err);
+                return;
+            }
+        }
+    }
+}
+
+// Show the undo journal
+pub fn show_history(store: &TaskStore) {
+    let journal = match store.journal() {
+        Ok(journal) => journal,
+        Err(err) => {
+            println!("{} {}", "❌".red(), err);
+            return;
+        }
+    };
+
+    if journal.entries.is_empty() {
+        println!("{}", "📭 No history yet.".yellow());
+        return;
+    }
+
+    println!("{}", "🕘 History (newest first):".blue().bold());
+    for (index, operation) in journal.entries.iter().enumerate().rev() {
+        let line = format!("{} {}", operation.timestamp, operation.description);
+        if index < journal.position {
+            println!("  {}", line);
+        } else {
+            // Undone operations can still be redone
+            println!("  {} {}", line.dimmed(), "(undone)".yellow());
+        }
+    }
+    println!("\n{} {} undo / {} redo available", "📊".blue(), journal.undo_count(), journal.redo_count());
+}
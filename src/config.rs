@@ -0,0 +1,73 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use crate::backup::BackupSettings;
+use crate::store::write_atomic;
+use crate::task::TaskError;
+use crate::uda::UdaDef;
+use crate::view::ViewSettings;
+
+// Storage backend used for todos and removed tasks
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum BackendKind {
+    #[default]
+    Json,
+    Sqlite,
+}
+
+impl fmt::Display for BackendKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BackendKind::Json => write!(f, "json"),
+            BackendKind::Sqlite => write!(f, "sqlite"),
+        }
+    }
+}
+
+// Settings stored in config.json inside the data directory
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct Config {
+    #[serde(default)]
+    pub backend: BackendKind,
+    #[serde(default)]
+    pub backups: BackupSettings,
+    // User-defined attributes tasks may carry, by name
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub udas: BTreeMap<String, UdaDef>,
+    // Default sort order and grouping of list, search and removed
+    #[serde(default, skip_serializing_if = "ViewSettings::is_empty")]
+    pub list: ViewSettings,
+    // Default sort order and grouping of the TUI
+    #[serde(default, skip_serializing_if = "ViewSettings::is_empty")]
+    pub tui: ViewSettings,
+}
+
+impl Config {
+    // Load config.json from the data directory (defaults when missing)
+    pub fn load(dir: &Path) -> Result<Config, TaskError> {
+        let file_path = dir.join("config.json");
+        if !file_path.exists() {
+            return Ok(Config::default());
+        }
+
+        let contents = fs::read_to_string(&file_path)?;
+        if contents.trim().is_empty() {
+            return Ok(Config::default());
+        }
+
+        Ok(serde_json::from_str(&contents)?)
+    }
+
+    // Write config.json into the data directory
+    pub fn save(&self, dir: &Path) -> Result<(), TaskError> {
+        if !dir.exists() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let json = serde_json::to_string_pretty(self)?;
+        write_atomic(&dir.join("config.json"), json.as_bytes())
+    }
+}
@@ -0,0 +1,270 @@
+use chrono::{Datelike, Days, Months, NaiveDate, NaiveTime, Weekday};
+use chrono_tz::{TZ_VARIANTS, Tz};
+use std::fmt;
+use crate::task::{Task, TaskError, validate_date};
+
+// Resolve a date expression against the active clock, as YYYY-MM-DD
+pub fn resolve_date(expr: &str) -> Result<String, TaskError> {
+    Ok(parse_date_expr(expr, crate::clock::today()?)?.format("%Y-%m-%d").to_string())
+}
+
+// A due date with optional time of day and timezone, as stored on a task
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DueSpec {
+    // YYYY-MM-DD
+    pub date: String,
+    // HH:MM (24h); None means "by the end of the day"
+    pub time: Option<String>,
+    // IANA timezone name of the time; None means local time
+    pub tz: Option<String>,
+}
+
+impl DueSpec {
+    // The due date, time and timezone of a task
+    pub fn of(task: &Task) -> Option<DueSpec> {
+        Some(DueSpec { date: task.due_date.clone()?, time: task.due_time.clone(), tz: task.due_tz.clone() })
+    }
+
+    // Store this due date on a task, replacing any previous time and timezone
+    pub fn apply(self, task: &mut Task) {
+        task.due_date = Some(self.date);
+        task.due_time = self.time;
+        task.due_tz = self.tz;
+    }
+}
+
+// "2025-12-03", "2025-12-03 15:00" or "2025-12-03 15:00 Europe/Berlin"
+impl fmt::Display for DueSpec {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.date)?;
+        if let Some(time) = &self.time {
+            write!(f, " {}", time)?;
+        }
+        if let Some(tz) = &self.tz {
+            write!(f, " {}", tz)?;
+        }
+        Ok(())
+    }
+}
+
+// Resolve a due expression against the active clock
+pub fn resolve_due(expr: &str) -> Result<DueSpec, TaskError> {
+    parse_due_expr(expr, crate::clock::today()?)
+}
+
+// Parse a date expression optionally followed by a time and a timezone, e.g.
+// "fri 15:00", "tomorrow at 9am Europe/Berlin", "2025-12-31T23:59 UTC" or just "15:30" (today)
+pub fn parse_due_expr(expr: &str, today: NaiveDate) -> Result<DueSpec, TaskError> {
+    // "2025-12-31T15:00" is a date and a time
+    let expr = match expr.trim().split_once('T') {
+        Some((date, rest)) if date.len() == 10 && validate_date(date).is_ok() => format!("{} {}", date, rest),
+        _ => expr.trim().to_string(),
+    };
+    let mut words: Vec<&str> = expr.split_whitespace().collect();
+
+    let tz = match words.last().and_then(|word| parse_timezone(word)) {
+        Some(tz) => {
+            words.pop();
+            Some(tz.name().to_string())
+        }
+        None => None,
+    };
+    let time = match words.last().and_then(|word| parse_time(word)) {
+        Some(time) => {
+            words.pop();
+            if words.last().is_some_and(|word| word.eq_ignore_ascii_case("at")) {
+                words.pop();
+            }
+            Some(time.format("%H:%M").to_string())
+        }
+        None => None,
+    };
+    if tz.is_some() && time.is_none() {
+        let date = if words.is_empty() { "today".to_string() } else { words.join(" ") };
+        return Err(TaskError::InvalidDate(format!("A timezone needs a time of day, e.g. '{} 15:00 {}'",
+            date, tz.unwrap_or_default())));
+    }
+
+    // A bare time means today
+    let date = if words.is_empty() && time.is_some() { today } else { parse_date_expr(&words.join(" "), today)? };
+    Ok(DueSpec { date: date.format("%Y-%m-%d").to_string(), time, tz })
+}
+
+// "15:00", "9:30", "3pm", "11:45am", "noon", "midnight"
+pub fn parse_time(word: &str) -> Option<NaiveTime> {
+    let word = word.to_lowercase();
+    match word.as_str() {
+        "noon" => return NaiveTime::from_hms_opt(12, 0, 0),
+        "midnight" => return NaiveTime::from_hms_opt(0, 0, 0),
+        _ => {}
+    }
+
+    let (clock, meridiem) = match word.strip_suffix("am") {
+        Some(clock) => (clock, Some(false)),
+        None => match word.strip_suffix("pm") {
+            Some(clock) => (clock, Some(true)),
+            None => (word.as_str(), None),
+        },
+    };
+    let (hour, minute) = match clock.split_once(':') {
+        Some((hour, minute)) if minute.len() == 2 => (hour.parse::<u32>().ok()?, minute.parse::<u32>().ok()?),
+        // A bare number is only a time with am/pm ("3pm"), otherwise it could be a day
+        None if meridiem.is_some() => (clock.parse::<u32>().ok()?, 0),
+        _ => return None,
+    };
+    let hour = match meridiem {
+        Some(_) if !(1..=12).contains(&hour) => return None,
+        Some(pm) => hour % 12 + if pm { 12 } else { 0 },
+        None => hour,
+    };
+    NaiveTime::from_hms_opt(hour, minute, 0)
+}
+
+// IANA timezone name, case-insensitive ("utc", "Europe/Berlin")
+pub fn parse_timezone(name: &str) -> Option<Tz> {
+    TZ_VARIANTS.iter().copied().find(|tz| tz.name().eq_ignore_ascii_case(name))
+}
+
+// Parse a date expression relative to `today`. Understands:
+// - today, tomorrow, yesterday
+// - weekdays: fri (the next one after today), next monday (Monday of next week)
+// - offsets: in 3 days, in 2 weeks, in 1 month, +3d, -1w
+// - eow, eom, eoy (end of week/month/year)
+// - YYYY-MM-DD, dec 3, 3 dec, dec 3 2026 (month-day without a year is the next such date)
+pub fn parse_date_expr(expr: &str, today: NaiveDate) -> Result<NaiveDate, TaskError> {
+    let expr = expr.trim().to_lowercase();
+    let words: Vec<&str> = expr.split_whitespace().collect();
+    let invalid = || TaskError::InvalidDate(format!(
+        "Unrecognized date '{}' (try tomorrow, fri, next monday, in 2 weeks, eom, dec 3 or YYYY-MM-DD)", expr));
+
+    let date = match words.as_slice() {
+        ["today" | "tod" | "now"] => Some(today),
+        ["tomorrow" | "tmr" | "tom"] => today.checked_add_days(Days::new(1)),
+        ["yesterday"] => today.checked_sub_days(Days::new(1)),
+        ["eow"] => Some(week_start(today) + Days::new(6)),
+        ["eom"] => end_of_month(today),
+        ["eoy"] => NaiveDate::from_ymd_opt(today.year(), 12, 31),
+        [iso] if iso.len() == 10 && iso.as_bytes()[4] == b'-' => {
+            validate_date(iso)?;
+            NaiveDate::parse_from_str(iso, "%Y-%m-%d").ok()
+        }
+        [offset] if offset.starts_with(['+', '-']) => {
+            let (sign, rest) = offset.split_at(1);
+            let split = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+            let count: i64 = rest[..split].parse().map_err(|_| invalid())?;
+            let count = if sign == "-" { -count } else { count };
+            add_units(today, count, unit(&rest[split..]).ok_or_else(invalid)?)
+        }
+        ["in", count, unit_name] => {
+            let count: i64 = count.parse().map_err(|_| invalid())?;
+            add_units(today, count, unit(unit_name).ok_or_else(invalid)?)
+        }
+        [day] => {
+            let weekday: Weekday = day.parse().map_err(|_| invalid())?;
+            Some(next_weekday(today, weekday))
+        }
+        ["next", day] => {
+            let weekday: Weekday = day.parse().map_err(|_| invalid())?;
+            Some(week_start(today) + Days::new(7 + u64::from(weekday.num_days_from_monday())))
+        }
+        [first, second] => month_day(first, second, None, today),
+        [first, second, year] => {
+            let year: i32 = year.parse().map_err(|_| invalid())?;
+            month_day(first, second, Some(year), today)
+        }
+        _ => None,
+    };
+
+    date.ok_or_else(invalid)
+}
+
+// Calendar units usable in offsets
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Unit {
+    Day,
+    Week,
+    Month,
+    Year,
+}
+
+fn unit(name: &str) -> Option<Unit> {
+    match name {
+        "" | "d" | "day" | "days" => Some(Unit::Day),
+        "w" | "week" | "weeks" => Some(Unit::Week),
+        "m" | "month" | "months" => Some(Unit::Month),
+        "y" | "year" | "years" => Some(Unit::Year),
+        _ => None,
+    }
+}
+
+// Move `count` units away from `date`; months clamp to the last day of short months
+fn add_units(date: NaiveDate, count: i64, unit: Unit) -> Option<NaiveDate> {
+    let (count, months) = match unit {
+        Unit::Day => return shift_days(date, count),
+        Unit::Week => return shift_days(date, count.checked_mul(7)?),
+        Unit::Month => (count, 1),
+        Unit::Year => (count, 12),
+    };
+    let months = Months::new(u32::try_from(count.unsigned_abs().checked_mul(months)?).ok()?);
+    if count >= 0 { date.checked_add_months(months) } else { date.checked_sub_months(months) }
+}
+
+fn shift_days(date: NaiveDate, days: i64) -> Option<NaiveDate> {
+    let shift = Days::new(days.unsigned_abs());
+    if days >= 0 { date.checked_add_days(shift) } else { date.checked_sub_days(shift) }
+}
+
+// Monday of the week containing `date`
+fn week_start(date: NaiveDate) -> NaiveDate {
+    date - Days::new(u64::from(date.weekday().num_days_from_monday()))
+}
+
+// The first `weekday` strictly after `date`
+fn next_weekday(date: NaiveDate, weekday: Weekday) -> NaiveDate {
+    let ahead = (7 + weekday.num_days_from_monday() - date.weekday().num_days_from_monday()) % 7;
+    date + Days::new(if ahead == 0 { 7 } else { u64::from(ahead) })
+}
+
+fn end_of_month(date: NaiveDate) -> Option<NaiveDate> {
+    NaiveDate::from_ymd_opt(date.year(), date.month(), 1)?
+        .checked_add_months(Months::new(1))?
+        .pred_opt()
+}
+
+// "dec 3" or "3 dec" (month names or their three-letter abbreviations). Without
+// a year this is the next such date on or after today.
+fn month_day(first: &str, second: &str, year: Option<i32>, today: NaiveDate) -> Option<NaiveDate> {
+    let (month, day) = match (month_number(first), month_number(second)) {
+        (Some(month), None) => (month, second.parse().ok()?),
+        (None, Some(month)) => (month, first.parse().ok()?),
+        _ => return None,
+    };
+
+    match year {
+        Some(year) => NaiveDate::from_ymd_opt(year, month, day),
+        None => {
+            // Feb 29 waits for the next leap year
+            (today.year()..today.year() + 8)
+                .filter_map(|year| NaiveDate::from_ymd_opt(year, month, day))
+                .find(|date| *date >= today)
+        }
+    }
+}
+
+fn month_number(name: &str) -> Option<u32> {
+    const MONTHS: [&str; 12] = [
+        "january", "february", "march", "april", "may", "june",
+        "july", "august", "september", "october", "november", "december",
+    ];
+    if name.len() < 3 {
+        return None;
+    }
+    MONTHS.iter().position(|month| month.starts_with(name)).map(|index| index as u32 + 1)
+}
+
+// Whole days from today until a YYYY-MM-DD date (negative when it is in the past)
+pub fn days_from_today(date: &str) -> Result<i64, TaskError> {
+    let date = NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .map_err(|_| TaskError::InvalidDate(format!("Date must be in YYYY-MM-DD format, got: {}", date)))?;
+    Ok((date - crate::clock::today()?).num_days())
+}
@@ -0,0 +1,70 @@
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use uuid::Uuid;
+use crate::task::TaskError;
+
+// Editor used when neither $VISUAL nor $EDITOR is set
+#[cfg(unix)]
+const DEFAULT_EDITOR: &str = "vi";
+#[cfg(windows)]
+const DEFAULT_EDITOR: &str = "notepad";
+
+// Editor command from $VISUAL, then $EDITOR, falling back to vi (notepad on Windows).
+// May carry arguments ("code --wait").
+pub fn editor_command() -> String {
+    ["VISUAL", "EDITOR"].iter()
+        .filter_map(|name| env::var(name).ok())
+        .find(|command| !command.trim().is_empty())
+        .unwrap_or_else(|| DEFAULT_EDITOR.to_string())
+}
+
+// A command that runs the editor on the path appended as its last argument. A command naming an
+// existing file is run as is (its path may contain spaces); anything else goes through the shell,
+// so quoting and arguments like "code --wait" work as they do on the command line.
+fn editor_process(command: &str) -> Command {
+    if Path::new(command).is_file() {
+        return Command::new(command);
+    }
+    shell_command(command)
+}
+
+#[cfg(unix)]
+fn shell_command(command: &str) -> Command {
+    let mut process = Command::new("sh");
+    process.arg("-c").arg(format!("{} \"$@\"", command)).arg("sh");
+    process
+}
+
+// cmd.exe takes the command line as typed; the path argument follows it
+#[cfg(windows)]
+fn shell_command(command: &str) -> Command {
+    use std::os::windows::process::CommandExt;
+    let mut process = Command::new("cmd");
+    process.arg("/C").raw_arg(command);
+    process
+}
+
+// Open `initial` in the user's editor on a temporary file and return the saved contents
+pub fn edit_text(initial: &str) -> Result<String, TaskError> {
+    edit_text_with(&editor_command(), initial)
+}
+
+// Same as edit_text with an explicit editor command
+pub fn edit_text_with(command: &str, initial: &str) -> Result<String, TaskError> {
+    let path = env::temp_dir().join(format!("todo-note-{}.md", Uuid::new_v4()));
+    // Editors expect text files to end with a newline
+    let initial = if initial.is_empty() || initial.ends_with('\n') { initial.to_string() } else { format!("{}\n", initial) };
+    fs::write(&path, initial)?;
+    let status = editor_process(command).arg(&path).status();
+    let contents = fs::read_to_string(&path);
+    // The temporary file is only a vehicle for the editor
+    let _ = fs::remove_file(&path);
+
+    let status = status.map_err(|err| TaskError::InvalidInput(format!("Could not run editor '{}': {}", command, err)))?;
+    if !status.success() {
+        return Err(TaskError::InvalidInput(format!("Editor '{}' exited with {}; notes left unchanged", command, status)));
+    }
+    Ok(contents?)
+}
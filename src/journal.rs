@@ -0,0 +1,203 @@
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use crate::store::write_atomic;
+use crate::task::{Task, TaskError};
+
+// Oldest operations are dropped once the journal grows beyond this
+const MAX_ENTRIES: usize = 100;
+
+// State of a single task before and after an operation (None = did not exist)
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TaskChange {
+    pub id: u32,
+    pub before: Option<Task>,
+    pub after: Option<Task>,
+}
+
+// One recorded mutating command
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Operation {
+    pub description: String,
+    pub timestamp: String,
+    pub changes: Vec<TaskChange>,
+    // Tasks moved to removed storage by this operation
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub trashed: Vec<Task>,
+    // Removed tasks edited in place (e.g. by a tag rename)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub removed_edits: Vec<RemovedEdit>,
+}
+
+// State of a removed task before and after an operation, found again by UUID
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RemovedEdit {
+    pub before: Task,
+    pub after: Task,
+}
+
+impl Operation {
+    // Record the difference between two task lists
+    pub fn new(description: impl Into<String>, before: &[Task], after: &[Task]) -> Operation {
+        let mut changes = Vec::new();
+
+        for old in before {
+            let new = after.iter().find(|task| task.id == old.id);
+            if new != Some(old) {
+                changes.push(TaskChange { id: old.id, before: Some(old.clone()), after: new.cloned() });
+            }
+        }
+        for new in after.iter().filter(|task| !before.iter().any(|old| old.id == task.id)) {
+            changes.push(TaskChange { id: new.id, before: None, after: Some(new.clone()) });
+        }
+
+        Operation {
+            description: description.into(),
+            timestamp: Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+            changes,
+            trashed: Vec::new(),
+            removed_edits: Vec::new(),
+        }
+    }
+
+    // Also remember tasks that were moved to removed storage
+    pub fn with_trashed(mut self, trashed: Vec<Task>) -> Operation {
+        self.trashed = trashed;
+        self
+    }
+
+    // Also remember removed tasks that changed between two versions of removed storage
+    pub fn with_removed_edits(mut self, before: &[Task], after: &[Task]) -> Operation {
+        self.removed_edits = before.iter()
+            .filter_map(|old| {
+                let new = after.iter().find(|task| task.uuid == old.uuid)?;
+                (new != old).then(|| RemovedEdit { before: old.clone(), after: new.clone() })
+            })
+            .collect();
+        self
+    }
+
+    // True when the operation changed nothing
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty() && self.trashed.is_empty() && self.removed_edits.is_empty()
+    }
+
+    // True when undoing or redoing the operation touches removed storage
+    pub fn touches_removed(&self) -> bool {
+        !self.trashed.is_empty() || !self.removed_edits.is_empty()
+    }
+
+    // Put tasks back to their state before the operation
+    pub fn revert(&self, tasks: &mut Vec<Task>) {
+        for change in self.changes.iter().rev() {
+            set_task(tasks, change.id, change.before.as_ref());
+        }
+    }
+
+    // Apply the operation again
+    pub fn apply(&self, tasks: &mut Vec<Task>) {
+        for change in &self.changes {
+            set_task(tasks, change.id, change.after.as_ref());
+        }
+    }
+
+    // Take the trashed tasks back out of removed storage and undo edits to removed tasks
+    pub fn untrash(&self, removed: &mut Vec<Task>) {
+        for trashed in self.trashed.iter().rev() {
+            if let Some(index) = removed.iter().rposition(|task| task.uuid == trashed.uuid) {
+                removed.remove(index);
+            }
+        }
+        for edit in &self.removed_edits {
+            set_removed(removed, &edit.before);
+        }
+    }
+
+    // Apply edits to removed tasks again
+    pub fn reapply_removed_edits(&self, removed: &mut [Task]) {
+        for edit in &self.removed_edits {
+            set_removed(removed, &edit.after);
+        }
+    }
+}
+
+// Replace the removed task with the same UUID, if it is still there
+fn set_removed(removed: &mut [Task], state: &Task) {
+    if let Some(task) = removed.iter_mut().find(|task| task.uuid == state.uuid) {
+        *task = state.clone();
+    }
+}
+
+// Replace, insert (in id order) or delete the task with the given id
+fn set_task(tasks: &mut Vec<Task>, id: u32, state: Option<&Task>) {
+    let position = tasks.iter().position(|task| task.id == id);
+    match (position, state) {
+        (Some(index), Some(task)) => tasks[index] = task.clone(),
+        (Some(index), None) => {
+            tasks.remove(index);
+        }
+        (None, Some(task)) => {
+            let index = tasks.iter().position(|other| other.id > id).unwrap_or(tasks.len());
+            tasks.insert(index, task.clone());
+        }
+        (None, None) => {}
+    }
+}
+
+// Undo/redo history: entries before `position` can be undone, the rest redone
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct Journal {
+    pub entries: Vec<Operation>,
+    pub position: usize,
+}
+
+impl Journal {
+    // Load journal.json from the data directory (empty when missing)
+    pub fn load(dir: &Path) -> Result<Journal, TaskError> {
+        let file_path = dir.join("journal.json");
+        if !file_path.exists() {
+            return Ok(Journal::default());
+        }
+
+        let contents = fs::read_to_string(&file_path)?;
+        if contents.trim().is_empty() {
+            return Ok(Journal::default());
+        }
+
+        let mut journal: Journal = serde_json::from_str(&contents)?;
+        journal.position = journal.position.min(journal.entries.len());
+        Ok(journal)
+    }
+
+    // Write journal.json into the data directory
+    pub fn save(&self, dir: &Path) -> Result<(), TaskError> {
+        if !dir.exists() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let json = serde_json::to_string_pretty(self)?;
+        write_atomic(&dir.join("journal.json"), json.as_bytes())
+    }
+
+    // Add an operation, discarding anything that could have been redone
+    pub fn push(&mut self, operation: Operation) {
+        self.entries.truncate(self.position);
+        self.entries.push(operation);
+        if self.entries.len() > MAX_ENTRIES {
+            let excess = self.entries.len() - MAX_ENTRIES;
+            self.entries.drain(..excess);
+        }
+        self.position = self.entries.len();
+    }
+
+    // Number of operations that can be undone
+    pub fn undo_count(&self) -> usize {
+        self.position
+    }
+
+    // Number of operations that can be redone
+    pub fn redo_count(&self) -> usize {
+        self.entries.len() - self.position
+    }
+}
@@ -5,17 +5,45 @@
 //! ## Features
 //! - CLI mode (command line)
 //! - TUI mode (Interactive UI)
-//! - JSON-based storage
+//! - JSON or SQLite storage
 //! - Due date support
-//! - Search feature
+//! - Recurring tasks
+//! - Multi-line task notes
+//! - User-defined attributes
+//! - Filter expressions & search
+//! - Configurable sorting & grouping
 //! - Colorful UI
 
 pub mod task;
+pub mod clock;
+pub mod dates;
+pub mod recur;
+pub mod uda;
+pub mod query;
+pub mod saved;
+pub mod view;
+pub mod store;
+pub mod schema;
+pub mod config;
+pub mod backup;
+pub mod journal;
+pub mod editor;
 pub mod commands;
 pub mod tui;
 pub mod cli;
 
-pub use task::{Task, TaskError, load_tasks, save_tasks, save_tasks_safe, get_data_location, get_today, get_date_with_offset, validate_date, load_removed_tasks, add_to_removed, restore_from_backup};
+pub use task::{Task, TaskError, TaskRef, DateField, DueState, Priority, Status, TimestampField, ChildPolicy, add_dependency, is_blocked, open_dependencies, open_dependents, extract_tags, tree_order, descendants, subtask_progress, normalize_project, project_paths, get_today, get_date_with_offset, offset_date, validate_date};
+pub use clock::{Clock, SystemClock, FixedClock, ClockGuard, set_clock};
+pub use dates::{DueSpec, parse_date_expr, parse_due_expr, resolve_date, resolve_due, days_from_today};
+pub use recur::{RecurRule, Recurrence};
+pub use uda::{UdaDef, UdaFilter, UdaKind, UdaOp, UdaValue, validate_udas};
+pub use query::{Query, QueryError};
+pub use saved::{SavedQueries, validate_query_name};
+pub use view::{GroupBy, ListView, SortField, SortKey, SortSpec, ViewSettings, LIST_DEFAULTS, TUI_DEFAULTS};
+pub use store::{TaskStore, StorageBackend, JsonBackend, MemoryBackend, SqliteBackend, DataDir, DataDirSource, resolve_data_dir, resolve_data_dir_with, validate_json_file};
+pub use config::{Config, BackendKind};
+pub use backup::{Backup, BackupSettings};
+pub use journal::{Journal, Operation, RemovedEdit, TaskChange};
 pub use commands::*;
 pub use tui::{run_tui, App, AppMode};
-pub use cli::{Cli, Commands};
\ No newline at end of file
+pub use cli::{Cli, Commands, BackupAction, BulkAction, ContextAction, QueryAction, TagAction};
\ No newline at end of file
@@ -1,39 +1,115 @@
-mod task;
-mod commands;
-mod tui;
-mod cli;
-
 use clap::Parser;
-use cli::{Cli, Commands};
-use commands::*;
-use tui::run_tui;
-use task::restore_from_backup;
+use todo::{Cli, Commands, DateField, Priority, Status, TaskStore};
+use todo::cli::{BackupAction, BulkAction, ContextAction, QueryAction, TagAction};
+use todo::commands::*;
+use todo::tui::run_tui;
 use colored::*;
 
 fn main() {
     let cli = Cli::parse();
 
+    let store = match TaskStore::open_default(cli.data_dir.as_deref()) {
+        Ok(store) => store,
+        Err(err) => {
+            eprintln!("{} {}", "❌".red(), err);
+            std::process::exit(1);
+        }
+    };
+
     match cli.command {
-        Commands::Add { text, days } => add_task(text, days),
-        Commands::List { all, today } => list_tasks(all, today),
-        Commands::Done { id } => toggle_task(id),
-        Commands::Delete { id } => delete_task(id),
-        Commands::Remove { days } => remove_tasks_by_date(days),
-        Commands::Removed => show_removed_tasks(),
-        Commands::Edit { id, text } => edit_task(id, text),
-        Commands::Due { id, date } => set_due_date(id, date),
-        Commands::Sync => sync_tasks(),
+        Commands::Add { text, days, due, priority, project, parent, scheduled, wait } => {
+            add_task_with(&store, text, AddOptions { days_offset: days, due, priority, project, parent, scheduled, wait })
+        }
+        Commands::List { all, today, sort, group_by, created_since, modified_since, completed_since, project, ready, waiting, attr_filters, filters } => {
+            let options = ListOptions {
+                all, today, sort, group_by, created_since, modified_since, completed_since, filter: filters, project, ready, waiting,
+                attr_filters,
+            };
+            list_tasks(&store, &options)
+        }
+        Commands::Start { id } => set_task_status(&store, id, Status::Started, None),
+        Commands::Block { id, reason } => set_task_status(&store, id, Status::Blocked, reason),
+        Commands::Defer { id } => set_task_status(&store, id, Status::Deferred, None),
+        Commands::Cancel { id, children } => set_task_status_with(&store, id, Status::Cancelled, None, children),
+        Commands::Reopen { id, children } => set_task_status_with(&store, id, Status::Pending, None, children),
+        Commands::Set { id, assignments } => set_attributes(&store, id, assignments),
+        Commands::Show { id } => show_task(&store, id),
+        Commands::Note { id, message, append } => match (message, append) {
+            (Some(notes), _) => set_notes(&store, id, notes),
+            (None, Some(line)) => append_notes(&store, id, line),
+            (None, None) => edit_notes(&store, id),
+        },
+        Commands::Done { id, children } => toggle_task_with(&store, id, children),
+        Commands::Delete { id, children } => delete_task_with(&store, id, children),
+        Commands::Remove { days } => remove_tasks_by_date(&store, days),
+        Commands::Removed { sort, group_by } => show_removed_tasks(&store, &ViewOptions { sort, group_by }),
+        Commands::Edit { id, text } => edit_task(&store, id, text),
+        Commands::Due { id, date } => set_due_date(&store, id, date.join(" ")),
+        Commands::Schedule { id, date } => set_task_date(&store, id, DateField::Scheduled, date.join(" ")),
+        Commands::Wait { id, date } => set_task_date(&store, id, DateField::Wait, date.join(" ")),
+        Commands::Date { expr } => preview_date(&expr.join(" ")),
+        Commands::Priority { id, level } => set_priority(&store, id, &level),
+        Commands::Depend { id, dependency, remove, .. } => depend_task(&store, id, dependency, remove),
+        Commands::Recur { id, rule, until } => recur_task(&store, id, &rule.join(" "), until),
+        Commands::Graph { all } => show_graph(&store, all),
+        Commands::Sync => sync_tasks(&store),
         Commands::Party => party(),
-        Commands::Search { query } => search(query),
-        Commands::Info => show_info(),
-        Commands::Restore => {
-            match restore_from_backup() {
-                Ok(_) => println!("{} Tasks restored from backup successfully!", "✅".green()),
-                Err(err) => println!("{} Failed to restore from backup: {}", "❌".red(), err),
-            }
+        Commands::Search { sort, group_by, query } => search(&store, query.join(" "), &ViewOptions { sort, group_by }),
+        Commands::Bulk { yes, action } => {
+            let (filter, change) = match action {
+                BulkAction::Done { filter } => (filter, BulkChange::Status(Status::Done)),
+                BulkAction::Start { filter } => (filter, BulkChange::Status(Status::Started)),
+                BulkAction::Defer { filter } => (filter, BulkChange::Status(Status::Deferred)),
+                BulkAction::Cancel { filter } => (filter, BulkChange::Status(Status::Cancelled)),
+                BulkAction::Reopen { filter } => (filter, BulkChange::Status(Status::Pending)),
+                BulkAction::Delete { filter } => (filter, BulkChange::Delete),
+                BulkAction::Priority { level, filter } => match Priority::parse_level(&level) {
+                    Ok(priority) => (filter, BulkChange::Priority(priority)),
+                    Err(err) => {
+                        println!("{} {}", "❌".red(), err);
+                        return;
+                    }
+                },
+                BulkAction::Tag { change, filter } => match change.strip_prefix('-') {
+                    Some(tag) => (filter, BulkChange::RemoveTag(tag.to_string())),
+                    None => (filter, BulkChange::AddTag(change)),
+                },
+                BulkAction::Project { project, filter } => {
+                    let project = (!project.eq_ignore_ascii_case("none")).then_some(project);
+                    (filter, BulkChange::Project(project))
+                }
+            };
+            bulk_update(&store, &filter.join(" "), change, yes)
         }
+        Commands::Query { action } => match action {
+            QueryAction::Save { name, filter } => save_query(&store, &name, filter.join(" ")),
+            QueryAction::Run { name, filter } => run_query(&store, &name, filter),
+            QueryAction::List => list_queries(&store),
+            QueryAction::Delete { name } => delete_query(&store, &name),
+        },
+        Commands::Context { action } => match action {
+            ContextAction::Set { name, filter } => set_context(&store, &name, filter),
+            ContextAction::Clear => clear_context(&store),
+            ContextAction::Show => show_context(&store),
+        },
+        Commands::Info => show_info(&store),
+        Commands::Restore { generation } => restore_backup(&store, generation),
+        Commands::Backup { action } => match action {
+            BackupAction::List => backup_list(&store),
+            BackupAction::Show { generation } => backup_show(&store, generation),
+            BackupAction::Diff { generation } => backup_diff(&store, generation),
+        },
+        Commands::Undo { steps } => undo(&store, steps),
+        Commands::Redo { steps } => redo(&store, steps),
+        Commands::History => show_history(&store),
+        Commands::Projects => show_projects(&store),
+        Commands::Tags => show_tags(&store),
+        Commands::Tag { action } => match action {
+            TagAction::Rename { old, new } => rename_tag(&store, &old, &new),
+        },
+        Commands::Migrate { to } => migrate(&store, to),
         Commands::Tui => {
-            if let Err(e) = run_tui() {
+            if let Err(e) = run_tui(store) {
                 eprintln!("Error: {}", e);
             }
         }
@@ -0,0 +1,735 @@
+use chrono::{DateTime, Local};
+use std::collections::{BTreeMap, BTreeSet};
+use std::error::Error;
+use std::fmt;
+use crate::clock;
+use crate::dates::parse_date_expr;
+use crate::task::{DueState, Priority, Status, Task, TaskError, TimestampField, is_blocked, normalize_project, normalize_tag, timestamp_date};
+use crate::uda::{UdaDef, UdaFilter, UdaOp};
+
+// Filter expressions shared by `todo list`, `todo search`, `todo bulk` and the TUI, e.g.
+//   status:pending due.before:2025-12-01 +work -someday pri>=M (text~"deploy" or text~release)
+// Terms next to each other must all match; `or`, `not` and parentheses combine them.
+
+// A query that could not be parsed, with the byte range of the offending token
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryError {
+    pub message: String,
+    pub query: String,
+    pub start: usize,
+    pub end: usize,
+}
+
+impl QueryError {
+    fn new(message: impl Into<String>, query: &str, start: usize, end: usize) -> QueryError {
+        QueryError { message: message.into(), query: query.to_string(), start, end }
+    }
+
+    // 1-based character column of the offending token
+    pub fn column(&self) -> usize {
+        self.query[..self.start].chars().count() + 1
+    }
+
+    // One-line form for places without room for the pointer, like the TUI status line
+    pub fn short(&self) -> String {
+        format!("{} (column {})", self.message, self.column())
+    }
+}
+
+impl fmt::Display for QueryError {
+    // The message, then the query with ^^^ under the offending token
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let width = self.query[self.start..self.end].chars().count().max(1);
+        write!(f, "Invalid filter: {}\n   {}\n   {}{}", self.message, self.query, " ".repeat(self.column() - 1), "^".repeat(width))
+    }
+}
+
+impl Error for QueryError {}
+
+// Comparison in a field term
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Cmp {
+    // ":", whose meaning depends on the field (contains for text, within for projects)
+    Is,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Contains,
+    NotContains,
+}
+
+impl Cmp {
+    fn holds(self, ordering: std::cmp::Ordering) -> bool {
+        use std::cmp::Ordering::*;
+        match self {
+            Cmp::Is | Cmp::Eq => ordering == Equal,
+            Cmp::Ne => ordering != Equal,
+            Cmp::Lt => ordering == Less,
+            Cmp::Le => ordering != Greater,
+            Cmp::Gt => ordering == Greater,
+            Cmp::Ge => ordering != Less,
+            Cmp::Contains | Cmp::NotContains => false,
+        }
+    }
+}
+
+// Dates a term can compare
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DateKind {
+    Due,
+    Scheduled,
+    Wait,
+    Stamp(TimestampField),
+}
+
+impl DateKind {
+    fn of(self, task: &Task) -> Option<String> {
+        match self {
+            DateKind::Due => task.local_due_date(),
+            DateKind::Scheduled => task.scheduled.clone(),
+            DateKind::Wait => task.wait.clone(),
+            DateKind::Stamp(field) => field.of(task).map(|at| timestamp_date(&at)),
+        }
+    }
+}
+
+// Conditions for `is:`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Flag {
+    Open,
+    Closed,
+    Ready,
+    Blocked,
+    Waiting,
+    Overdue,
+    Today,
+    Recurring,
+    Subtask,
+}
+
+impl Flag {
+    const NAMES: [(&'static str, Flag); 9] = [
+        ("open", Flag::Open), ("closed", Flag::Closed), ("ready", Flag::Ready), ("blocked", Flag::Blocked),
+        ("waiting", Flag::Waiting), ("overdue", Flag::Overdue), ("today", Flag::Today),
+        ("recurring", Flag::Recurring), ("subtask", Flag::Subtask),
+    ];
+}
+
+#[derive(Debug, Clone)]
+enum Term {
+    Text { notes: bool, cmp: Cmp, value: String },
+    Tag { cmp: Cmp, value: String },
+    Status { statuses: Vec<Status>, negate: bool },
+    Project { cmp: Cmp, value: Option<String> },
+    Priority { cmp: Cmp, value: Option<Priority> },
+    Date { kind: DateKind, cmp: Cmp, value: Option<String> },
+    Id { cmp: Cmp, ids: Vec<u32> },
+    Uuid { prefix: String, negate: bool },
+    Parent { id: Option<u32>, negate: bool },
+    Is { flag: Flag, negate: bool },
+    Attr(UdaFilter),
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Term(Term),
+}
+
+// A parsed filter expression
+#[derive(Debug, Clone)]
+pub struct Query {
+    source: String,
+    expr: Option<Expr>,
+    // Fields the query mentions, so listings know not to hide closed or waiting tasks
+    fields: BTreeSet<&'static str>,
+    udas: BTreeMap<String, UdaDef>,
+    now: DateTime<Local>,
+    today: String,
+}
+
+impl Query {
+    // Parse a filter, resolving relative dates against the active clock
+    pub fn parse(input: &str, udas: &BTreeMap<String, UdaDef>) -> Result<Query, QueryError> {
+        let now = clock::now().map_err(|err| QueryError::new(err.to_string(), input, 0, input.len()))?;
+        Query::parse_at(input, udas, now)
+    }
+
+    // Parse a search: like a filter, but a word whose field name is unknown is searched for as
+    // text, so "fix: login" or "http://x" find tasks instead of failing
+    pub fn parse_search(input: &str, udas: &BTreeMap<String, UdaDef>) -> Result<Query, QueryError> {
+        let now = clock::now().map_err(|err| QueryError::new(err.to_string(), input, 0, input.len()))?;
+        Query::parse_with(input, udas, now, true)
+    }
+
+    // Parse a filter, resolving relative dates like "today" or "friday" against `now`
+    pub fn parse_at(input: &str, udas: &BTreeMap<String, UdaDef>, now: DateTime<Local>) -> Result<Query, QueryError> {
+        Query::parse_with(input, udas, now, false)
+    }
+
+    fn parse_with(input: &str, udas: &BTreeMap<String, UdaDef>, now: DateTime<Local>, text_fallback: bool) -> Result<Query, QueryError> {
+        let tokens = tokenize(input)?;
+        let mut parser = Parser { query: input, tokens, pos: 0, udas, now, fields: BTreeSet::new(), text_fallback };
+        let expr = if parser.tokens.is_empty() { None } else { Some(parser.parse_or()?) };
+        if let Some(token) = parser.tokens.get(parser.pos) {
+            return Err(QueryError::new("Unmatched ')'", input, token.start, token.end));
+        }
+        let fields = parser.fields;
+        let today = now.format("%Y-%m-%d").to_string();
+        Ok(Query { source: input.trim().to_string(), expr, fields, udas: udas.clone(), now, today })
+    }
+
+    // True when the query has no terms and so matches every task
+    pub fn is_empty(&self) -> bool {
+        self.expr.is_none()
+    }
+
+    // True when the query mentions a field like "status", "wait" or "completed"
+    pub fn mentions(&self, field: &str) -> bool {
+        self.fields.contains(field)
+    }
+
+    // Both filters at once, e.g. the active context and the filter typed on the command line
+    pub fn and(self, other: Query) -> Query {
+        let source = match (self.is_empty(), other.is_empty()) {
+            (true, _) => other.source,
+            (_, true) => self.source,
+            _ => format!("({}) ({})", self.source, other.source),
+        };
+        let expr = match (self.expr, other.expr) {
+            (Some(left), Some(right)) => Some(Expr::And(Box::new(left), Box::new(right))),
+            (left, right) => left.or(right),
+        };
+        let fields = self.fields.union(&other.fields).copied().collect();
+        Query { source, expr, fields, ..self }
+    }
+
+    // Tags every matching task must have (`+tag` terms outside `or` and `not`), given to
+    // tasks added while the query is the active context
+    pub fn implied_tags(&self) -> Vec<String> {
+        self.required_terms().into_iter().filter_map(|term| match term {
+            Term::Tag { cmp: Cmp::Is | Cmp::Eq, value } if !value.is_empty() => Some(value.clone()),
+            _ => None,
+        }).collect()
+    }
+
+    // Project every matching task must be in (`project:name` outside `or` and `not`)
+    pub fn implied_project(&self) -> Option<String> {
+        self.required_terms().into_iter().find_map(|term| match term {
+            Term::Project { cmp: Cmp::Is | Cmp::Eq, value } => value.clone(),
+            _ => None,
+        })
+    }
+
+    // Terms joined only by `and`
+    fn required_terms(&self) -> Vec<&Term> {
+        fn collect<'a>(expr: &'a Expr, terms: &mut Vec<&'a Term>) {
+            match expr {
+                Expr::And(left, right) => {
+                    collect(left, terms);
+                    collect(right, terms);
+                }
+                Expr::Term(term) => terms.push(term),
+                Expr::Or(..) | Expr::Not(_) => {}
+            }
+        }
+        let mut terms = Vec::new();
+        if let Some(expr) = &self.expr {
+            collect(expr, &mut terms);
+        }
+        terms
+    }
+
+    // Check a task; `all` is the whole task list, used for dependencies and parents
+    pub fn matches(&self, task: &Task, all: &[Task]) -> bool {
+        self.expr.as_ref().is_none_or(|expr| self.eval(expr, task, all))
+    }
+
+    fn eval(&self, expr: &Expr, task: &Task, all: &[Task]) -> bool {
+        match expr {
+            Expr::And(left, right) => self.eval(left, task, all) && self.eval(right, task, all),
+            Expr::Or(left, right) => self.eval(left, task, all) || self.eval(right, task, all),
+            Expr::Not(inner) => !self.eval(inner, task, all),
+            Expr::Term(term) => self.eval_term(term, task, all),
+        }
+    }
+
+    fn eval_term(&self, term: &Term, task: &Task, all: &[Task]) -> bool {
+        let today = &self.today;
+        match term {
+            Term::Text { notes, cmp, value } => {
+                let actual = if *notes { task.notes.as_deref().unwrap_or("") } else { &task.text };
+                compare_text(&actual.to_lowercase(), *cmp, value)
+            }
+            Term::Tag { cmp, value } if value.is_empty() => (*cmp == Cmp::Ne) != task.tags.is_empty(),
+            Term::Tag { cmp, value } => match cmp {
+                Cmp::Ne => !task.tags.contains(value),
+                Cmp::Contains => task.tags.iter().any(|tag| tag.contains(value.as_str())),
+                Cmp::NotContains => !task.tags.iter().any(|tag| tag.contains(value.as_str())),
+                _ => task.tags.contains(value),
+            },
+            Term::Status { statuses, negate } => statuses.contains(&task.status) != *negate,
+            Term::Project { cmp, value: None } => (*cmp == Cmp::Ne) == task.project.is_some(),
+            Term::Project { cmp, value: Some(project) } => match cmp {
+                Cmp::Is => task.in_project(project),
+                Cmp::Ne => !task.in_project(project),
+                Cmp::Eq => task.project.as_ref() == Some(project),
+                _ => compare_text(task.project.as_deref().unwrap_or(""), *cmp, project),
+            },
+            Term::Priority { cmp, value } => compare(task.priority, *cmp, value.as_ref()),
+            Term::Date { kind, cmp, value } => compare(kind.of(task), *cmp, value.as_ref()),
+            Term::Id { cmp, ids } => match cmp {
+                Cmp::Eq => ids.contains(&task.id),
+                Cmp::Ne => !ids.contains(&task.id),
+                _ => cmp.holds(task.id.cmp(&ids[0])),
+            },
+            Term::Uuid { prefix, negate } => task.uuid.simple().to_string().starts_with(prefix.as_str()) != *negate,
+            Term::Parent { id, negate } => {
+                let parent = id.map(|id| all.iter().find(|other| other.id == id).map(|other| other.uuid));
+                let matched = match parent {
+                    None => task.parent.is_none(),
+                    Some(uuid) => uuid.is_some() && task.parent == uuid,
+                };
+                matched != *negate
+            }
+            Term::Is { flag, negate } => {
+                let open = !task.is_closed();
+                let matched = match flag {
+                    Flag::Open => open,
+                    Flag::Closed => !open,
+                    Flag::Ready => open && task.status != Status::Blocked && !is_blocked(all, task) && !task.is_waiting(today),
+                    Flag::Blocked => open && (task.status == Status::Blocked || is_blocked(all, task)),
+                    Flag::Waiting => task.is_waiting(today),
+                    Flag::Overdue => open && task.due_state(self.now) == Some(DueState::Overdue),
+                    Flag::Today => task.local_due_date().as_deref() == Some(today),
+                    Flag::Recurring => task.recur.is_some(),
+                    Flag::Subtask => task.parent.is_some(),
+                };
+                matched != *negate
+            }
+            Term::Attr(filter) => filter.matches(task, &self.udas),
+        }
+    }
+}
+
+impl fmt::Display for Query {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+// Ordering comparison of optional values; a missing value on either side only
+// satisfies "!=" (or "=" when both are missing)
+fn compare<T: Ord>(actual: Option<T>, cmp: Cmp, expected: Option<&T>) -> bool {
+    match (actual, expected) {
+        (actual, None) => (cmp == Cmp::Ne) == actual.is_some(),
+        (None, Some(_)) => cmp == Cmp::Ne,
+        (Some(actual), Some(expected)) => cmp.holds(actual.cmp(expected)),
+    }
+}
+
+// Case-insensitive text comparison; `actual` and `value` are already lowercase
+fn compare_text(actual: &str, cmp: Cmp, value: &str) -> bool {
+    match cmp {
+        Cmp::Is | Cmp::Contains => actual.contains(value),
+        Cmp::NotContains => !actual.contains(value),
+        _ => cmp.holds(actual.cmp(value)),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum TokenKind {
+    Open,
+    Close,
+    Word,
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    kind: TokenKind,
+    // Source text, quotes included
+    raw: String,
+    start: usize,
+    end: usize,
+}
+
+impl Token {
+    fn is_keyword(&self, keyword: &str) -> bool {
+        self.kind == TokenKind::Word && self.raw.eq_ignore_ascii_case(keyword)
+    }
+}
+
+// Split a query into parentheses and words; double quotes group words with spaces
+fn tokenize(query: &str) -> Result<Vec<Token>, QueryError> {
+    let mut tokens = Vec::new();
+    let mut chars = query.char_indices().peekable();
+    while let Some(&(start, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+        if c == '(' || c == ')' {
+            chars.next();
+            let kind = if c == '(' { TokenKind::Open } else { TokenKind::Close };
+            tokens.push(Token { kind, raw: c.to_string(), start, end: start + 1 });
+            continue;
+        }
+
+        let mut quote_start = None;
+        let mut end = query.len();
+        while let Some(&(index, c)) = chars.peek() {
+            if quote_start.is_none() && (c.is_whitespace() || c == '(' || c == ')') {
+                end = index;
+                break;
+            }
+            if c == '"' {
+                quote_start = if quote_start.is_some() { None } else { Some(index) };
+            }
+            chars.next();
+        }
+        if let Some(quote) = quote_start {
+            return Err(QueryError::new("Unclosed quote", query, quote, query.len()));
+        }
+        tokens.push(Token { kind: TokenKind::Word, raw: query[start..end].to_string(), start, end });
+    }
+    Ok(tokens)
+}
+
+// Operators, longest first so "<=" wins over "<"
+const OPERATORS: [(&str, Cmp); 9] = [
+    ("!=", Cmp::Ne), ("!~", Cmp::NotContains), ("<=", Cmp::Le), (">=", Cmp::Ge),
+    (":", Cmp::Is), ("=", Cmp::Eq), ("<", Cmp::Lt), (">", Cmp::Gt), ("~", Cmp::Contains),
+];
+
+// Field names parse_term understands, besides declared attributes
+const FIELDS: [&str; 26] = [
+    "text", "description", "desc", "notes", "note", "tag", "tags", "status", "project", "proj", "pro", "priority", "pri",
+    "due", "scheduled", "sched", "wait", "created", "entry", "modified", "completed", "end", "id", "uuid",
+    "parent", "is",
+];
+
+fn is_field(name: &str, udas: &BTreeMap<String, UdaDef>) -> bool {
+    FIELDS.contains(&name) || udas.contains_key(name)
+}
+
+// Find the operator of a `field<op>value` word, if the word has a field name before it
+fn split_term(word: &str) -> Option<(usize, &'static str)> {
+    let index = word.find([':', '=', '<', '>', '~', '!'])?;
+    let name = &word[..index];
+    let mut chars = name.chars();
+    let is_name = chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.');
+    if !is_name {
+        return None;
+    }
+    OPERATORS.iter().find(|(symbol, _)| word[index..].starts_with(symbol)).map(|(symbol, _)| (index, *symbol))
+}
+
+fn unquote(text: &str) -> String {
+    text.replace('"', "")
+}
+
+struct Parser<'a> {
+    query: &'a str,
+    tokens: Vec<Token>,
+    pos: usize,
+    udas: &'a BTreeMap<String, UdaDef>,
+    now: DateTime<Local>,
+    fields: BTreeSet<&'static str>,
+    // Treat words with an unknown field name ("fix:", "http://x") as text to search for
+    text_fallback: bool,
+}
+
+impl Parser<'_> {
+    fn error(&self, message: impl Into<String>, token: &Token) -> QueryError {
+        QueryError::new(message, self.query, token.start, token.end)
+    }
+
+    // Take a keyword token and make sure a term follows it
+    fn keyword(&mut self) -> Result<Token, QueryError> {
+        let token = self.tokens[self.pos].clone();
+        self.pos += 1;
+        match self.tokens.get(self.pos) {
+            Some(next) if next.kind != TokenKind::Close && !next.is_keyword("and") && !next.is_keyword("or") => Ok(token),
+            _ => Err(self.error(format!("Expected a filter after '{}'", token.raw), &token)),
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, QueryError> {
+        let mut left = self.parse_and()?;
+        while self.tokens.get(self.pos).is_some_and(|token| token.is_keyword("or")) {
+            self.keyword()?;
+            left = Expr::Or(Box::new(left), Box::new(self.parse_and()?));
+        }
+        Ok(left)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, QueryError> {
+        let mut left = self.parse_not()?;
+        while let Some(token) = self.tokens.get(self.pos) {
+            if token.kind == TokenKind::Close || token.is_keyword("or") {
+                break;
+            }
+            if token.is_keyword("and") {
+                self.keyword()?;
+            }
+            left = Expr::And(Box::new(left), Box::new(self.parse_not()?));
+        }
+        Ok(left)
+    }
+
+    fn parse_not(&mut self) -> Result<Expr, QueryError> {
+        if self.tokens.get(self.pos).is_some_and(|token| token.is_keyword("not")) {
+            self.keyword()?;
+            return Ok(Expr::Not(Box::new(self.parse_not()?)));
+        }
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, QueryError> {
+        let token = self.tokens[self.pos].clone();
+        self.pos += 1;
+        match token.kind {
+            TokenKind::Open => {
+                match self.tokens.get(self.pos) {
+                    None => return Err(self.error("Unclosed '('", &token)),
+                    Some(close) if close.kind == TokenKind::Close => {
+                        return Err(QueryError::new("Empty parentheses", self.query, token.start, close.end));
+                    }
+                    Some(_) => {}
+                }
+                let inner = self.parse_or()?;
+                match self.tokens.get(self.pos) {
+                    Some(close) if close.kind == TokenKind::Close => {
+                        self.pos += 1;
+                        Ok(inner)
+                    }
+                    _ => Err(self.error("Unclosed '('", &token)),
+                }
+            }
+            TokenKind::Close => Err(self.error("Unmatched ')'", &token)),
+            TokenKind::Word if token.is_keyword("and") || token.is_keyword("or") => {
+                Err(self.error(format!("Expected a filter before '{}'", token.raw), &token))
+            }
+            TokenKind::Word => self.parse_term(&token).map(Expr::Term),
+        }
+    }
+
+    fn parse_term(&mut self, token: &Token) -> Result<Term, QueryError> {
+        let raw = token.raw.as_str();
+        let text = |value: &str| Term::Text { notes: false, cmp: Cmp::Contains, value: unquote(value).to_lowercase() };
+        if raw.starts_with('"') {
+            return Ok(text(raw));
+        }
+        for (prefix, cmp) in [('+', Cmp::Eq), ('-', Cmp::Ne)] {
+            if let Some(tag) = raw.strip_prefix(prefix).filter(|tag| !tag.is_empty()) {
+                let value = normalize_tag(tag).map_err(|err| self.error(message(err), token))?;
+                self.fields.insert("tag");
+                return Ok(Term::Tag { cmp, value });
+            }
+        }
+        let Some((index, symbol)) = split_term(raw) else {
+            return Ok(text(raw));
+        };
+
+        let name = raw[..index].to_lowercase();
+        let (field, modifier) = name.split_once('.').map_or((name.as_str(), None), |(field, modifier)| (field, Some(modifier)));
+        if self.text_fallback && !is_field(field, self.udas) {
+            return Ok(text(raw));
+        }
+        let mut cmp = OPERATORS.iter().find(|(op, _)| *op == symbol).map(|(_, cmp)| *cmp).unwrap_or(Cmp::Is);
+        if let Some(modifier) = modifier {
+            if !matches!(cmp, Cmp::Is | Cmp::Eq) {
+                return Err(self.error(format!("Use ':' after the '.{}' modifier", modifier), token));
+            }
+            cmp = match modifier {
+                "before" | "below" | "under" => Cmp::Lt,
+                "after" | "above" | "over" => Cmp::Gt,
+                "by" | "until" => Cmp::Le,
+                "since" => Cmp::Ge,
+                "is" | "on" | "equals" => Cmp::Eq,
+                "not" | "isnt" => Cmp::Ne,
+                "has" | "contains" => Cmp::Contains,
+                "hasnt" => Cmp::NotContains,
+                _ => return Err(self.error(format!(
+                    "Unknown modifier '.{}' (use before, after, by, since, is, not, has or hasnt)", modifier), token)),
+            };
+        }
+
+        let value_start = token.start + index + symbol.len();
+        let value_token = Token { kind: TokenKind::Word, raw: raw[index + symbol.len()..].to_string(), start: value_start, end: token.end.max(value_start) };
+        let value = unquote(&value_token.raw);
+        let value = value.trim();
+        let bad_value = |err: TaskError| self.error(message(err), &value_token);
+
+        let term = match field {
+            "text" | "description" | "desc" | "notes" | "note" => {
+                let notes = field.starts_with("note");
+                if value.is_empty() && !notes {
+                    return Err(self.error(format!("'{}' needs a value", field), token));
+                }
+                // "notes:" / "notes=" match tasks without notes, "notes!=" tasks with them
+                let cmp = if value.is_empty() && cmp == Cmp::Is { Cmp::Eq } else { cmp };
+                self.fields.insert(if notes { "notes" } else { "text" });
+                Term::Text { notes, cmp, value: value.to_lowercase() }
+            }
+            "tag" | "tags" => {
+                let value = if value.is_empty() { String::new() } else { normalize_tag(value).map_err(bad_value)? };
+                self.fields.insert("tag");
+                Term::Tag { cmp: self.equality(cmp, field, token, true)?, value }
+            }
+            "status" => {
+                let negate = self.equality(cmp, field, token, false)? == Cmp::Ne;
+                let mut statuses = Vec::new();
+                for name in value.split(',') {
+                    match name.trim().to_lowercase().as_str() {
+                        "open" => statuses.extend(Status::ALL.iter().filter(|status| !status.is_closed())),
+                        "closed" => statuses.extend(Status::ALL.iter().filter(|status| status.is_closed())),
+                        name => statuses.push(name.parse().map_err(bad_value)?),
+                    }
+                }
+                self.fields.insert("status");
+                Term::Status { statuses, negate }
+            }
+            "project" | "proj" | "pro" => {
+                let value = none_or(value);
+                let cmp = match cmp {
+                    Cmp::Contains | Cmp::NotContains if value.is_some() => cmp,
+                    _ => self.equality(cmp, field, token, false)?,
+                };
+                let value = match (value, cmp) {
+                    (Some(value), Cmp::Contains | Cmp::NotContains) => Some(value.to_lowercase()),
+                    (value, _) => value.map(normalize_project).transpose().map_err(bad_value)?,
+                };
+                self.fields.insert("project");
+                Term::Project { cmp, value }
+            }
+            "priority" | "pri" => {
+                let cmp = self.ordered(cmp, field, token)?;
+                let value = Priority::parse_level(value).map_err(bad_value)?;
+                self.needs_value(value.is_some(), cmp, field, token)?;
+                self.fields.insert("priority");
+                Term::Priority { cmp, value }
+            }
+            "due" | "scheduled" | "sched" | "wait" | "created" | "entry" | "modified" | "completed" | "end" => {
+                let (kind, name) = match field {
+                    "due" => (DateKind::Due, "due"),
+                    "scheduled" | "sched" => (DateKind::Scheduled, "scheduled"),
+                    "wait" => (DateKind::Wait, "wait"),
+                    "created" | "entry" => (DateKind::Stamp(TimestampField::Created), "created"),
+                    "modified" => (DateKind::Stamp(TimestampField::Modified), "modified"),
+                    _ => (DateKind::Stamp(TimestampField::Completed), "completed"),
+                };
+                let cmp = self.ordered(cmp, field, token)?;
+                let value = none_or(value).map(|expr| {
+                    parse_date_expr(expr, self.now.date_naive()).map(|date| date.format("%Y-%m-%d").to_string())
+                }).transpose().map_err(bad_value)?;
+                self.needs_value(value.is_some(), cmp, field, token)?;
+                self.fields.insert(name);
+                Term::Date { kind, cmp, value }
+            }
+            "id" => {
+                let cmp = self.ordered(cmp, field, token)?;
+                let ids = value.split(',').map(|id| id.trim().parse::<u32>())
+                    .collect::<Result<Vec<u32>, _>>()
+                    .map_err(|_| self.error(format!("'id' needs task numbers like 3 or 3,5,8, got '{}'", value), &value_token))?;
+                if ids.len() > 1 && !matches!(cmp, Cmp::Eq | Cmp::Ne) {
+                    return Err(self.error("Only ':', '=' and '!=' take a list of ids", token));
+                }
+                Term::Id { cmp, ids }
+            }
+            "uuid" => {
+                let negate = self.equality(cmp, field, token, false)? == Cmp::Ne;
+                let prefix = value.to_lowercase().replace('-', "");
+                if prefix.is_empty() || !prefix.chars().all(|c| c.is_ascii_hexdigit()) {
+                    return Err(self.error(format!("'uuid' needs a UUID or UUID prefix, got '{}'", value), &value_token));
+                }
+                Term::Uuid { prefix, negate }
+            }
+            "parent" => {
+                let negate = self.equality(cmp, field, token, false)? == Cmp::Ne;
+                let id = none_or(value).map(|id| id.parse::<u32>())
+                    .transpose()
+                    .map_err(|_| self.error(format!("'parent' needs a task number, got '{}'", value), &value_token))?;
+                Term::Parent { id, negate }
+            }
+            "is" => {
+                let negate = self.equality(cmp, field, token, false)? == Cmp::Ne;
+                let name = value.to_lowercase();
+                let Some((_, flag)) = Flag::NAMES.iter().find(|(flag, _)| *flag == name) else {
+                    let names: Vec<&str> = Flag::NAMES.iter().map(|(name, _)| *name).collect();
+                    return Err(self.error(format!("Unknown condition '{}' (use {})", value, names.join(", ")), &value_token));
+                };
+                match flag {
+                    Flag::Open | Flag::Closed => self.fields.insert("status"),
+                    Flag::Waiting => self.fields.insert("wait"),
+                    _ => self.fields.insert("is"),
+                };
+                Term::Is { flag: *flag, negate }
+            }
+            name if self.udas.contains_key(name) => {
+                let op = match cmp {
+                    Cmp::Is | Cmp::Eq => UdaOp::Eq,
+                    Cmp::Ne => UdaOp::Ne,
+                    Cmp::Lt => UdaOp::Lt,
+                    Cmp::Le => UdaOp::Le,
+                    Cmp::Gt => UdaOp::Gt,
+                    Cmp::Ge => UdaOp::Ge,
+                    Cmp::Contains => UdaOp::Contains,
+                    Cmp::NotContains => return Err(self.error(format!("'{}' does not support '!~'", name), token)),
+                };
+                let value = if value.eq_ignore_ascii_case("none") { "" } else { value };
+                Term::Attr(UdaFilter::new(name, op, value, self.udas).map_err(bad_value)?)
+            }
+            _ => return Err(QueryError::new(format!(
+                "Unknown field '{}' (use status, project, tag, text, notes, priority, due, scheduled, wait, created, \
+                 modified, completed, id, uuid, parent, is or a declared attribute; quote text to search for it)", field),
+                self.query, token.start, token.start + index)),
+        };
+        Ok(term)
+    }
+
+    // Fields that only compare for (in)equality; `contains` also allows "~" and "!~"
+    fn equality(&self, cmp: Cmp, field: &str, token: &Token, contains: bool) -> Result<Cmp, QueryError> {
+        match cmp {
+            Cmp::Is | Cmp::Eq | Cmp::Ne => Ok(cmp),
+            Cmp::Contains | Cmp::NotContains if contains => Ok(cmp),
+            _ => Err(self.error(format!("'{}' only supports ':', '=' and '!='", field), token)),
+        }
+    }
+
+    // Fields that compare by order but not by substring
+    fn ordered(&self, cmp: Cmp, field: &str, token: &Token) -> Result<Cmp, QueryError> {
+        match cmp {
+            Cmp::Contains | Cmp::NotContains => Err(self.error(format!("'{}' does not support '~' (use :, =, !=, <, <=, > or >=)", field), token)),
+            Cmp::Is => Ok(Cmp::Eq),
+            _ => Ok(cmp),
+        }
+    }
+
+    // "none" can only be tested for (in)equality
+    fn needs_value(&self, has_value: bool, cmp: Cmp, field: &str, token: &Token) -> Result<(), QueryError> {
+        if !has_value && !matches!(cmp, Cmp::Eq | Cmp::Ne) {
+            return Err(self.error(format!("'{}' needs a value to compare with", field), token));
+        }
+        Ok(())
+    }
+}
+
+// Empty values and "none" mean the field is not set
+fn none_or(value: &str) -> Option<&str> {
+    if value.is_empty() || value.eq_ignore_ascii_case("none") { None } else { Some(value) }
+}
+
+// Error text without the "Invalid input:" prefix added by TaskError
+fn message(err: TaskError) -> String {
+    match err {
+        TaskError::InvalidInput(message) | TaskError::InvalidDate(message) => message,
+        err => err.to_string(),
+    }
+}
@@ -0,0 +1,227 @@
+use chrono::{Datelike, Days, Months, NaiveDate, Weekday};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use crate::task::{Task, TaskError, validate_date};
+
+// When the next instance of a recurring task is due
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum RecurRule {
+    Daily,
+    // On these weekdays (the due date's weekday when empty)
+    Weekly(Vec<Weekday>),
+    // On this day of the month, clamped to short months (the due date's day when None)
+    Monthly(Option<u32>),
+    // N days after the previous instance was completed
+    AfterCompletion(u32),
+}
+
+impl RecurRule {
+    // Next due date after an instance due on `due` was completed on `today`.
+    // Fixed schedules skip occurrences that already passed. None when the date would
+    // fall outside the calendar chrono can represent.
+    pub fn next_due(&self, due: Option<NaiveDate>, today: NaiveDate) -> Option<NaiveDate> {
+        let base = due.unwrap_or(today);
+        if let RecurRule::AfterCompletion(days) = self {
+            return today.checked_add_days(Days::new(u64::from(*days)));
+        }
+
+        let mut next = self.step(base, base)?;
+        while next < today {
+            next = self.step(next, base)?;
+        }
+        Some(next)
+    }
+
+    // The occurrence following `date`; `anchor` supplies defaults for weekday and day of month
+    fn step(&self, date: NaiveDate, anchor: NaiveDate) -> Option<NaiveDate> {
+        match self {
+            RecurRule::Daily => date.checked_add_days(Days::new(1)),
+            RecurRule::Weekly(days) => {
+                let days = if days.is_empty() { vec![anchor.weekday()] } else { days.clone() };
+                (1..=7).map_while(|offset| date.checked_add_days(Days::new(offset)))
+                    .find(|candidate| days.contains(&candidate.weekday()))
+            }
+            RecurRule::Monthly(day) => {
+                let day = day.unwrap_or(anchor.day());
+                let month = NaiveDate::from_ymd_opt(date.year(), date.month(), 1).unwrap_or(date);
+                // This month's occurrence if it is still ahead, otherwise next month's
+                let this_month = on_day(month, day);
+                if this_month > date {
+                    Some(this_month)
+                } else {
+                    Some(on_day(month.checked_add_months(Months::new(1))?, day))
+                }
+            }
+            RecurRule::AfterCompletion(days) => date.checked_add_days(Days::new(u64::from(*days))),
+        }
+    }
+}
+
+// `day` of the month starting at `first`, or the month's last day if it is shorter
+fn on_day(first: NaiveDate, day: u32) -> NaiveDate {
+    (1..=day).rev()
+        .find_map(|day| first.with_day(day))
+        .unwrap_or(first)
+}
+
+impl fmt::Display for RecurRule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RecurRule::Daily => write!(f, "daily"),
+            RecurRule::Weekly(days) if days.is_empty() => write!(f, "weekly"),
+            RecurRule::Weekly(days) => {
+                let names: Vec<String> = days.iter().map(|day| day.to_string().to_lowercase()).collect();
+                write!(f, "weekly {}", names.join(","))
+            }
+            RecurRule::Monthly(None) => write!(f, "monthly"),
+            RecurRule::Monthly(Some(day)) => write!(f, "monthly {}", day),
+            RecurRule::AfterCompletion(1) => write!(f, "every 1 day"),
+            RecurRule::AfterCompletion(days) => write!(f, "every {} days", days),
+        }
+    }
+}
+
+// Longest interval of "every N days", about ten years
+const MAX_INTERVAL_DAYS: u32 = 3660;
+
+// Accepts "daily", "weekly", "weekly mon,thu", "monthly", "monthly 15" and "every 3 days"
+impl FromStr for RecurRule {
+    type Err = TaskError;
+
+    fn from_str(rule: &str) -> Result<Self, Self::Err> {
+        let rule = rule.trim().to_lowercase();
+        let words: Vec<&str> = rule.split([' ', ':']).filter(|word| !word.is_empty()).collect();
+        let invalid = || TaskError::InvalidInput(format!(
+            "Invalid recurrence '{}' (use daily, weekly [mon,thu], monthly [15] or every N days)", rule));
+
+        match words.as_slice() {
+            ["daily"] => Ok(RecurRule::Daily),
+            ["weekly"] => Ok(RecurRule::Weekly(Vec::new())),
+            ["weekly", days @ ..] => {
+                let mut weekdays = Vec::new();
+                for day in days.join(",").split(',').filter(|day| !day.is_empty()) {
+                    let day: Weekday = day.parse().map_err(|_| invalid())?;
+                    if !weekdays.contains(&day) {
+                        weekdays.push(day);
+                    }
+                }
+                weekdays.sort_by_key(|day| day.num_days_from_monday());
+                Ok(RecurRule::Weekly(weekdays))
+            }
+            ["monthly"] => Ok(RecurRule::Monthly(None)),
+            ["monthly", day] => match day.parse() {
+                Ok(day) if (1..=31).contains(&day) => Ok(RecurRule::Monthly(Some(day))),
+                _ => Err(invalid()),
+            },
+            ["every", days] | ["every", days, "day" | "days"] => {
+                let days = days.trim_end_matches('d').parse().map_err(|_| invalid())?;
+                if days == 0 || days > MAX_INTERVAL_DAYS {
+                    return Err(TaskError::InvalidInput(format!(
+                        "Invalid recurrence '{}' (every N days needs N from 1 to {})", rule, MAX_INTERVAL_DAYS)));
+                }
+                Ok(RecurRule::AfterCompletion(days))
+            }
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl TryFrom<String> for RecurRule {
+    type Error = TaskError;
+
+    fn try_from(rule: String) -> Result<Self, Self::Error> {
+        rule.parse()
+    }
+}
+
+impl From<RecurRule> for String {
+    fn from(rule: RecurRule) -> Self {
+        rule.to_string()
+    }
+}
+
+// Recurrence settings of a task; the newest instance of a series carries them
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Recurrence {
+    pub rule: RecurRule,
+    // Last date a new instance may be due on (YYYY-MM-DD)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub until: Option<String>,
+    // A paused series spawns no new instances
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub paused: bool,
+}
+
+impl Recurrence {
+    pub fn new(rule: RecurRule) -> Recurrence {
+        Recurrence { rule, until: None, paused: false }
+    }
+}
+
+impl fmt::Display for Recurrence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.rule)?;
+        if let Some(until) = &self.until {
+            write!(f, " until {}", until)?;
+        }
+        if self.paused {
+            write!(f, " (paused)")?;
+        }
+        Ok(())
+    }
+}
+
+// Build the next instance of a recurring task that was just completed on `today`.
+// None when the task does not recur, the series is paused or it has ended, which includes
+// a next date past the year 9999 that the task files cannot hold.
+pub fn next_instance(task: &Task, id: u32, today: &str) -> Option<Task> {
+    let recur = task.recur.as_ref().filter(|recur| !recur.paused)?;
+    let parse = |date: &str| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok();
+    // Dates are only written when they pass the same check the loader applies
+    let format = |date: NaiveDate| {
+        let date = date.format("%Y-%m-%d").to_string();
+        validate_date(&date).ok().map(|_| date)
+    };
+
+    let today = parse(today)?;
+    let due = task.due_date.as_deref().and_then(parse);
+    let next_date = recur.rule.next_due(due, today)?;
+    let next = format(next_date)?;
+    if recur.until.as_ref().is_some_and(|until| next > *until) {
+        return None;
+    }
+
+    // Scheduled and wait dates keep their distance to the due date
+    let delta = next_date.signed_duration_since(due.unwrap_or(today));
+    let shift = |date: &Option<String>| match date.as_deref().and_then(parse) {
+        Some(date) => date.checked_add_signed(delta).and_then(format).map(Some),
+        None => Some(None),
+    };
+
+    Some(Task {
+        priority: task.priority,
+        notes: task.notes.clone(),
+        scheduled: shift(&task.scheduled)?,
+        wait: shift(&task.wait)?,
+        tags: task.tags.clone(),
+        project: task.project.clone(),
+        parent: task.parent,
+        due_time: task.due_time.clone(),
+        due_tz: task.due_tz.clone(),
+        recur: Some(recur.clone()),
+        udas: task.udas.clone(),
+        ..Task::new(id, task.text.clone(), Some(next))
+    })
+}
+
+// After tasks[index] was completed, append its next instance and move the recurrence
+// over to it, so reopening and completing the old one does not spawn twice.
+// Returns the index of the new instance.
+pub fn spawn_next(tasks: &mut Vec<Task>, index: usize, id: u32, today: &str) -> Option<usize> {
+    let next = next_instance(&tasks[index], id, today)?;
+    tasks[index].recur = None;
+    tasks.push(next);
+    Some(tasks.len() - 1)
+}
@@ -0,0 +1,70 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use crate::query::Query;
+use crate::store::write_atomic;
+use crate::task::TaskError;
+use crate::uda::UdaDef;
+
+// Named filters and the active context, stored in queries.json inside the data directory
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct SavedQueries {
+    // Filter expressions by name
+    #[serde(default)]
+    pub queries: BTreeMap<String, String>,
+    // Saved query applied to every listing, search and TUI view
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context: Option<String>,
+}
+
+impl SavedQueries {
+    // Load queries.json from the data directory (empty when missing)
+    pub fn load(dir: &Path) -> Result<SavedQueries, TaskError> {
+        let file_path = dir.join("queries.json");
+        if !file_path.exists() {
+            return Ok(SavedQueries::default());
+        }
+
+        let contents = fs::read_to_string(&file_path)?;
+        if contents.trim().is_empty() {
+            return Ok(SavedQueries::default());
+        }
+
+        Ok(serde_json::from_str(&contents)?)
+    }
+
+    // Write queries.json into the data directory
+    pub fn save(&self, dir: &Path) -> Result<(), TaskError> {
+        if !dir.exists() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let json = serde_json::to_string_pretty(self)?;
+        write_atomic(&dir.join("queries.json"), json.as_bytes())
+    }
+
+    // Filter of the active context, parsed; None when no context is set. A context naming a
+    // query that is missing (queries.json edited by hand) is an error rather than no context.
+    pub fn context_query(&self, udas: &BTreeMap<String, UdaDef>) -> Result<Option<Query>, TaskError> {
+        let Some(name) = &self.context else { return Ok(None) };
+        let Some(filter) = self.queries.get(name) else {
+            return Err(TaskError::InvalidInput(format!(
+                "Context '{}' names no saved query; save one with 'todo context {} <filter>' or run 'todo context clear'", name, name)));
+        };
+        Query::parse(filter, udas).map(Some).map_err(|err| TaskError::InvalidInput(format!(
+            "Context '{}' has an invalid filter; fix it with 'todo query save' or run 'todo context clear'\n{}", name, err)))
+    }
+}
+
+// Query names are words like "work" or "due-soon"
+pub fn validate_query_name(name: &str) -> Result<(), TaskError> {
+    let mut chars = name.chars();
+    let valid = chars.next().is_some_and(|c| c.is_ascii_lowercase() || c.is_ascii_digit())
+        && chars.all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '-');
+    if !valid {
+        return Err(TaskError::InvalidInput(format!(
+            "Invalid query name '{}' (use lowercase letters, digits, '_' and '-')", name)));
+    }
+    Ok(())
+}
@@ -0,0 +1,110 @@
+use serde::Serialize;
+use serde_json::{Map, Value, json};
+use crate::task::{Task, TaskError};
+
+// Version written into every task file. Bump it together with a new entry in MIGRATIONS.
+pub const SCHEMA_VERSION: u32 = 2;
+
+// A step upgrading an envelope from version N to N + 1 (index N in MIGRATIONS)
+type Migration = fn(Value) -> Result<Value, TaskError>;
+
+const MIGRATIONS: &[Migration] = &[
+    // 0 -> 1: bare task array wrapped in a versioned envelope
+    |value| Ok(json!({ "version": 1, "tasks": value })),
+    // 1 -> 2: "done": true/false replaced by a "status"
+    done_to_status,
+];
+
+fn done_to_status(mut value: Value) -> Result<Value, TaskError> {
+    if let Some(tasks) = value.get_mut("tasks").and_then(Value::as_array_mut) {
+        for task in tasks.iter_mut().filter_map(Value::as_object_mut) {
+            if let Some(done) = task.remove("done") {
+                let status = if done.as_bool() == Some(true) { "done" } else { "pending" };
+                task.insert("status".to_string(), json!(status));
+            }
+        }
+    }
+    value["version"] = json!(2);
+    Ok(value)
+}
+
+// Read the schema version of parsed file contents (a bare array is version 0)
+pub fn version_of(value: &Value) -> Result<u32, TaskError> {
+    match value {
+        Value::Array(_) => Ok(0),
+        Value::Object(map) => map.get("version")
+            .and_then(Value::as_u64)
+            .and_then(|version| u32::try_from(version).ok())
+            .ok_or_else(|| TaskError::InvalidInput("Task file has no valid \"version\" field".to_string())),
+        _ => Err(TaskError::InvalidInput("Task file must contain an array or an object".to_string())),
+    }
+}
+
+// Fail when the data was written by a newer schema than this build understands
+pub fn check_version(version: u32) -> Result<(), TaskError> {
+    if version > SCHEMA_VERSION {
+        return Err(TaskError::NewerSchema(version));
+    }
+    Ok(())
+}
+
+// On-disk layout written by this version
+#[derive(Serialize)]
+struct Envelope<'a> {
+    version: u32,
+    tasks: &'a [Task],
+    // Unknown top-level fields, kept as they were
+    #[serde(flatten)]
+    extra: Map<String, Value>,
+}
+
+// Run every migration needed to bring file contents up to SCHEMA_VERSION
+pub fn upgrade(mut value: Value) -> Result<Value, TaskError> {
+    let mut version = version_of(&value)?;
+    check_version(version)?;
+
+    while version < SCHEMA_VERSION {
+        value = MIGRATIONS[version as usize](value)?;
+        version += 1;
+    }
+
+    Ok(value)
+}
+
+// Parse task file contents of any supported version
+pub fn decode_tasks(contents: &str) -> Result<Vec<Task>, TaskError> {
+    let value: Value = serde_json::from_str(contents)?;
+    decode_value(value)
+}
+
+// Convert an already parsed envelope (or bare array) into tasks
+pub fn decode_value(value: Value) -> Result<Vec<Task>, TaskError> {
+    let Value::Object(mut envelope) = upgrade(value)? else {
+        return Err(TaskError::InvalidInput("Task file envelope must be an object".to_string()));
+    };
+
+    let tasks = envelope.remove("tasks").unwrap_or(Value::Array(Vec::new()));
+    Ok(serde_json::from_value(tasks)?)
+}
+
+// Serialize tasks as a current envelope. Top-level fields of the existing
+// contents that this version does not know about are carried over.
+pub fn encode_tasks(tasks: &[Task], existing: Option<&str>) -> Result<String, TaskError> {
+    let mut extra = Map::new();
+
+    // Unreadable existing contents are being replaced by recovery, so only a valid newer version stops the write
+    if let Some(existing) = existing
+        && let Ok(value) = serde_json::from_str::<Value>(existing)
+        && let Ok(version) = version_of(&value)
+    {
+        check_version(version)?;
+        if let Value::Object(map) = value {
+            extra = map;
+            extra.remove("version");
+            extra.remove("tasks");
+        }
+    }
+
+    let envelope = Envelope { version: SCHEMA_VERSION, tasks, extra };
+    Ok(serde_json::to_string_pretty(&envelope)?)
+}
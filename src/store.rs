@@ -0,0 +1,528 @@
+use chrono::Utc;
+use std::cell::RefCell;
+use std::fmt;
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+use crate::backup::{Backup, create_backup, create_sqlite_backup, find_backup, list_backups};
+use crate::config::{BackendKind, Config};
+use crate::journal::{Journal, Operation};
+use crate::saved::SavedQueries;
+use crate::schema::decode_tasks;
+use crate::task::{Task, TaskError, assign_missing_uuids, validate_date};
+use crate::uda::{validate_name, validate_udas};
+
+mod json;
+mod memory;
+mod sqlite;
+
+pub use json::{JsonBackend, validate_json_file};
+pub use memory::MemoryBackend;
+pub use sqlite::SqliteBackend;
+
+// Where the data directory location came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DataDirSource {
+    Flag,
+    Env,
+    Xdg,
+    Default,
+    Legacy,
+}
+
+impl fmt::Display for DataDirSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DataDirSource::Flag => write!(f, "--data-dir flag"),
+            DataDirSource::Env => write!(f, "TODO_DIR environment variable"),
+            DataDirSource::Xdg => write!(f, "XDG_DATA_HOME"),
+            DataDirSource::Default => write!(f, "default (~/.local/share/todo)"),
+            DataDirSource::Legacy => write!(f, "legacy (~/.todo)"),
+        }
+    }
+}
+
+// Resolved data directory together with its origin
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DataDir {
+    pub path: PathBuf,
+    pub source: DataDirSource,
+}
+
+// Resolve the data directory: flag, TODO_DIR, $XDG_DATA_HOME/todo (~/.local/share/todo when
+// unset), or an existing ~/.todo while the XDG directory has not been created
+pub fn resolve_data_dir(flag: Option<&Path>) -> Result<DataDir, TaskError> {
+    resolve_data_dir_with(flag, |name| std::env::var(name).ok())
+}
+
+// Same as resolve_data_dir, reading environment variables through `env`; empty values count as unset
+pub fn resolve_data_dir_with(flag: Option<&Path>, env: impl Fn(&str) -> Option<String>) -> Result<DataDir, TaskError> {
+    let env = |name: &str| env(name).filter(|value| !value.trim().is_empty());
+    if let Some(path) = flag {
+        return Ok(DataDir { path: path.to_path_buf(), source: DataDirSource::Flag });
+    }
+
+    if let Some(dir) = env("TODO_DIR") {
+        return Ok(DataDir { path: PathBuf::from(dir), source: DataDirSource::Env });
+    }
+
+    let home = env("HOME").or_else(|| env("USERPROFILE")).map(PathBuf::from);
+    let xdg_dir = match env("XDG_DATA_HOME") {
+        Some(xdg_home) => Some(DataDir { path: PathBuf::from(xdg_home).join("todo"), source: DataDirSource::Xdg }),
+        None => home.as_ref().map(|home| DataDir { path: home.join(".local/share/todo"), source: DataDirSource::Default }),
+    };
+
+    // Keep using an existing ~/.todo so moving to the XDG location never hides old data
+    if let Some(legacy_dir) = home.map(|home| home.join(".todo"))
+        && legacy_dir.exists()
+        && !xdg_dir.as_ref().is_some_and(|dir| dir.path.exists())
+    {
+        return Ok(DataDir { path: legacy_dir, source: DataDirSource::Legacy });
+    }
+
+    xdg_dir.ok_or_else(|| TaskError::InvalidInput("Could not find home directory".to_string()))
+}
+
+// Storage backend behind TaskStore
+pub trait StorageBackend {
+    // Short backend name shown in `todo info`
+    fn name(&self) -> &'static str;
+    // Human readable location of the stored tasks
+    fn location(&self) -> String;
+    fn load_tasks(&self) -> Result<Vec<Task>, TaskError>;
+    fn save_tasks(&self, tasks: &[Task]) -> Result<(), TaskError>;
+    fn load_removed(&self) -> Result<Vec<Task>, TaskError>;
+    fn save_removed(&self, tasks: &[Task]) -> Result<(), TaskError>;
+}
+
+// Open the backend of the given kind inside a data directory
+pub fn open_backend(dir: &Path, kind: BackendKind) -> Result<Box<dyn StorageBackend>, TaskError> {
+    match kind {
+        BackendKind::Json => Ok(Box::new(JsonBackend::new(dir))),
+        BackendKind::Sqlite => Ok(Box::new(SqliteBackend::open(dir)?)),
+    }
+}
+
+// How long lock() waits for another process before giving up
+const LOCK_TIMEOUT: Duration = Duration::from_secs(2);
+
+// Advisory lock on the data directory, released when dropped
+pub struct StoreLock {
+    _file: Option<File>,
+}
+
+// Write a file by writing a temp file next to it and renaming it into place
+pub(crate) fn write_atomic(path: &Path, contents: &[u8]) -> Result<(), TaskError> {
+    let mut tmp_name = path.file_name().unwrap_or_default().to_os_string();
+    tmp_name.push(".tmp");
+    let tmp_path = path.with_file_name(tmp_name);
+
+    let mut file = File::create(&tmp_path)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    fs::rename(&tmp_path, path)?;
+    Ok(())
+}
+
+// Task storage used by commands and the TUI
+pub struct TaskStore {
+    backend: Box<dyn StorageBackend>,
+    dir: Option<PathBuf>,
+    source: Option<DataDirSource>,
+    config: Config,
+    // Undo journal for stores without a data directory
+    memory_journal: RefCell<Journal>,
+    // Saved queries for stores without a data directory
+    memory_queries: RefCell<SavedQueries>,
+}
+
+impl TaskStore {
+    // Store in the given directory, using the backend selected in its config.json
+    pub fn open(dir: impl Into<PathBuf>) -> Result<TaskStore, TaskError> {
+        let dir = dir.into();
+        let config = Config::load(&dir)?;
+        let backend = open_backend(&dir, config.backend)?;
+        Ok(TaskStore { backend, dir: Some(dir), source: None, config, memory_journal: RefCell::default(), memory_queries: RefCell::default() })
+    }
+
+    // Store backed by a resolved data directory (remembers where it came from)
+    pub fn open_data_dir(data_dir: DataDir) -> Result<TaskStore, TaskError> {
+        let mut store = TaskStore::open(data_dir.path)?;
+        store.source = Some(data_dir.source);
+        Ok(store)
+    }
+
+    // Store in the directory picked by resolve_data_dir()
+    pub fn open_default(flag: Option<&Path>) -> Result<TaskStore, TaskError> {
+        TaskStore::open_data_dir(resolve_data_dir(flag)?)
+    }
+
+    // Store using a custom backend
+    pub fn with_backend(backend: Box<dyn StorageBackend>) -> TaskStore {
+        TaskStore { backend, dir: None, source: None, config: Config::default(), memory_journal: RefCell::default(), memory_queries: RefCell::default() }
+    }
+
+    // Store that never touches the file system
+    pub fn in_memory() -> TaskStore {
+        TaskStore::with_backend(Box::new(MemoryBackend::default()))
+    }
+
+    // In-memory store seeded from a JSON task array
+    pub fn from_json(json: &str) -> Result<TaskStore, TaskError> {
+        let tasks = parse_tasks(json)?;
+        Ok(TaskStore::with_backend(Box::new(MemoryBackend::new(tasks))))
+    }
+
+    // Data directory, or None for stores without one
+    pub fn dir(&self) -> Option<&Path> {
+        self.dir.as_deref()
+    }
+
+    // Where the data directory came from, if it was resolved
+    pub fn source(&self) -> Option<DataDirSource> {
+        self.source
+    }
+
+    // Settings loaded from config.json
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    // Name of the storage backend in use
+    pub fn backend_name(&self) -> &'static str {
+        self.backend.name()
+    }
+
+    // Human readable location of the stored tasks (for user info)
+    pub fn location(&self) -> String {
+        self.backend.location()
+    }
+
+    // Take the store lock without waiting
+    pub fn try_lock(&self) -> Result<StoreLock, TaskError> {
+        let Some(dir) = &self.dir else {
+            return Ok(StoreLock { _file: None });
+        };
+
+        if !dir.exists() {
+            fs::create_dir_all(dir)?;
+        }
+        let lock_path = dir.join("todos.lock");
+        let file = OpenOptions::new().create(true).truncate(false).write(true).open(&lock_path)?;
+
+        match file.try_lock() {
+            Ok(()) => Ok(StoreLock { _file: Some(file) }),
+            Err(TryLockError::WouldBlock) => Err(TaskError::Locked(lock_path)),
+            Err(TryLockError::Error(err)) => Err(TaskError::IoError(err)),
+        }
+    }
+
+    // Take the store lock for a read-modify-write cycle, waiting briefly for other processes
+    pub fn lock(&self) -> Result<StoreLock, TaskError> {
+        let started = Instant::now();
+        loop {
+            match self.try_lock() {
+                Err(TaskError::Locked(_)) if started.elapsed() < LOCK_TIMEOUT => {
+                    thread::sleep(Duration::from_millis(50));
+                }
+                result => return result,
+            }
+        }
+    }
+
+    // Load tasks, printing a warning and returning an empty list on failure.
+    // Attribute values that do not fit config.json are reported but kept.
+    pub fn load(&self) -> Vec<Task> {
+        match self.load_result() {
+            Ok(tasks) => {
+                for problem in self.attribute_problems(&tasks) {
+                    eprintln!("Warning: {}", problem);
+                }
+                tasks
+            }
+            Err(err) => {
+                eprintln!("Warning: Could not load tasks: {}", err);
+                Vec::new()
+            }
+        }
+    }
+
+    // Undeclared attributes and values that do not match their declaration
+    pub fn attribute_problems(&self, tasks: &[Task]) -> Vec<String> {
+        validate_udas(tasks, &self.config.udas)
+    }
+
+    // Load tasks with error handling
+    pub fn load_result(&self) -> Result<Vec<Task>, TaskError> {
+        let mut tasks = self.backend.load_tasks()?;
+        if assign_missing_uuids(&mut tasks) {
+            self.persist_migration(|| self.backend.save_tasks(&tasks))?;
+        }
+        Ok(tasks)
+    }
+
+    // Write back tasks that were given UUIDs on load. Callers holding the lock
+    // save the migrated tasks themselves, so this is skipped while it is taken.
+    fn persist_migration(&self, save: impl FnOnce() -> Result<(), TaskError>) -> Result<(), TaskError> {
+        match self.try_lock() {
+            Ok(_lock) => save(),
+            Err(TaskError::Locked(_)) => Ok(()),
+            Err(err) => Err(err),
+        }
+    }
+
+    // Next free short ID, skipping IDs still used by removed tasks
+    pub fn next_id(&self, tasks: &[Task]) -> u32 {
+        let removed = self.load_removed();
+        tasks.iter().chain(removed.iter()).map(|t| t.id).max().unwrap_or(0) + 1
+    }
+
+    // Save tasks, keeping the previous contents as a backup generation
+    pub fn save(&self, tasks: &[Task]) -> Result<(), TaskError> {
+        // Never write a file the loader would reject as corrupted
+        validate_tasks(tasks)?;
+        // Backups are housekeeping: failing to make one must not lose the change itself
+        if let Err(err) = self.backup_current(false) {
+            eprintln!("Warning: Could not back up tasks before saving: {}", err);
+        }
+        self.backend.save_tasks(tasks)
+    }
+
+    // Keep the stored tasks as a backup generation: a JSON copy before every save, or a copy
+    // of todos.db at most once per configured interval (always when `force` is set)
+    fn backup_current(&self, force: bool) -> Result<(), TaskError> {
+        let Some(dir) = &self.dir else { return Ok(()) };
+        match self.config.backend {
+            BackendKind::Json => {
+                let current = self.backend.load_tasks()?;
+                if !current.is_empty() {
+                    create_backup(dir, &current, &self.config.backups)?;
+                }
+                Ok(())
+            }
+            BackendKind::Sqlite => create_sqlite_backup(dir, &self.config.backups, force),
+        }
+    }
+
+    // Save tasks (fallback version that prints errors)
+    pub fn save_safe(&self, tasks: &[Task]) {
+        if let Err(err) = self.save(tasks) {
+            eprintln!("Error: Could not save tasks: {}", err);
+        }
+    }
+
+    // Save tasks and record the change in the undo journal
+    pub fn save_recorded(&self, tasks: &[Task], operation: Operation) -> Result<(), TaskError> {
+        self.save(tasks)?;
+        self.record(operation)
+    }
+
+    // Save and record (fallback version that prints errors)
+    pub fn save_recorded_safe(&self, tasks: &[Task], operation: Operation) {
+        if let Err(err) = self.save_recorded(tasks, operation) {
+            eprintln!("Error: Could not save tasks: {}", err);
+        }
+    }
+
+    // Load the undo journal
+    pub fn journal(&self) -> Result<Journal, TaskError> {
+        match &self.dir {
+            Some(dir) => Journal::load(dir),
+            None => Ok(self.memory_journal.borrow().clone()),
+        }
+    }
+
+    fn save_journal(&self, journal: &Journal) -> Result<(), TaskError> {
+        match &self.dir {
+            Some(dir) => journal.save(dir),
+            None => {
+                *self.memory_journal.borrow_mut() = journal.clone();
+                Ok(())
+            }
+        }
+    }
+
+    // Load the saved queries and active context
+    pub fn saved_queries(&self) -> Result<SavedQueries, TaskError> {
+        match &self.dir {
+            Some(dir) => SavedQueries::load(dir),
+            None => Ok(self.memory_queries.borrow().clone()),
+        }
+    }
+
+    // Store the saved queries and active context
+    pub fn save_queries(&self, saved: &SavedQueries) -> Result<(), TaskError> {
+        match &self.dir {
+            Some(dir) => saved.save(dir),
+            None => {
+                *self.memory_queries.borrow_mut() = saved.clone();
+                Ok(())
+            }
+        }
+    }
+
+    // Append an operation to the undo journal
+    pub fn record(&self, operation: Operation) -> Result<(), TaskError> {
+        if operation.is_empty() {
+            return Ok(());
+        }
+
+        let mut journal = self.journal()?;
+        journal.push(operation);
+        self.save_journal(&journal)
+    }
+
+    // Undo the most recent operation, returning it (None when nothing is left to undo)
+    pub fn undo(&self) -> Result<Option<Operation>, TaskError> {
+        let mut journal = self.journal()?;
+        if journal.position == 0 {
+            return Ok(None);
+        }
+        journal.position -= 1;
+        let operation = journal.entries[journal.position].clone();
+
+        let mut tasks = self.load_result()?;
+        operation.revert(&mut tasks);
+        self.save(&tasks)?;
+
+        if operation.touches_removed() {
+            let mut removed = self.load_removed_result()?;
+            operation.untrash(&mut removed);
+            self.save_removed(&removed)?;
+        }
+
+        self.save_journal(&journal)?;
+        Ok(Some(operation))
+    }
+
+    // Redo the most recently undone operation, returning it (None when nothing is left to redo)
+    pub fn redo(&self) -> Result<Option<Operation>, TaskError> {
+        let mut journal = self.journal()?;
+        let Some(operation) = journal.entries.get(journal.position).cloned() else {
+            return Ok(None);
+        };
+        journal.position += 1;
+
+        let mut tasks = self.load_result()?;
+        operation.apply(&mut tasks);
+        self.save(&tasks)?;
+
+        if !operation.trashed.is_empty() {
+            self.trash_result(operation.trashed.clone())?;
+        }
+        if !operation.removed_edits.is_empty() {
+            let mut removed = self.load_removed_result()?;
+            operation.reapply_removed_edits(&mut removed);
+            self.save_removed(&removed)?;
+        }
+
+        self.save_journal(&journal)?;
+        Ok(Some(operation))
+    }
+
+    // Backup generations, newest first
+    pub fn backups(&self) -> Result<Vec<Backup>, TaskError> {
+        match &self.dir {
+            Some(dir) => list_backups(dir),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    // Find a backup generation (1 = newest)
+    pub fn backup(&self, generation: usize) -> Result<Backup, TaskError> {
+        let dir = self.dir.as_deref()
+            .ok_or_else(|| TaskError::InvalidInput("This store keeps no backups".to_string()))?;
+        find_backup(dir, generation)
+    }
+
+    // Restore tasks from a backup generation (the current tasks become the newest backup).
+    // The restore is journaled so undo goes back to the tasks it replaced.
+    pub fn restore_generation(&self, generation: usize) -> Result<Vec<Task>, TaskError> {
+        let tasks = self.backup(generation)?.load()?;
+        let before = self.load_result()?;
+        self.backup_current(true)?;
+        self.backend.save_tasks(&tasks)?;
+        self.record(Operation::new(format!("restore backup {}", generation), &before, &tasks))?;
+        Ok(tasks)
+    }
+
+    // Restore from the newest backup generation
+    pub fn restore_from_backup(&self) -> Result<(), TaskError> {
+        self.restore_generation(1).map(|_| ())
+    }
+
+    // Load removed tasks, printing a warning and returning an empty list on failure
+    pub fn load_removed(&self) -> Vec<Task> {
+        match self.load_removed_result() {
+            Ok(tasks) => tasks,
+            Err(err) => {
+                eprintln!("Warning: Could not load removed tasks: {}", err);
+                Vec::new()
+            }
+        }
+    }
+
+    // Load removed tasks with error handling
+    pub fn load_removed_result(&self) -> Result<Vec<Task>, TaskError> {
+        let mut tasks = self.backend.load_removed()?;
+        if assign_missing_uuids(&mut tasks) {
+            self.persist_migration(|| self.backend.save_removed(&tasks))?;
+        }
+        Ok(tasks)
+    }
+
+    // Save removed tasks
+    pub fn save_removed(&self, tasks: &[Task]) -> Result<(), TaskError> {
+        self.backend.save_removed(tasks)
+    }
+
+    // Move tasks to removed storage
+    pub fn trash(&self, tasks_to_remove: Vec<Task>) {
+        if let Err(err) = self.trash_result(tasks_to_remove) {
+            eprintln!("Error: Could not add tasks to removed storage: {}", err);
+        }
+    }
+
+    // Move tasks to removed storage with error handling
+    pub fn trash_result(&self, tasks_to_remove: Vec<Task>) -> Result<(), TaskError> {
+        let mut removed_tasks = self.load_removed_result()?;
+        let removed_at = Utc::now();
+        removed_tasks.extend(tasks_to_remove.into_iter().map(|task| Task { removed_at: Some(removed_at), ..task }));
+        self.save_removed(&removed_tasks)
+    }
+}
+
+// Parse and validate task file contents
+fn parse_tasks(contents: &str) -> Result<Vec<Task>, TaskError> {
+    if contents.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+
+    // Parse any supported schema version, migrating older layouts
+    let tasks = decode_tasks(contents)?;
+
+    validate_tasks(&tasks)?;
+    Ok(tasks)
+}
+
+// Validate each loaded task
+fn validate_tasks(tasks: &[Task]) -> Result<(), TaskError> {
+    for task in tasks {
+        if task.text.is_empty() {
+            return Err(TaskError::InvalidInput(format!("Task {} has empty text", task.id)));
+        }
+        if task.text.len() > 500 {
+            return Err(TaskError::InvalidInput(format!("Task {} text too long", task.id)));
+        }
+        if let Some(ref due_date) = task.due_date {
+            validate_date(due_date)?;
+        }
+        for name in task.udas.keys() {
+            validate_name(name)?;
+        }
+    }
+
+    Ok(())
+}
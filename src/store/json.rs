@@ -0,0 +1,152 @@
+use std::fs::{self, OpenOptions};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use crate::task::{Task, TaskError};
+use crate::backup::list_backups;
+use crate::schema::{decode_tasks, encode_tasks};
+use super::{StorageBackend, parse_tasks, write_atomic};
+
+// Backend storing todos.json and removed.json in the data directory
+pub struct JsonBackend {
+    dir: PathBuf,
+}
+
+impl JsonBackend {
+    pub fn new(dir: impl Into<PathBuf>) -> JsonBackend {
+        JsonBackend { dir: dir.into() }
+    }
+
+    // Get the full path to a file in the data directory, creating the directory if needed
+    fn file_path(&self, name: &str) -> Result<PathBuf, TaskError> {
+        if !self.dir.exists() {
+            fs::create_dir_all(&self.dir)?;
+        }
+        Ok(self.dir.join(name))
+    }
+}
+
+impl StorageBackend for JsonBackend {
+    fn name(&self) -> &'static str {
+        "json"
+    }
+
+    fn location(&self) -> String {
+        self.dir.join("todos.json").to_string_lossy().to_string()
+    }
+
+    fn load_tasks(&self) -> Result<Vec<Task>, TaskError> {
+        let file_path = self.file_path("todos.json")?;
+
+        // If file doesn't exist, return empty list
+        if !file_path.exists() {
+            return Ok(Vec::new());
+        }
+
+        // Try to validate and load the main file
+        match validate_json_file(&file_path) {
+            Ok(tasks) => Ok(tasks),
+            Err(err @ TaskError::NewerSchema(_)) => Err(err),
+            Err(_) => {
+                eprintln!("Warning: Main task file is corrupted, attempting to restore from backup...");
+
+                // Try the newest backup generation that still loads
+                for backup in list_backups(&self.dir)? {
+                    if let Ok(tasks) = backup.load() {
+                        write_tasks_file(&file_path, &tasks)?;
+                        eprintln!("✅ Successfully restored tasks from backup {} ({})", backup.generation, backup.created_local());
+                        return Ok(tasks);
+                    }
+                }
+
+                eprintln!("❌ No usable backup available, creating new empty task list...");
+                Ok(Vec::new())
+            }
+        }
+    }
+
+    fn save_tasks(&self, tasks: &[Task]) -> Result<(), TaskError> {
+        let file_path = self.file_path("todos.json")?;
+        write_tasks_file(&file_path, tasks)
+    }
+
+    fn load_removed(&self) -> Result<Vec<Task>, TaskError> {
+        let file_path = self.file_path("removed.json")?;
+
+        // If file doesn't exist, return empty list
+        if !file_path.exists() {
+            return Ok(Vec::new());
+        }
+
+        // Try to validate and load the file
+        match validate_json_file(&file_path) {
+            Ok(tasks) => Ok(tasks),
+            Err(err @ TaskError::NewerSchema(_)) => Err(err),
+            Err(_) => {
+                eprintln!("Warning: Removed tasks file is corrupted, attempting recovery...");
+
+                // Try to restore from backup
+                let backup_path = file_path.with_extension("json.backup");
+                if backup_path.exists() {
+                    match fs::read(&backup_path).map_err(TaskError::from).and_then(|data| write_atomic(&file_path, &data)) {
+                        Ok(_) => {
+                            match validate_json_file(&file_path) {
+                                Ok(tasks) => {
+                                    eprintln!("✅ Successfully restored removed tasks from backup");
+                                    Ok(tasks)
+                                },
+                                Err(_) => {
+                                    eprintln!("❌ Backup file is also corrupted, starting with empty removed list");
+                                    Ok(Vec::new())
+                                }
+                            }
+                        },
+                        Err(_) => {
+                            eprintln!("❌ Could not restore from backup, starting with empty removed list");
+                            Ok(Vec::new())
+                        }
+                    }
+                } else {
+                    eprintln!("❌ No backup available, starting with empty removed list");
+                    Ok(Vec::new())
+                }
+            }
+        }
+    }
+
+    fn save_removed(&self, tasks: &[Task]) -> Result<(), TaskError> {
+        let file_path = self.file_path("removed.json")?;
+
+        // Create backup before saving
+        create_backup(&file_path)?;
+        write_tasks_file(&file_path, tasks)
+    }
+}
+
+// Write a task list to disk, refusing to replace a file from a newer schema
+fn write_tasks_file(file_path: &Path, tasks: &[Task]) -> Result<(), TaskError> {
+    let existing = fs::read_to_string(file_path).ok();
+    let json = encode_tasks(tasks, existing.as_deref())?;
+
+    // Validate JSON before writing
+    decode_tasks(&json)?;
+
+    write_atomic(file_path, json.as_bytes())
+}
+
+// Create backup of existing file
+fn create_backup(file_path: &Path) -> Result<(), TaskError> {
+    if file_path.exists() {
+        let backup_path = file_path.with_extension("json.backup");
+        write_atomic(&backup_path, &fs::read(file_path)?)?;
+    }
+    Ok(())
+}
+
+// Validate JSON file integrity
+pub fn validate_json_file(file_path: &Path) -> Result<Vec<Task>, TaskError> {
+    let mut file = OpenOptions::new().read(true).open(file_path)?;
+    let mut contents = String::new();
+    file.read_to_string(&mut contents)?;
+
+    parse_tasks(&contents)
+}
@@ -0,0 +1,49 @@
+use std::cell::RefCell;
+use crate::task::{Task, TaskError};
+use super::StorageBackend;
+
+#[derive(Default)]
+struct MemoryData {
+    tasks: Vec<Task>,
+    removed: Vec<Task>,
+}
+
+// Backend that keeps everything in memory (embedding and tests)
+#[derive(Default)]
+pub struct MemoryBackend {
+    data: RefCell<MemoryData>,
+}
+
+impl MemoryBackend {
+    pub fn new(tasks: Vec<Task>) -> MemoryBackend {
+        MemoryBackend { data: RefCell::new(MemoryData { tasks, ..MemoryData::default() }) }
+    }
+}
+
+impl StorageBackend for MemoryBackend {
+    fn name(&self) -> &'static str {
+        "memory"
+    }
+
+    fn location(&self) -> String {
+        "(in memory)".to_string()
+    }
+
+    fn load_tasks(&self) -> Result<Vec<Task>, TaskError> {
+        Ok(self.data.borrow().tasks.clone())
+    }
+
+    fn save_tasks(&self, tasks: &[Task]) -> Result<(), TaskError> {
+        self.data.borrow_mut().tasks = tasks.to_vec();
+        Ok(())
+    }
+
+    fn load_removed(&self) -> Result<Vec<Task>, TaskError> {
+        Ok(self.data.borrow().removed.clone())
+    }
+
+    fn save_removed(&self, tasks: &[Task]) -> Result<(), TaskError> {
+        self.data.borrow_mut().removed = tasks.to_vec();
+        Ok(())
+    }
+}
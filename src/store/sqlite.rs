@@ -0,0 +1,172 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use rusqlite::{Connection, OpenFlags, params};
+use serde_json::{Value, json};
+use crate::schema::{SCHEMA_VERSION, check_version, decode_value};
+use crate::task::{Task, TaskError};
+use super::{StorageBackend, validate_tasks};
+
+// Backend storing tasks as rows in todos.db, updating only what changed
+pub struct SqliteBackend {
+    conn: Connection,
+    path: PathBuf,
+}
+
+impl SqliteBackend {
+    // Open (or create) todos.db inside the data directory
+    pub fn open(dir: &Path) -> Result<SqliteBackend, TaskError> {
+        if !dir.exists() {
+            fs::create_dir_all(dir)?;
+        }
+
+        SqliteBackend::open_file(dir.join("todos.db"))
+    }
+
+    // Open (or create) a database file
+    fn open_file(path: PathBuf) -> Result<SqliteBackend, TaskError> {
+        let conn = Connection::open(&path)?;
+        conn.busy_timeout(Duration::from_secs(2))?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS tasks (
+                id INTEGER PRIMARY KEY,
+                data TEXT NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS removed (
+                seq INTEGER PRIMARY KEY AUTOINCREMENT,
+                data TEXT NOT NULL
+            );",
+        )?;
+
+        let backend = SqliteBackend { conn, path };
+        backend.migrate()?;
+        Ok(backend)
+    }
+
+    // Read the tasks of a database file without writing to it, e.g. a backup generation.
+    // Rows from an older schema are upgraded in memory only.
+    pub fn read_tasks(path: &Path) -> Result<Vec<Task>, TaskError> {
+        let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX)?;
+        let backend = SqliteBackend { conn, path: path.to_path_buf() };
+        let version = backend.version()?;
+        check_version(version)?;
+        backend.read_table("SELECT data FROM tasks ORDER BY id", version)
+    }
+
+    // Schema version of the stored rows (kept in PRAGMA user_version)
+    fn version(&self) -> Result<u32, TaskError> {
+        Ok(self.conn.query_row("PRAGMA user_version", [], |row| row.get(0))?)
+    }
+
+    // Bring rows written by an older schema up to date, refusing newer ones
+    fn migrate(&self) -> Result<(), TaskError> {
+        let version = self.version()?;
+        check_version(version)?;
+        if version == SCHEMA_VERSION {
+            return Ok(());
+        }
+
+        let tasks = self.read_table("SELECT data FROM tasks ORDER BY id", version)?;
+        let removed = self.read_table("SELECT data FROM removed ORDER BY seq", version)?;
+        self.save_tasks(&tasks)?;
+        self.save_removed(&removed)?;
+        self.conn.pragma_update(None, "user_version", SCHEMA_VERSION)?;
+        Ok(())
+    }
+
+    // Read every task row of a table in storage order, upgrading from the given schema version
+    fn read_table(&self, sql: &str, version: u32) -> Result<Vec<Task>, TaskError> {
+        let mut stmt = self.conn.prepare(sql)?;
+        let rows = stmt.query_map([], |row| row.get::<_, String>(0))?;
+
+        let mut values = Vec::new();
+        for data in rows {
+            values.push(serde_json::from_str::<Value>(&data?)?);
+        }
+
+        // Rows are migrated through the same pipeline as JSON files
+        let envelope = if version == 0 { Value::Array(values) } else { json!({ "version": version, "tasks": values }) };
+        let tasks = decode_value(envelope)?;
+        validate_tasks(&tasks)?;
+        Ok(tasks)
+    }
+}
+
+impl StorageBackend for SqliteBackend {
+    fn name(&self) -> &'static str {
+        "sqlite"
+    }
+
+    fn location(&self) -> String {
+        self.path.to_string_lossy().to_string()
+    }
+
+    fn load_tasks(&self) -> Result<Vec<Task>, TaskError> {
+        self.read_table("SELECT data FROM tasks ORDER BY id", SCHEMA_VERSION)
+    }
+
+    fn save_tasks(&self, tasks: &[Task]) -> Result<(), TaskError> {
+        check_version(self.version()?)?;
+        let tx = self.conn.unchecked_transaction()?;
+
+        let mut existing: HashMap<u32, String> = HashMap::new();
+        {
+            let mut stmt = tx.prepare("SELECT id, data FROM tasks")?;
+            let rows = stmt.query_map([], |row| Ok((row.get::<_, u32>(0)?, row.get::<_, String>(1)?)))?;
+            for row in rows {
+                let (id, data) = row?;
+                existing.insert(id, data);
+            }
+        }
+
+        // Write only rows that are new or changed
+        for task in tasks {
+            let data = serde_json::to_string(task)?;
+            if existing.remove(&task.id).as_ref() != Some(&data) {
+                tx.execute("INSERT OR REPLACE INTO tasks (id, data) VALUES (?1, ?2)", params![task.id, data])?;
+            }
+        }
+
+        // Anything left over was deleted
+        for id in existing.keys() {
+            tx.execute("DELETE FROM tasks WHERE id = ?1", params![id])?;
+        }
+
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn load_removed(&self) -> Result<Vec<Task>, TaskError> {
+        self.read_table("SELECT data FROM removed ORDER BY seq", SCHEMA_VERSION)
+    }
+
+    fn save_removed(&self, tasks: &[Task]) -> Result<(), TaskError> {
+        check_version(self.version()?)?;
+        let tx = self.conn.unchecked_transaction()?;
+
+        let stored: Vec<String> = {
+            let mut stmt = tx.prepare("SELECT data FROM removed ORDER BY seq")?;
+            let rows = stmt.query_map([], |row| row.get::<_, String>(0))?;
+            rows.collect::<Result<_, _>>()?
+        };
+        let serialized = tasks.iter()
+            .map(serde_json::to_string)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // Removed tasks are usually only appended, so keep the existing rows when possible
+        let keep = if serialized.starts_with(&stored) {
+            stored.len()
+        } else {
+            tx.execute("DELETE FROM removed", [])?;
+            0
+        };
+
+        for data in &serialized[keep..] {
+            tx.execute("INSERT INTO removed (data) VALUES (?1)", params![data])?;
+        }
+
+        tx.commit()?;
+        Ok(())
+    }
+}
@@ -1,17 +1,29 @@
-use serde::{Deserialize, Serialize};
-use std::fs::{self, OpenOptions};
-use std::io::Read;
-use std::path::PathBuf;
+use chrono::{DateTime, Local, NaiveDate, NaiveTime, TimeDelta, TimeZone, Utc};
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
 use std::error::Error;
+use std::path::PathBuf;
 use std::fmt;
+use std::str::FromStr;
+use uuid::Uuid;
+use crate::dates::parse_timezone;
+use crate::recur::Recurrence;
+use crate::uda::UdaValue;
 
 // Custom error type for task operations
 #[derive(Debug)]
 pub enum TaskError {
     IoError(std::io::Error),
     JsonError(serde_json::Error),
+    DatabaseError(rusqlite::Error),
     InvalidDate(String),
     InvalidInput(String),
+    Locked(PathBuf),
+    // Data written by a newer schema version than this build supports
+    NewerSchema(u32),
+    // The current date could not be determined
+    ClockUnavailable(String),
 }
 
 impl fmt::Display for TaskError {
@@ -19,8 +31,14 @@ impl fmt::Display for TaskError {
         match self {
             TaskError::IoError(err) => write!(f, "File operation failed: {}", err),
             TaskError::JsonError(err) => write!(f, "JSON operation failed: {}", err),
+            TaskError::DatabaseError(err) => write!(f, "Database operation failed: {}", err),
             TaskError::InvalidDate(date) => write!(f, "Invalid date format: {}", date),
             TaskError::InvalidInput(msg) => write!(f, "Invalid input: {}", msg),
+            TaskError::Locked(path) => write!(f, "Task store is locked by another process: {}", path.display()),
+            TaskError::NewerSchema(version) => write!(f,
+                "Data was written by a newer version of todo (schema {}, this build supports {}); refusing to modify it",
+                version, crate::schema::SCHEMA_VERSION),
+            TaskError::ClockUnavailable(reason) => write!(f, "Cannot determine today's date: {}", reason),
         }
     }
 }
@@ -39,197 +57,772 @@ impl From<serde_json::Error> for TaskError {
     }
 }
 
+impl From<rusqlite::Error> for TaskError {
+    fn from(err: rusqlite::Error) -> Self {
+        TaskError::DatabaseError(err)
+    }
+}
+
 // Your Todo Item
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
 pub struct Task {
+    // Short display handle, unique among live tasks
     pub id: u32,
+    // Stable identifier that never changes or gets reused (nil until assigned on load)
+    #[serde(default, skip_serializing_if = "Uuid::is_nil")]
+    pub uuid: Uuid,
     pub text: String,
-    pub done: bool,
+    // Multi-line description: links, acceptance criteria, logs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+    // Lifecycle state; tasks written before it existed carry "done": true/false instead
+    #[serde(default, alias = "done", deserialize_with = "status_or_done")]
+    pub status: Status,
+    // Why the task is blocked, while its status is Blocked
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block_reason: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub due_date: Option<String>,
+    // Time of day (HH:MM) the task is due; without one it is due by the end of due_date
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub due_time: Option<String>,
+    // IANA timezone of due_date/due_time; without one they are local time
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub due_tz: Option<String>,
+    // Date the task can be started on (YYYY-MM-DD)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scheduled: Option<String>,
+    // Hidden from listings until this date (YYYY-MM-DD)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wait: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<Priority>,
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub tags: BTreeSet<String>,
+    // Dot-separated project path, e.g. "work.infra.ci"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project: Option<String>,
+    // UUID of the parent task for subtasks
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent: Option<Uuid>,
+    // UUIDs of tasks that must be done before this one can start
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub depends_on: BTreeSet<Uuid>,
+    // Repeat rule; completing the task spawns the next instance
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recur: Option<Recurrence>,
+    // User-defined attributes declared in config.json, by name
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub udas: BTreeMap<String, UdaValue>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub modified_at: Option<DateTime<Utc>>,
+    // Set while the task is done or cancelled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub completed_at: Option<DateTime<Utc>>,
+    // Set when the task is moved to removed storage
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub removed_at: Option<DateTime<Utc>>,
+    // Fields this version does not know about, kept so they survive a round trip
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
 }
 
-// Get the todo data directory path
-fn get_todo_dir() -> Result<PathBuf, TaskError> {
-    let home_dir = std::env::var("HOME")
-        .or_else(|_| std::env::var("USERPROFILE"))
-        .map_err(|_| TaskError::InvalidInput("Could not find home directory".to_string()))?;
-    
-    let todo_dir = PathBuf::from(home_dir).join(".todo");
-    
-    // Create .todo directory if it doesn't exist
-    if !todo_dir.exists() {
-        fs::create_dir_all(&todo_dir)?;
+impl Task {
+    // Create a pending task with a fresh UUID
+    pub fn new(id: u32, text: impl Into<String>, due_date: Option<String>) -> Task {
+        let now = Utc::now();
+        Task {
+            id,
+            uuid: Uuid::new_v4(),
+            text: text.into(),
+            due_date,
+            created_at: Some(now),
+            modified_at: Some(now),
+            ..Task::default()
+        }
+    }
+
+    // Mark the task as modified now
+    pub fn touch(&mut self) {
+        self.modified_at = Some(Utc::now());
+    }
+
+    // Text followed by its +tags, as typed when adding or editing
+    pub fn text_with_tags(&self) -> String {
+        self.tags.iter().fold(self.text.clone(), |text, tag| format!("{} +{}", text, tag))
+    }
+
+    // Replace the notes; blank notes clear them
+    pub fn set_notes(&mut self, notes: &str) {
+        let notes = notes.trim_end().trim_start_matches(['\n', '\r']);
+        self.notes = if notes.trim().is_empty() { None } else { Some(notes.to_string()) };
+        self.touch();
+    }
+
+    // Done or cancelled
+    pub fn is_closed(&self) -> bool {
+        self.status.is_closed()
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.status == Status::Done
+    }
+
+    // Move the task to a new status, keeping completed_at and block_reason in step
+    pub fn set_status(&mut self, status: Status) {
+        if !status.is_closed() {
+            self.completed_at = None;
+        } else if !self.is_closed() || self.completed_at.is_none() {
+            self.completed_at = Some(Utc::now());
+        }
+        if status != Status::Blocked {
+            self.block_reason = None;
+        }
+        self.status = status;
+        self.touch();
+    }
+
+    // Complete or reopen the task
+    pub fn set_done(&mut self, done: bool) {
+        self.set_status(if done { Status::Done } else { Status::Pending });
+    }
+
+    // How urgent an open task is: priority plus due-date proximity, a bonus for tasks
+    // in progress and a penalty for blocked or deferred ones (closed tasks score 0)
+    pub fn urgency(&self, today: &str) -> f64 {
+        if self.is_closed() {
+            return 0.0;
+        }
+
+        let priority = self.priority.map_or(0.0, Priority::urgency);
+        let due = self.local_due_date()
+            .and_then(|due| days_between(today, &due))
+            .map_or(0.0, due_urgency);
+        priority + due + self.status.urgency()
+    }
+
+    // Exact moment the task is due, for tasks with a due time
+    pub fn due_at(&self) -> Option<DateTime<Local>> {
+        let date = NaiveDate::parse_from_str(self.due_date.as_deref()?, "%Y-%m-%d").ok()?;
+        let time = NaiveTime::parse_from_str(self.due_time.as_deref()?, "%H:%M").ok()?;
+        let due = date.and_time(time);
+        match self.due_tz.as_deref().and_then(parse_timezone) {
+            Some(tz) => tz.from_local_datetime(&due).earliest().map(|at| at.with_timezone(&Local)),
+            None => Local.from_local_datetime(&due).earliest(),
+        }
+    }
+
+    // Local calendar date the task is due on; a time in another timezone may fall on a different day here
+    pub fn local_due_date(&self) -> Option<String> {
+        match self.due_at() {
+            Some(at) => Some(at.format("%Y-%m-%d").to_string()),
+            None => self.due_date.clone(),
+        }
+    }
+
+    // Where the due date falls relative to `now`. Tasks with a time are overdue as soon as
+    // it passes, date-only tasks once their day is over.
+    pub fn due_state(&self, now: DateTime<Local>) -> Option<DueState> {
+        let today = now.date_naive();
+        if let Some(at) = self.due_at()
+            && at < now
+        {
+            return Some(DueState::Overdue);
+        }
+
+        let due = NaiveDate::parse_from_str(&self.local_due_date()?, "%Y-%m-%d").ok()?;
+        Some(match (due - today).num_days() {
+            ..0 => DueState::Overdue,
+            0 => DueState::Today,
+            1 => DueState::Tomorrow,
+            _ => DueState::Later,
+        })
+    }
+
+    // An open task whose wait date has not come yet
+    pub fn is_waiting(&self, today: &str) -> bool {
+        !self.is_closed() && self.wait.as_deref().is_some_and(|wait| wait > today)
+    }
+
+    // An open task whose scheduled date has come
+    pub fn can_start(&self, today: &str) -> bool {
+        !self.is_closed() && self.scheduled.as_deref().is_some_and(|scheduled| scheduled <= today)
+    }
+
+    // True when the task belongs to the project or one of its subprojects
+    pub fn in_project(&self, project: &str) -> bool {
+        self.project.as_deref().is_some_and(|own| {
+            own == project || own.strip_prefix(project).is_some_and(|rest| rest.starts_with('.'))
+        })
+    }
+
+    // True when the reference points at this task
+    pub fn matches(&self, reference: &TaskRef) -> bool {
+        match reference {
+            TaskRef::Short(id) => self.id == *id,
+            TaskRef::Uuid(uuid) => self.uuid == *uuid,
+            TaskRef::UuidPrefix(prefix) => self.uuid.simple().to_string().starts_with(prefix.as_str()),
+        }
     }
-    
-    Ok(todo_dir)
 }
 
-// Get the full path to todos.json
-fn get_todos_file_path() -> Result<PathBuf, TaskError> {
-    Ok(get_todo_dir()?.join("todos.json"))
+// Task priority, stored as "H", "M" or "L"
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Priority {
+    #[serde(rename = "L")]
+    Low,
+    #[serde(rename = "M")]
+    Medium,
+    #[serde(rename = "H")]
+    High,
 }
 
-// Get the full path to removed.json
-fn get_removed_file_path() -> Result<PathBuf, TaskError> {
-    Ok(get_todo_dir()?.join("removed.json"))
+impl Priority {
+    // Parse a level given on the command line; "none" clears the priority
+    pub fn parse_level(input: &str) -> Result<Option<Priority>, TaskError> {
+        match input.trim().to_lowercase().as_str() {
+            "none" | "-" | "" => Ok(None),
+            level => level.parse().map(Some),
+        }
+    }
+
+    // Next level when cycling with a single key: none -> H -> M -> L -> none
+    pub fn cycle(current: Option<Priority>) -> Option<Priority> {
+        match current {
+            None => Some(Priority::High),
+            Some(Priority::High) => Some(Priority::Medium),
+            Some(Priority::Medium) => Some(Priority::Low),
+            Some(Priority::Low) => None,
+        }
+    }
+
+    // Urgency contributed by the priority
+    fn urgency(self) -> f64 {
+        match self {
+            Priority::High => 6.0,
+            Priority::Medium => 3.9,
+            Priority::Low => 1.8,
+        }
+    }
 }
 
-// Load tasks from file
-pub fn load_tasks() -> Vec<Task> {
-    match load_tasks_result() {
-        Ok(tasks) => tasks,
-        Err(err) => {
-            eprintln!("Warning: Could not load tasks: {}", err);
-            Vec::new()
+impl FromStr for Priority {
+    type Err = TaskError;
+
+    // Accepts H/M/L, high/medium/low, or 1-5 (1 = highest)
+    fn from_str(input: &str) -> Result<Priority, TaskError> {
+        match input.trim().to_lowercase().as_str() {
+            "h" | "high" | "1" | "2" => Ok(Priority::High),
+            "m" | "medium" | "3" => Ok(Priority::Medium),
+            "l" | "low" | "4" | "5" => Ok(Priority::Low),
+            _ => Err(TaskError::InvalidInput(format!("Unknown priority '{}' (use H, M, L or 1-5)", input))),
         }
     }
 }
 
-// Load tasks from file with error handling
-pub fn load_tasks_result() -> Result<Vec<Task>, TaskError> {
-    let file_path = get_todos_file_path()?;
-    
-    // If file doesn't exist, return empty list
-    if !file_path.exists() {
-        return Ok(Vec::new());
+// Lifecycle state of a task, stored as "pending", "started", ...
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    #[default]
+    Pending,
+    // In progress
+    Started,
+    // Stuck on something outside the task list (see block_reason)
+    Blocked,
+    // Put off without a date
+    Deferred,
+    Done,
+    Cancelled,
+}
+
+impl Status {
+    pub const ALL: [Status; 6] = [Status::Pending, Status::Started, Status::Blocked, Status::Deferred, Status::Done, Status::Cancelled];
+
+    // Done and cancelled tasks need no more work
+    pub fn is_closed(self) -> bool {
+        matches!(self, Status::Done | Status::Cancelled)
     }
-    
-    // Try to validate and load the main file
-    match validate_json_file(&file_path) {
-        Ok(tasks) => Ok(tasks),
-        Err(_) => {
-            eprintln!("Warning: Main task file is corrupted, attempting to restore from backup...");
-            
-            // Try to restore from backup
-            if let Ok(()) = restore_from_backup() {
-                // Try loading again after restoration
-                match validate_json_file(&file_path) {
-                    Ok(tasks) => {
-                        eprintln!("✅ Successfully restored tasks from backup");
-                        Ok(tasks)
-                    },
-                    Err(e) => {
-                        eprintln!("❌ Backup file is also corrupted: {}", e);
-                        eprintln!("Creating new empty task list...");
-                        Ok(Vec::new())
-                    }
-                }
-            } else {
-                eprintln!("❌ No backup available, creating new empty task list...");
-                Ok(Vec::new())
-            }
+
+    // Next open status when cycling with a single key: pending -> started -> blocked -> deferred -> pending
+    pub fn cycle(self) -> Status {
+        match self {
+            Status::Pending => Status::Started,
+            Status::Started => Status::Blocked,
+            Status::Blocked => Status::Deferred,
+            Status::Deferred | Status::Done | Status::Cancelled => Status::Pending,
+        }
+    }
+
+    pub fn emoji(self) -> &'static str {
+        match self {
+            Status::Pending => "⬜",
+            Status::Started => "🚧",
+            Status::Blocked => "⛔",
+            Status::Deferred => "⏸️",
+            Status::Done => "✅",
+            Status::Cancelled => "🚫",
+        }
+    }
+
+    // Urgency contributed by the status
+    fn urgency(self) -> f64 {
+        match self {
+            Status::Started => 4.0,
+            Status::Blocked => -5.0,
+            Status::Deferred => -3.0,
+            _ => 0.0,
         }
     }
 }
 
-// Save to file
-pub fn save_tasks(tasks: &[Task]) -> Result<(), TaskError> {
-    let file_path = get_todos_file_path()?;
-    
-    // Create backup before saving
-    create_backup(&file_path)?;
-    
-    let json = serde_json::to_string_pretty(tasks)?;
-    
-    // Validate JSON before writing
-    let _: Vec<Task> = serde_json::from_str(&json)?;
-    
-    fs::write(&file_path, json)?;
-    Ok(())
+impl FromStr for Status {
+    type Err = TaskError;
+
+    fn from_str(input: &str) -> Result<Status, TaskError> {
+        match input.trim().to_lowercase().as_str() {
+            "pending" | "todo" | "open" => Ok(Status::Pending),
+            "started" | "start" | "active" | "in-progress" => Ok(Status::Started),
+            "blocked" => Ok(Status::Blocked),
+            "deferred" | "defer" => Ok(Status::Deferred),
+            "done" | "completed" => Ok(Status::Done),
+            "cancelled" | "canceled" => Ok(Status::Cancelled),
+            _ => Err(TaskError::InvalidInput(format!(
+                "Unknown status '{}' (use pending, started, blocked, deferred, done or cancelled)", input))),
+        }
+    }
 }
 
-// Create backup of existing file
-fn create_backup(file_path: &std::path::Path) -> Result<(), TaskError> {
-    if file_path.exists() {
-        let backup_path = file_path.with_extension("json.backup");
-        fs::copy(file_path, backup_path)?;
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Status::Pending => "pending",
+            Status::Started => "started",
+            Status::Blocked => "blocked",
+            Status::Deferred => "deferred",
+            Status::Done => "done",
+            Status::Cancelled => "cancelled",
+        };
+        write!(f, "{}", name)
     }
-    Ok(())
 }
 
-// Restore from backup if main file is corrupted
-pub fn restore_from_backup() -> Result<(), TaskError> {
-    let file_path = get_todos_file_path()?;
-    let backup_path = file_path.with_extension("json.backup");
-    
-    if backup_path.exists() {
-        fs::copy(&backup_path, &file_path)?;
-        println!("✅ Restored from backup file");
-        Ok(())
+// Read a status, or the "done" flag written before statuses existed
+fn status_or_done<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Status, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Stored {
+        Done(bool),
+        Status(Status),
+    }
+
+    Ok(match Stored::deserialize(deserializer)? {
+        Stored::Done(true) => Status::Done,
+        Stored::Done(false) => Status::Pending,
+        Stored::Status(status) => status,
+    })
+}
+
+impl fmt::Display for Priority {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Priority::High => write!(f, "H"),
+            Priority::Medium => write!(f, "M"),
+            Priority::Low => write!(f, "L"),
+        }
+    }
+}
+
+// Urgency from due-date proximity: full weight a week overdue, tapering to a fifth two weeks out
+fn due_urgency(days_until_due: i64) -> f64 {
+    let factor = if days_until_due <= -7 {
+        1.0
+    } else if days_until_due >= 14 {
+        0.2
     } else {
-        Err(TaskError::InvalidInput("No backup file found".to_string()))
+        (14 - days_until_due) as f64 * 0.8 / 21.0 + 0.2
+    };
+    12.0 * factor
+}
+
+// Days from one YYYY-MM-DD date to another
+fn days_between(from: &str, to: &str) -> Option<i64> {
+    let from = NaiveDate::parse_from_str(from, "%Y-%m-%d").ok()?;
+    let to = NaiveDate::parse_from_str(to, "%Y-%m-%d").ok()?;
+    Some((to - from).num_days())
+}
+
+// Normalize a tag name (without the leading +), rejecting invalid characters
+pub fn normalize_tag(tag: &str) -> Result<String, TaskError> {
+    let tag = tag.trim().trim_start_matches('+').to_lowercase();
+    if tag.is_empty() || !tag.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_') {
+        return Err(TaskError::InvalidInput(format!("Invalid tag '{}' (use letters, digits, - and _)", tag)));
     }
+    Ok(tag)
 }
 
-// Validate JSON file integrity
-pub fn validate_json_file(file_path: &std::path::Path) -> Result<Vec<Task>, TaskError> {
-    let mut file = OpenOptions::new().read(true).open(file_path)?;
-    let mut contents = String::new();
-    file.read_to_string(&mut contents)?;
-    
-    if contents.trim().is_empty() {
-        return Ok(Vec::new());
+// Dates that hold a task back, settable like the due date
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateField {
+    // Earliest date work can start
+    Scheduled,
+    // Hide the task until this date
+    Wait,
+}
+
+impl DateField {
+    pub fn of(self, task: &Task) -> Option<&str> {
+        match self {
+            DateField::Scheduled => task.scheduled.as_deref(),
+            DateField::Wait => task.wait.as_deref(),
+        }
     }
-    
-    // Try to parse JSON
-    let tasks: Vec<Task> = serde_json::from_str(&contents)
-        .map_err(|e| TaskError::JsonError(e))?;
-    
-    // Validate each task
-    for task in &tasks {
-        if task.text.is_empty() {
-            return Err(TaskError::InvalidInput(format!("Task {} has empty text", task.id)));
+
+    pub fn slot(self, task: &mut Task) -> &mut Option<String> {
+        match self {
+            DateField::Scheduled => &mut task.scheduled,
+            DateField::Wait => &mut task.wait,
         }
-        if task.text.len() > 500 {
-            return Err(TaskError::InvalidInput(format!("Task {} text too long", task.id)));
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            DateField::Scheduled => "scheduled",
+            DateField::Wait => "wait",
         }
-        if let Some(ref due_date) = task.due_date {
-            validate_date(due_date)?;
+    }
+}
+
+// Where a due date falls relative to now
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DueState {
+    Overdue,
+    Today,
+    Tomorrow,
+    Later,
+}
+
+// What happens to subtasks when their parent is completed or deleted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ChildPolicy {
+    // Apply the same change to every subtask
+    Cascade,
+    // Refuse while the task has (open) subtasks
+    Refuse,
+    // Leave subtasks alone; deleting the parent makes them top-level tasks
+    Orphan,
+}
+
+// Order tasks so every subtask follows its parent, returning each task with its depth.
+// Tasks whose parent is not in the list are roots; the given order is kept otherwise.
+pub fn tree_order<'a>(tasks: &[&'a Task]) -> Vec<(&'a Task, usize)> {
+    fn visit<'a>(task: &'a Task, depth: usize, tasks: &[&'a Task], seen: &mut HashSet<Uuid>, ordered: &mut Vec<(&'a Task, usize)>) {
+        if !seen.insert(task.uuid) {
+            return;
+        }
+        ordered.push((task, depth));
+        for child in tasks.iter().filter(|child| child.parent == Some(task.uuid)) {
+            visit(child, depth + 1, tasks, seen, ordered);
         }
     }
-    
-    Ok(tasks)
+
+    let present: HashSet<Uuid> = tasks.iter().map(|task| task.uuid).collect();
+    let mut seen = HashSet::new();
+    let mut ordered = Vec::new();
+
+    for task in tasks.iter().filter(|task| task.parent.is_none_or(|parent| !present.contains(&parent))) {
+        visit(task, 0, tasks, &mut seen, &mut ordered);
+    }
+    // Tasks caught in a parent cycle (corrupt data) are still shown, as roots
+    for task in tasks {
+        visit(task, 0, tasks, &mut seen, &mut ordered);
+    }
+
+    ordered
 }
 
-// Save to file (fallback version that prints errors)
-pub fn save_tasks_safe(tasks: &[Task]) {
-    if let Err(err) = save_tasks(tasks) {
-        eprintln!("Error: Could not save tasks: {}", err);
+// UUIDs of all subtasks below a task, at any depth
+pub fn descendants(tasks: &[Task], uuid: Uuid) -> Vec<Uuid> {
+    let mut found = Vec::new();
+    let mut pending = vec![uuid];
+    while let Some(current) = pending.pop() {
+        for child in tasks.iter().filter(|task| task.parent == Some(current)) {
+            if child.uuid != uuid && !found.contains(&child.uuid) {
+                found.push(child.uuid);
+                pending.push(child.uuid);
+            }
+        }
     }
+    found
 }
 
-// Get the path where todo data is stored (for user info)
-pub fn get_data_location() -> String {
-    match get_todos_file_path() {
-        Ok(path) => path.to_string_lossy().to_string(),
-        Err(_) => "Error: Could not determine data location".to_string(),
+// Complete or reopen tasks[index], applying the policy to its subtasks.
+// Returns how many subtasks were changed as well.
+pub fn set_done_with_children(tasks: &mut [Task], index: usize, done: bool, policy: ChildPolicy) -> Result<usize, TaskError> {
+    set_status_with_children(tasks, index, if done { Status::Done } else { Status::Pending }, policy)
+}
+
+// Move tasks[index] to a status, applying the policy to its subtasks when the task is
+// closed or reopened. Returns how many subtasks were changed as well.
+pub fn set_status_with_children(tasks: &mut [Task], index: usize, status: Status, policy: ChildPolicy) -> Result<usize, TaskError> {
+    let children = descendants(tasks, tasks[index].uuid);
+    let done = status.is_closed();
+
+    if policy == ChildPolicy::Refuse && done {
+        let open = tasks.iter().filter(|task| children.contains(&task.uuid) && !task.is_closed()).count();
+        if open > 0 {
+            return Err(TaskError::InvalidInput(format!(
+                "Task {} has {} open subtask(s); use --children cascade or orphan", tasks[index].id, open)));
+        }
+    }
+
+    tasks[index].set_status(status);
+    if policy != ChildPolicy::Cascade || !(done || status == Status::Pending) {
+        return Ok(0);
+    }
+
+    let mut changed = 0;
+    // Closing leaves closed subtasks alone; reopening only reopens completed ones
+    let affected = |task: &Task| if done { !task.is_closed() } else { task.is_done() };
+    for task in tasks.iter_mut().filter(|task| children.contains(&task.uuid) && affected(task)) {
+        task.set_status(status);
+        changed += 1;
     }
+    Ok(changed)
 }
 
-// Get today's date in YYYY-MM-DD format
-pub fn get_today() -> String {
-    use std::process::Command;
-    
-    // Use system date command for accurate date
-    let output = Command::new("date")
-        .arg("+%Y-%m-%d")
-        .output();
-    
-    match output {
-        Ok(output) => {
-            String::from_utf8(output.stdout)
-                .unwrap_or_else(|_| "2025-11-07".to_string())
-                .trim()
-                .to_string()
-        },
-        Err(_) => {
-            // Fallback to a simple implementation
-            "2025-11-07".to_string()
+// Remove tasks[index], applying the policy to its subtasks. Returns every removed task.
+pub fn remove_with_children(tasks: &mut Vec<Task>, index: usize, policy: ChildPolicy) -> Result<Vec<Task>, TaskError> {
+    let uuid = tasks[index].uuid;
+    let children = descendants(tasks, uuid);
+
+    match policy {
+        ChildPolicy::Refuse if !children.is_empty() => Err(TaskError::InvalidInput(format!(
+            "Task {} has {} subtask(s); use --children cascade or orphan", tasks[index].id, children.len()))),
+        ChildPolicy::Cascade => {
+            let (removed, kept) = tasks.drain(..).partition(|task| task.uuid == uuid || children.contains(&task.uuid));
+            *tasks = kept;
+            Ok(removed)
+        }
+        _ => {
+            // Direct children become top-level tasks
+            for task in tasks.iter_mut().filter(|task| task.parent == Some(uuid)) {
+                task.parent = None;
+                task.touch();
+            }
+            Ok(vec![tasks.remove(index)])
+        }
+    }
+}
+
+// Roll-up of direct subtasks as (closed, total), None when the task has none
+pub fn subtask_progress(tasks: &[Task], uuid: Uuid) -> Option<(usize, usize)> {
+    let children: Vec<&Task> = tasks.iter().filter(|task| task.parent == Some(uuid)).collect();
+    if children.is_empty() {
+        return None;
+    }
+    Some((children.iter().filter(|task| task.is_closed()).count(), children.len()))
+}
+
+// Open tasks this task depends on; dependencies that were deleted no longer count
+pub fn open_dependencies<'a>(tasks: &'a [Task], task: &Task) -> Vec<&'a Task> {
+    tasks.iter().filter(|other| task.depends_on.contains(&other.uuid) && !other.is_closed()).collect()
+}
+
+// An open task is blocked while any of its dependencies is still open
+pub fn is_blocked(tasks: &[Task], task: &Task) -> bool {
+    !task.is_closed() && !open_dependencies(tasks, task).is_empty()
+}
+
+// Open tasks that depend on the given one
+pub fn open_dependents(tasks: &[Task], uuid: Uuid) -> Vec<&Task> {
+    tasks.iter().filter(|task| !task.is_closed() && task.depends_on.contains(&uuid)).collect()
+}
+
+// Make tasks[index] depend on the task with the given UUID, refusing links that would form a cycle
+pub fn add_dependency(tasks: &mut [Task], index: usize, dependency: Uuid) -> Result<bool, TaskError> {
+    let uuid = tasks[index].uuid;
+    if uuid == dependency {
+        return Err(TaskError::InvalidInput(format!("Task {} cannot depend on itself", tasks[index].id)));
+    }
+
+    // Walk everything the new dependency (transitively) depends on
+    let mut pending = vec![dependency];
+    let mut seen = HashSet::new();
+    while let Some(current) = pending.pop() {
+        if current == uuid {
+            return Err(TaskError::InvalidInput(format!(
+                "Task {} already depends on task {} (directly or indirectly); that would create a cycle",
+                short_id(tasks, dependency), tasks[index].id)));
+        }
+        if seen.insert(current)
+            && let Some(task) = tasks.iter().find(|task| task.uuid == current)
+        {
+            pending.extend(task.depends_on.iter().copied());
+        }
+    }
+
+    Ok(tasks[index].depends_on.insert(dependency))
+}
+
+// Short ID of the task with the given UUID, for messages
+fn short_id(tasks: &[Task], uuid: Uuid) -> String {
+    tasks.iter().find(|task| task.uuid == uuid).map_or_else(|| uuid.to_string(), |task| task.id.to_string())
+}
+
+// Normalize a project path like "Work.Infra.CI", rejecting empty or invalid segments
+pub fn normalize_project(project: &str) -> Result<String, TaskError> {
+    let project = project.trim().to_lowercase();
+    let valid = project.split('.')
+        .all(|segment| !segment.is_empty() && segment.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_'));
+    if !valid {
+        return Err(TaskError::InvalidInput(format!("Invalid project '{}' (use names like work.infra.ci)", project)));
+    }
+    Ok(project)
+}
+
+// Every project used by the tasks together with all of its parents, sorted
+pub fn project_paths(tasks: &[Task]) -> Vec<String> {
+    let mut paths = BTreeSet::new();
+    for project in tasks.iter().filter_map(|task| task.project.as_deref()) {
+        let mut path = String::new();
+        for segment in project.split('.') {
+            if !path.is_empty() {
+                path.push('.');
+            }
+            path.push_str(segment);
+            paths.insert(path.clone());
+        }
+    }
+    // Compare segment by segment so children always follow their parent
+    let mut paths: Vec<String> = paths.into_iter().collect();
+    paths.sort_by(|a, b| a.split('.').cmp(b.split('.')));
+    paths
+}
+
+// Split `+tag` tokens out of task text, returning the remaining text and the tags
+pub fn extract_tags(text: &str) -> (String, BTreeSet<String>) {
+    let mut words = Vec::new();
+    let mut tags = BTreeSet::new();
+    if !text.contains('+') {
+        return (text.to_string(), tags);
+    }
+
+    for word in text.split_whitespace() {
+        match word.strip_prefix('+').map(normalize_tag) {
+            Some(Ok(tag)) => {
+                tags.insert(tag);
+            }
+            _ => words.push(word),
+        }
+    }
+
+    (words.join(" "), tags)
+}
+
+// Give every task without a UUID a fresh one, returning true when any changed
+pub fn assign_missing_uuids(tasks: &mut [Task]) -> bool {
+    let mut changed = false;
+    for task in tasks.iter_mut().filter(|task| task.uuid.is_nil()) {
+        task.uuid = Uuid::new_v4();
+        changed = true;
+    }
+    changed
+}
+
+// A task given on the command line: short ID, full UUID or UUID prefix
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TaskRef {
+    Short(u32),
+    Uuid(Uuid),
+    // Lowercase hex digits without dashes
+    UuidPrefix(String),
+}
+
+// Shortest UUID prefix accepted on the command line
+const MIN_UUID_PREFIX: usize = 4;
+
+impl FromStr for TaskRef {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<TaskRef, String> {
+        let input = input.trim();
+        if let Ok(id) = input.parse::<u32>() {
+            return Ok(TaskRef::Short(id));
+        }
+        if let Ok(uuid) = Uuid::parse_str(input) {
+            return Ok(TaskRef::Uuid(uuid));
+        }
+
+        let prefix = input.replace('-', "").to_lowercase();
+        if prefix.len() >= MIN_UUID_PREFIX && prefix.len() < 32 && prefix.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Ok(TaskRef::UuidPrefix(prefix));
         }
+
+        Err(format!("'{}' is not a task ID or UUID", input))
+    }
+}
+
+impl From<u32> for TaskRef {
+    fn from(id: u32) -> TaskRef {
+        TaskRef::Short(id)
+    }
+}
+
+impl From<Uuid> for TaskRef {
+    fn from(uuid: Uuid) -> TaskRef {
+        TaskRef::Uuid(uuid)
     }
 }
 
+impl fmt::Display for TaskRef {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TaskRef::Short(id) => write!(f, "{}", id),
+            TaskRef::Uuid(uuid) => write!(f, "{}", uuid),
+            TaskRef::UuidPrefix(prefix) => write!(f, "{}", prefix),
+        }
+    }
+}
+
+// Timestamp a task list can be sorted or filtered by
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimestampField {
+    Created,
+    Modified,
+    Completed,
+    Removed,
+}
+
+impl TimestampField {
+    // Read this timestamp from a task
+    pub fn of(self, task: &Task) -> Option<DateTime<Utc>> {
+        match self {
+            TimestampField::Created => task.created_at,
+            TimestampField::Modified => task.modified_at,
+            TimestampField::Completed => task.completed_at,
+            TimestampField::Removed => task.removed_at,
+        }
+    }
+}
+
+// Format a timestamp in local time for display
+pub fn format_timestamp(timestamp: &DateTime<Utc>) -> String {
+    timestamp.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string()
+}
+
+// Local calendar date of a timestamp in YYYY-MM-DD format
+pub fn timestamp_date(timestamp: &DateTime<Utc>) -> String {
+    timestamp.with_timezone(&Local).format("%Y-%m-%d").to_string()
+}
+
+// Get today's date in YYYY-MM-DD format (local timezone, from the active clock)
+pub fn get_today() -> Result<String, TaskError> {
+    get_date_with_offset(0)
+}
+
 // Validate date format (YYYY-MM-DD)
 pub fn validate_date(date: &str) -> Result<(), TaskError> {
     if date.is_empty() {
@@ -244,21 +837,21 @@ pub fn validate_date(date: &str) -> Result<(), TaskError> {
     // Check year (4 digits)
     let year: i32 = parts[0].parse()
         .map_err(|_| TaskError::InvalidDate(format!("Invalid year: {}", parts[0])))?;
-    if year < 1900 || year > 9999 {
+    if !(1900..=9999).contains(&year) {
         return Err(TaskError::InvalidDate(format!("Year must be between 1900 and 9999, got: {}", year)));
     }
     
     // Check month (01-12)
     let month: u32 = parts[1].parse()
         .map_err(|_| TaskError::InvalidDate(format!("Invalid month: {}", parts[1])))?;
-    if month < 1 || month > 12 {
+    if !(1..=12).contains(&month) {
         return Err(TaskError::InvalidDate(format!("Month must be between 01 and 12, got: {}", month)));
     }
     
     // Check day (01-31, simplified validation)
     let day: u32 = parts[2].parse()
         .map_err(|_| TaskError::InvalidDate(format!("Invalid day: {}", parts[2])))?;
-    if day < 1 || day > 31 {
+    if !(1..=31).contains(&day) {
         return Err(TaskError::InvalidDate(format!("Day must be between 01 and 31, got: {}", day)));
     }
     
@@ -285,119 +878,15 @@ pub fn validate_date(date: &str) -> Result<(), TaskError> {
 }
 
 // Get date with offset days from today in YYYY-MM-DD format
-pub fn get_date_with_offset(days: i32) -> String {
-    use std::process::Command;
-    
-    let offset_arg = if days >= 0 {
-        format!("+{} days", days)
-    } else {
-        format!("{} days", days)
-    };
-    
-    // Use system date command with offset
-    let output = Command::new("date")
-        .arg("-d")
-        .arg(&offset_arg)
-        .arg("+%Y-%m-%d")
-        .output();
-    
-    match output {
-        Ok(output) => {
-            String::from_utf8(output.stdout)
-                .unwrap_or_else(|_| get_today())
-                .trim()
-                .to_string()
-        },
-        Err(_) => {
-            // Fallback to today's date
-            get_today()
-        }
-    }
+pub fn get_date_with_offset(days: i32) -> Result<String, TaskError> {
+    offset_date(&crate::clock::today()?.format("%Y-%m-%d").to_string(), days)
 }
 
-// Load removed tasks from file
-pub fn load_removed_tasks() -> Vec<Task> {
-    match load_removed_tasks_result() {
-        Ok(tasks) => tasks,
-        Err(err) => {
-            eprintln!("Warning: Could not load removed tasks: {}", err);
-            Vec::new()
-        }
-    }
+// Calendar date `days` after (or before) a YYYY-MM-DD date
+pub fn offset_date(date: &str, days: i32) -> Result<String, TaskError> {
+    let date = NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .map_err(|_| TaskError::InvalidDate(format!("Date must be in YYYY-MM-DD format, got: {}", date)))?;
+    date.checked_add_signed(TimeDelta::days(i64::from(days)))
+        .map(|date| date.format("%Y-%m-%d").to_string())
+        .ok_or_else(|| TaskError::InvalidDate(format!("{} days from {} is out of range", days, date)))
 }
-
-// Load removed tasks from file with error handling
-pub fn load_removed_tasks_result() -> Result<Vec<Task>, TaskError> {
-    let file_path = get_removed_file_path()?;
-    
-    // If file doesn't exist, return empty list
-    if !file_path.exists() {
-        return Ok(Vec::new());
-    }
-    
-    // Try to validate and load the file
-    match validate_json_file(&file_path) {
-        Ok(tasks) => Ok(tasks),
-        Err(_) => {
-            eprintln!("Warning: Removed tasks file is corrupted, attempting recovery...");
-            
-            // Try to restore from backup
-            let backup_path = file_path.with_extension("json.backup");
-            if backup_path.exists() {
-                match fs::copy(&backup_path, &file_path) {
-                    Ok(_) => {
-                        match validate_json_file(&file_path) {
-                            Ok(tasks) => {
-                                eprintln!("✅ Successfully restored removed tasks from backup");
-                                Ok(tasks)
-                            },
-                            Err(_) => {
-                                eprintln!("❌ Backup file is also corrupted, starting with empty removed list");
-                                Ok(Vec::new())
-                            }
-                        }
-                    },
-                    Err(_) => {
-                        eprintln!("❌ Could not restore from backup, starting with empty removed list");
-                        Ok(Vec::new())
-                    }
-                }
-            } else {
-                eprintln!("❌ No backup available, starting with empty removed list");
-                Ok(Vec::new())
-            }
-        }
-    }
-}
-
-// Save removed tasks to file
-pub fn save_removed_tasks(tasks: &[Task]) -> Result<(), TaskError> {
-    let file_path = get_removed_file_path()?;
-    
-    // Create backup before saving
-    create_backup(&file_path)?;
-    
-    let json = serde_json::to_string_pretty(tasks)?;
-    
-    // Validate JSON before writing
-    let _: Vec<Task> = serde_json::from_str(&json)?;
-    
-    fs::write(&file_path, json)?;
-    Ok(())
-}
-
-// Add tasks to removed storage
-pub fn add_to_removed(tasks_to_remove: Vec<Task>) {
-    match add_to_removed_result(tasks_to_remove) {
-        Ok(()) => {},
-        Err(err) => eprintln!("Error: Could not add tasks to removed storage: {}", err),
-    }
-}
-
-// Add tasks to removed storage with error handling
-pub fn add_to_removed_result(tasks_to_remove: Vec<Task>) -> Result<(), TaskError> {
-    let mut removed_tasks = load_removed_tasks_result()?;
-    removed_tasks.extend(tasks_to_remove);
-    save_removed_tasks(&removed_tasks)?;
-    Ok(())
-}
\ No newline at end of file
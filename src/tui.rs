@@ -1,6 +1,7 @@
 use std::io;
+use std::time::Duration;
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEventKind},
+    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEventKind, KeyModifiers},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
@@ -8,19 +9,41 @@ use ratatui::{
     backend::CrosstermBackend,
     layout::{Constraint, Direction, Layout},
     style::{Color, Modifier, Style},
-    text::Line,
+    text::{Line, Span},
     widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Wrap},
     Frame, Terminal,
 };
-use crate::task::{load_tasks, save_tasks_safe, Task, validate_date};
+use crate::dates::{DueSpec, resolve_date, resolve_due};
+use crate::journal::Operation;
+use crate::query::Query;
+use crate::recur::spawn_next;
+use crate::store::{StoreLock, TaskStore};
+use crate::task::{
+    ChildPolicy, DateField, DueState, Priority, Status, Task, extract_tags, get_today, is_blocked, project_paths, remove_with_children, set_done_with_children,
+    subtask_progress, tree_order,
+};
+use crate::view::{GroupBy, ListView, SortField, SortKey, SortSpec, TUI_DEFAULTS};
 
 // TUI State
 pub struct App {
+    pub store: TaskStore,
     pub tasks: Vec<Task>,
     pub list_state: ListState,
     pub mode: AppMode,
     pub input: String,
     pub message: Option<String>,
+    // Project shown in the list (None = all tasks), chosen in the sidebar
+    pub project: Option<String>,
+    // Also list tasks that are waiting until a later date
+    pub show_waiting: bool,
+    // Show the detail pane (including notes) of the selected task
+    pub show_details: bool,
+    // Filter expression typed at the `/` prompt
+    pub filter: Option<Query>,
+    // Filter of the active context (`todo context set`), applied on top of everything else
+    pub context: Option<Query>,
+    // Sort order (`o`) and grouping (`g`) of the list, starting from the "tui" settings in config.json
+    pub view: ListView,
 }
 
 #[derive(PartialEq)]
@@ -29,28 +52,261 @@ pub enum AppMode {
     Adding,
     Editing(u32),
     SetDueDate(u32),
+    SetDate(u32, DateField),
+    Filter,
+    Sort,
 }
 
 impl App {
-    pub fn new() -> App {
-        let tasks = load_tasks();
+    pub fn new(store: TaskStore) -> App {
+        // Problems are shown in the message line; printing them would garble the screen
+        let (tasks, mut message) = match store.load_result() {
+            Ok(tasks) => {
+                let problems = store.attribute_problems(&tasks);
+                let message = (!problems.is_empty()).then(|| format!("Warning: {}", problems.join("; ")));
+                (tasks, message)
+            }
+            Err(err) => (Vec::new(), Some(format!("Could not load tasks: {}", err))),
+        };
+        let context = match store.saved_queries() {
+            Ok(saved) => saved.context_query(&store.config().udas).unwrap_or_else(|err| {
+                // Only the first line: the filter with its ^ marker does not fit the status bar
+                message = Some(format!("Context ignored: {}", err.to_string().lines().next().unwrap_or_default()));
+                None
+            }),
+            Err(err) => {
+                message = Some(format!("Could not load saved queries: {}", err));
+                None
+            }
+        };
+        let view = store.config().tui.resolve(None, None, TUI_DEFAULTS, &store.config().udas).unwrap_or_else(|err| {
+            message = Some(format!("TUI sort settings ignored: {}", err));
+            default_view()
+        });
         let mut list_state = ListState::default();
         if !tasks.is_empty() {
             list_state.select(Some(0));
         }
         App {
+            store,
             tasks,
             list_state,
             mode: AppMode::Normal,
             input: String::new(),
-            message: None,
+            message,
+            project: None,
+            show_waiting: false,
+            show_details: false,
+            filter: None,
+            context,
+            view,
         }
     }
 
+    // Show or hide the detail pane
+    pub fn toggle_details(&mut self) {
+        self.show_details = !self.show_details;
+    }
+
+    // The selected task
+    pub fn selected_task(&self) -> Option<&Task> {
+        let id = self.selected_id()?;
+        self.tasks.iter().find(|task| task.id == id)
+    }
+
+    // Show or hide waiting tasks, keeping the selection where possible
+    pub fn toggle_waiting(&mut self) {
+        let selected = self.selected_id();
+        self.show_waiting = !self.show_waiting;
+        self.select_id(selected);
+        self.message = Some(if self.show_waiting { "Showing waiting tasks" } else { "Hiding waiting tasks" }.to_string());
+    }
+
+    // Apply the filter typed at the `/` prompt; an empty one shows all tasks again.
+    // On a parse error the prompt stays open so the filter can be fixed.
+    pub fn apply_filter(&mut self) {
+        let selected = self.selected_id();
+        let input = self.input.trim();
+        if input.is_empty() {
+            self.filter = None;
+            self.message = Some("Filter cleared".to_string());
+        } else {
+            match Query::parse(input, &self.store.config().udas) {
+                Ok(query) => {
+                    self.message = Some(format!("Filter: {}", query));
+                    self.filter = Some(query);
+                }
+                Err(err) => {
+                    self.message = Some(err.short());
+                    return;
+                }
+            }
+        }
+        self.mode = AppMode::Normal;
+        self.input.clear();
+        self.select_id(selected);
+    }
+
+    // Apply the sort order typed at the `o` prompt; an empty one goes back to the configured order.
+    // On a parse error the prompt stays open so the order can be fixed.
+    pub fn apply_sort(&mut self) {
+        let selected = self.selected_id();
+        let config = self.store.config();
+        let input = self.input.trim();
+        let sort = (!input.is_empty()).then_some(input);
+        match config.tui.resolve(sort, Some(self.view.group_by), TUI_DEFAULTS, &config.udas) {
+            Ok(view) => {
+                self.message = Some(format!("Sorted by {}", view.sort));
+                self.view = view;
+            }
+            Err(err) => {
+                self.message = Some(err.to_string());
+                return;
+            }
+        }
+        self.mode = AppMode::Normal;
+        self.input.clear();
+        self.select_id(selected);
+    }
+
+    // Switch to the next grouping: month, week, day, project, tag, status, none
+    pub fn cycle_group(&mut self) {
+        let selected = self.selected_id();
+        self.view.group_by = self.view.group_by.next();
+        self.message = Some(format!("Grouped by {}", self.view.group_by));
+        self.select_id(selected);
+    }
+
+    // Sort key and heading of the group a top-level task is listed under. Each task is listed
+    // once, so tasks with several tags go under their first tag.
+    pub fn group_of(&self, task: &Task) -> Option<(String, String)> {
+        if self.view.group_by == GroupBy::None {
+            return None;
+        }
+        self.view.group_by.groups(task).into_iter().next()
+    }
+
+    // Tasks shown in the list, filtered by the selected project
+    pub fn visible_tasks(&self) -> Vec<&Task> {
+        self.visible_tree().into_iter().map(|(task, _)| task).collect()
+    }
+
+    // Visible tasks with their subtask depth, each subtask right after its parent
+    pub fn visible_tree(&self) -> Vec<(&Task, usize)> {
+        let today = get_today().unwrap_or_default();
+        let filters: Vec<&Query> = self.context.iter().chain(&self.filter).collect();
+        let show_waiting = self.show_waiting || filters.iter().any(|filter| filter.mentions("wait"));
+        let mut filtered: Vec<&Task> = self.tasks.iter()
+            .filter(|task| self.project.as_deref().is_none_or(|project| task.in_project(project)))
+            .filter(|task| show_waiting || !task.is_waiting(&today))
+            .filter(|task| filters.iter().all(|filter| filter.matches(task, &self.tasks)))
+            .collect();
+        // Sort, then gather each group together (a stable sort keeps the order within groups)
+        self.view.sort.sort(&mut filtered, &today);
+        filtered.sort_by_cached_key(|task| self.group_of(task).map(|(key, _)| key));
+        tree_order(&filtered)
+    }
+
+    // Sidebar entries: every project and parent project, sorted so children follow parents
+    pub fn project_entries(&self) -> Vec<String> {
+        project_paths(&self.tasks)
+    }
+
+    // ID of the selected task
+    pub fn selected_id(&self) -> Option<u32> {
+        self.list_state.selected()
+            .and_then(|i| self.visible_tasks().get(i).map(|task| task.id))
+    }
+
+    // Select a task by ID if it is visible, otherwise keep the selection in range
+    fn select_id(&mut self, id: Option<u32>) {
+        let visible: Vec<u32> = self.visible_tasks().iter().map(|task| task.id).collect();
+        let index = id
+            .and_then(|id| visible.iter().position(|&v| v == id))
+            .or_else(|| self.list_state.selected().map(|i| i.min(visible.len().saturating_sub(1))))
+            .or(Some(0));
+        self.list_state.select(if visible.is_empty() { None } else { index });
+    }
+
+    // Move the sidebar selection (None = all projects) forwards or backwards
+    pub fn cycle_project(&mut self, forward: bool) {
+        let mut entries: Vec<Option<String>> = vec![None];
+        entries.extend(self.project_entries().into_iter().map(Some));
+
+        let current = entries.iter().position(|entry| *entry == self.project).unwrap_or(0);
+        let next = if forward {
+            (current + 1) % entries.len()
+        } else {
+            (current + entries.len() - 1) % entries.len()
+        };
+        self.project = entries[next].clone();
+        self.list_state.select(None);
+        self.select_id(None);
+    }
+
+    // Reload tasks from the store, keeping the selected task selected
+    pub fn reload(&mut self) {
+        let selected_id = self.selected_id();
+
+        match self.store.load_result() {
+            Ok(tasks) => self.tasks = tasks,
+            Err(err) => {
+                self.message = Some(format!("Could not reload tasks: {}", err));
+                return;
+            }
+        }
+
+        self.select_id(selected_id);
+    }
+
+    // Lock the store and reload so writes from other processes are not lost
+    fn begin_write(&mut self) -> Option<StoreLock> {
+        match self.store.lock() {
+            Ok(lock) => {
+                self.reload();
+                Some(lock)
+            }
+            Err(err) => {
+                self.message = Some(err.to_string());
+                None
+            }
+        }
+    }
+
+    // Save tasks and record the change for undo
+    fn commit(&mut self, description: String, before: &[Task]) {
+        let operation = Operation::new(description, before, &self.tasks);
+        self.store.save_recorded_safe(&self.tasks, operation);
+    }
+
+    pub fn undo(&mut self) {
+        let Some(_lock) = self.begin_write() else { return };
+        self.message = Some(match self.store.undo() {
+            Ok(Some(operation)) => format!("Undid: {}", operation.description),
+            Ok(None) => "Nothing left to undo".to_string(),
+            Err(err) => format!("Undo failed: {}", err),
+        });
+        self.reload();
+    }
+
+    pub fn redo(&mut self) {
+        let Some(_lock) = self.begin_write() else { return };
+        self.message = Some(match self.store.redo() {
+            Ok(Some(operation)) => format!("Redid: {}", operation.description),
+            Ok(None) => "Nothing left to redo".to_string(),
+            Err(err) => format!("Redo failed: {}", err),
+        });
+        self.reload();
+    }
+
     pub fn next(&mut self) {
+        let len = self.visible_tasks().len();
+        if len == 0 {
+            return;
+        }
         let i = match self.list_state.selected() {
             Some(i) => {
-                if i >= self.tasks.len() - 1 {
+                if i >= len - 1 {
                     0
                 } else {
                     i + 1
@@ -62,10 +318,14 @@ impl App {
     }
 
     pub fn previous(&mut self) {
+        let len = self.visible_tasks().len();
+        if len == 0 {
+            return;
+        }
         let i = match self.list_state.selected() {
             Some(i) => {
                 if i == 0 {
-                    self.tasks.len() - 1
+                    len - 1
                 } else {
                     i - 1
                 }
@@ -76,35 +336,141 @@ impl App {
     }
 
     pub fn toggle_current(&mut self) {
-        if let Some(i) = self.list_state.selected() {
-            if i < self.tasks.len() {
-                self.tasks[i].done = !self.tasks[i].done;
-                save_tasks_safe(&self.tasks);
-                self.message = Some(format!("Task {} completed!", self.tasks[i].id));
+        let Some(id) = self.selected_id() else {
+            return;
+        };
+        let today = match get_today() {
+            Ok(today) => today,
+            Err(err) => {
+                self.message = Some(err.to_string());
+                return;
+            }
+        };
+        let Some(_lock) = self.begin_write() else { return };
+        let before = self.tasks.clone();
+
+        if let Some(i) = self.tasks.iter().position(|t| t.id == id) {
+            // Subtasks are left as they are, like `todo done` without --children
+            let done = !self.tasks[i].is_closed();
+            match set_done_with_children(&mut self.tasks, i, done, ChildPolicy::Orphan) {
+                Ok(_) => {
+                    let next_id = self.store.next_id(&self.tasks);
+                    let spawned = if done { spawn_next(&mut self.tasks, i, next_id, &today) } else { None };
+                    let description = format!("{} {}", if done { "done" } else { "reopen" }, id);
+                    self.commit(description, &before);
+                    self.message = Some(match spawned {
+                        Some(_) => format!("Task {} completed! Next: task {}", id, next_id),
+                        None if done && before[i].recur.as_ref().is_some_and(|recur| !recur.paused) => format!("Task {} completed! Series ended", id),
+                        None if done => format!("Task {} completed!", id),
+                        None => format!("Task {} reopened!", id),
+                    });
+                }
+                Err(err) => self.message = Some(err.to_string()),
+            }
+        } else {
+            self.message = Some(format!("Task {} not found!", id));
+        }
+    }
+
+    // Cycle the selected task's status: pending -> started -> blocked -> deferred -> pending.
+    // Done and cancelled tasks are reopened.
+    pub fn cycle_status_current(&mut self) {
+        let Some(id) = self.selected_id() else {
+            return;
+        };
+        let Some(_lock) = self.begin_write() else { return };
+        let before = self.tasks.clone();
+
+        if let Some(task) = self.tasks.iter_mut().find(|t| t.id == id) {
+            let status = task.status.cycle();
+            task.set_status(status);
+            self.commit(format!("status {} {}", id, status), &before);
+            self.message = Some(format!("Task {} {}", id, status));
+        } else {
+            self.message = Some(format!("Task {} not found!", id));
+        }
+    }
+
+    // Cancel the selected task, or reopen it when it is already cancelled
+    pub fn cancel_current(&mut self) {
+        let Some(id) = self.selected_id() else {
+            return;
+        };
+        let today = match get_today() {
+            Ok(today) => today,
+            Err(err) => {
+                self.message = Some(err.to_string());
+                return;
             }
+        };
+        let Some(_lock) = self.begin_write() else { return };
+        let before = self.tasks.clone();
+
+        let Some(i) = self.tasks.iter().position(|t| t.id == id) else {
+            self.message = Some(format!("Task {} not found!", id));
+            return;
+        };
+        if self.tasks[i].status == Status::Cancelled {
+            self.tasks[i].set_status(Status::Pending);
+            self.commit(format!("reopen {}", id), &before);
+            self.message = Some(format!("Task {} reopened!", id));
+            return;
+        }
+
+        // Cancelling one occurrence keeps the series going
+        self.tasks[i].set_status(Status::Cancelled);
+        let next_id = self.store.next_id(&self.tasks);
+        let spawned = spawn_next(&mut self.tasks, i, next_id, &today);
+        self.commit(format!("cancel {}", id), &before);
+        self.message = Some(match spawned {
+            Some(_) => format!("Task {} cancelled. Next: task {}", id, next_id),
+            None => format!("Task {} cancelled", id),
+        });
+    }
+
+    // Cycle the selected task's priority: none -> H -> M -> L -> none
+    pub fn cycle_priority_current(&mut self) {
+        let Some(id) = self.selected_id() else {
+            return;
+        };
+        let Some(_lock) = self.begin_write() else { return };
+        let before = self.tasks.clone();
+
+        if let Some(task) = self.tasks.iter_mut().find(|t| t.id == id) {
+            task.priority = Priority::cycle(task.priority);
+            task.touch();
+            let level = task.priority.map_or("none".to_string(), |priority| priority.to_string());
+            self.commit(format!("priority {} {}", id, level), &before);
+            self.message = Some(format!("Task {} priority: {}", id, level));
+        } else {
+            self.message = Some(format!("Task {} not found!", id));
         }
     }
 
     pub fn delete_current(&mut self) {
-        if let Some(i) = self.list_state.selected() {
-            if i < self.tasks.len() {
-                let id = self.tasks[i].id;
-                self.tasks.remove(i);
-                save_tasks_safe(&self.tasks);
-                self.message = Some(format!("Task {} deleted!", id));
-                
-                // Adjust selection
-                if self.tasks.is_empty() {
-                    self.list_state.select(None);
-                } else if i >= self.tasks.len() {
-                    self.list_state.select(Some(self.tasks.len() - 1));
-                }
+        let Some(id) = self.selected_id() else {
+            return;
+        };
+        let Some(_lock) = self.begin_write() else { return };
+        let before = self.tasks.clone();
+
+        if let Some(i) = self.tasks.iter().position(|t| t.id == id) {
+            // Tasks with subtasks are kept, like `todo delete` without --children
+            if let Err(err) = remove_with_children(&mut self.tasks, i, ChildPolicy::Refuse) {
+                self.message = Some(err.to_string());
+                return;
             }
+            self.commit(format!("delete {}", id), &before);
+            self.message = Some(format!("Task {} deleted!", id));
+            
+            // Adjust selection
+            self.select_id(None);
         }
     }
 
     pub fn add_task(&mut self) {
-        if self.input.trim().is_empty() {
+        let (text, tags) = extract_tags(&self.input);
+        if text.trim().is_empty() {
             self.message = Some("Task text cannot be empty!".to_string());
             self.input.clear();
             self.mode = AppMode::Normal;
@@ -118,22 +484,33 @@ impl App {
             return;
         }
         
-        let id = self.tasks.iter().map(|t| t.id).max().unwrap_or(0) + 1;
-        self.tasks.push(Task {
-            id,
-            text: self.input.clone(),
-            done: false,
-            due_date: Some(crate::task::get_today()),
-        });
-        save_tasks_safe(&self.tasks);
+        let today = match get_today() {
+            Ok(today) => today,
+            Err(err) => {
+                self.message = Some(err.to_string());
+                self.mode = AppMode::Normal;
+                return;
+            }
+        };
+        let Some(_lock) = self.begin_write() else { return };
+        let before = self.tasks.clone();
+        let id = self.store.next_id(&self.tasks);
+        // New tasks go into the project selected in the sidebar, or the context's project,
+        // and get the context's tags
+        let project = self.project.clone().or_else(|| self.context.as_ref().and_then(Query::implied_project));
+        let mut tags = tags;
+        tags.extend(self.context.iter().flat_map(Query::implied_tags));
+        self.tasks.push(Task { tags, project, ..Task::new(id, text.clone(), Some(today)) });
+        self.commit(format!("add {}: {}", id, text), &before);
         self.message = Some(format!("Task {} added!", id));
         self.input.clear();
         self.mode = AppMode::Normal;
-        self.list_state.select(Some(self.tasks.len() - 1));
+        self.select_id(Some(id));
     }
 
     pub fn edit_task(&mut self, id: u32) {
-        if self.input.trim().is_empty() {
+        let (text, tags) = extract_tags(&self.input);
+        if text.trim().is_empty() {
             self.message = Some("Task text cannot be empty!".to_string());
             self.input.clear();
             self.mode = AppMode::Normal;
@@ -147,9 +524,13 @@ impl App {
             return;
         }
         
+        let Some(_lock) = self.begin_write() else { return };
+        let before = self.tasks.clone();
         if let Some(task) = self.tasks.iter_mut().find(|t| t.id == id) {
-            task.text = self.input.clone();
-            save_tasks_safe(&self.tasks);
+            task.text = text;
+            task.tags = tags;
+            task.touch();
+            self.commit(format!("edit {}", id), &before);
             self.message = Some(format!("Task {} updated!", id));
         } else {
             self.message = Some(format!("Task {} not found!", id));
@@ -166,28 +547,114 @@ impl App {
             return;
         }
         
-        // Validate the date format first
-        if let Err(err) = validate_date(&self.input) {
-            self.message = Some(format!("Invalid date: {}", err));
-            self.input.clear();
-            self.mode = AppMode::Normal;
-            return;
-        }
+        // Resolve expressions like "next monday 15:00" first
+        let due = match resolve_due(&self.input) {
+            Ok(due) => due,
+            Err(err) => {
+                self.message = Some(err.to_string());
+                self.input.clear();
+                self.mode = AppMode::Normal;
+                return;
+            }
+        };
         
+        let Some(_lock) = self.begin_write() else { return };
+        let before = self.tasks.clone();
         if let Some(task) = self.tasks.iter_mut().find(|t| t.id == id) {
-            task.due_date = Some(self.input.clone());
-            save_tasks_safe(&self.tasks);
-            self.message = Some(format!("Due date {} set!", self.input));
+            let date = due.to_string();
+            due.apply(task);
+            task.touch();
+            self.commit(format!("due {} {}", id, date), &before);
+            self.message = Some(format!("Due date {} set!", date));
         } else {
             self.message = Some(format!("Task {} not found!", id));
         }
         self.input.clear();
         self.mode = AppMode::Normal;
     }
+
+    // Set the scheduled or wait date from the input ("none" clears it)
+    pub fn set_task_date(&mut self, id: u32, field: DateField) {
+        let date = match self.input.trim().to_lowercase().as_str() {
+            "none" | "clear" | "" => None,
+            expr => match resolve_date(expr) {
+                Ok(date) => Some(date),
+                Err(err) => {
+                    self.message = Some(err.to_string());
+                    self.input.clear();
+                    self.mode = AppMode::Normal;
+                    return;
+                }
+            },
+        };
+
+        let Some(_lock) = self.begin_write() else { return };
+        let before = self.tasks.clone();
+        if let Some(task) = self.tasks.iter_mut().find(|t| t.id == id) {
+            *field.slot(task) = date.clone();
+            task.touch();
+            let shown = date.as_deref().unwrap_or("none");
+            self.commit(format!("{} {} {}", field.name(), id, shown), &before);
+            self.message = Some(format!("{} date {} set!", field.name(), shown));
+            // A task that starts waiting drops out of the list
+            self.select_id(Some(id));
+        } else {
+            self.message = Some(format!("Task {} not found!", id));
+        }
+        self.input.clear();
+        self.mode = AppMode::Normal;
+    }
+
+    // Replace the notes of a task (as returned by the editor); blank notes clear them
+    pub fn set_notes(&mut self, id: u32, notes: &str) {
+        let Some(_lock) = self.begin_write() else { return };
+        let before = self.tasks.clone();
+        let Some(task) = self.tasks.iter_mut().find(|t| t.id == id) else {
+            self.message = Some(format!("Task {} not found!", id));
+            return;
+        };
+        let old = task.notes.clone();
+        task.set_notes(notes);
+        if task.notes == old {
+            self.tasks = before;
+            self.message = Some(format!("Notes of task {} unchanged", id));
+        } else {
+            self.commit(format!("note {}", id), &before);
+            self.message = Some(format!("Notes of task {} updated!", id));
+        }
+    }
+}
+
+// Built-in TUI order, used when the "tui" settings in config.json are invalid
+fn default_view() -> ListView {
+    ListView {
+        sort: SortSpec { keys: vec![SortKey { field: SortField::Id, descending: false }] },
+        group_by: TUI_DEFAULTS.1,
+    }
+}
+
+// Leave the TUI screen while the editor runs on the terminal, then come back
+fn edit_notes_in_editor<B: ratatui::backend::Backend>(terminal: &mut Terminal<B>, app: &mut App) -> io::Result<()> {
+    let Some((id, notes)) = app.selected_task().map(|task| (task.id, task.notes.clone().unwrap_or_default())) else {
+        return Ok(());
+    };
+
+    disable_raw_mode()?;
+    execute!(io::stdout(), LeaveAlternateScreen, DisableMouseCapture)?;
+    let edited = crate::editor::edit_text(&notes);
+    enable_raw_mode()?;
+    execute!(io::stdout(), EnterAlternateScreen, EnableMouseCapture)?;
+    terminal.clear()?;
+
+    match edited {
+        Ok(notes) => app.set_notes(id, &notes),
+        Err(err) => app.message = Some(err.to_string()),
+    }
+    Ok(())
 }
 
 // Launch TUI
-pub fn run_tui() -> io::Result<()> {
+pub fn run_tui(store: TaskStore) -> io::Result<()> {
     // Terminal setup
     enable_raw_mode()?;
     let mut stdout = io::stdout();
@@ -196,7 +663,7 @@ pub fn run_tui() -> io::Result<()> {
     let mut terminal = Terminal::new(backend)?;
 
     // App state
-    let mut app = App::new();
+    let mut app = App::new(store);
     let res = run_app(&mut terminal, &mut app);
 
     // Terminal restore
@@ -222,49 +689,117 @@ fn run_app<B: ratatui::backend::Backend>(
     loop {
         terminal.draw(|f| ui(f, app))?;
 
-        if let Event::Key(key) = event::read()? {
-            if key.kind == KeyEventKind::Press {
-                match &app.mode {
-                    AppMode::Normal => match key.code {
-                        KeyCode::Char('q') => return Ok(()),
-                        KeyCode::Char('j') | KeyCode::Down => app.next(),
-                        KeyCode::Char('k') | KeyCode::Up => app.previous(),
-                        KeyCode::Char(' ') | KeyCode::Enter => app.toggle_current(),
-                        KeyCode::Char('d') => app.delete_current(),
-                        KeyCode::Char('a') => {
-                            app.mode = AppMode::Adding;
-                            app.input.clear();
-                            app.message = Some("Add new task (Enter: save, Esc: cancel)".to_string());
+        // Pick up changes made by other processes while idle
+        if !event::poll(Duration::from_secs(2))? {
+            if app.mode == AppMode::Normal {
+                app.reload();
+            }
+            continue;
+        }
+
+        if let Event::Key(key) = event::read()?
+            && key.kind == KeyEventKind::Press
+        {
+            match &app.mode {
+                AppMode::Normal => match key.code {
+                    KeyCode::Char('q') => return Ok(()),
+                    KeyCode::Char('j') | KeyCode::Down => app.next(),
+                    KeyCode::Char('k') | KeyCode::Up => app.previous(),
+                    KeyCode::Char(' ') | KeyCode::Enter => app.toggle_current(),
+                    KeyCode::Char('d') => app.delete_current(),
+                    KeyCode::Char('p') => app.cycle_priority_current(),
+                    KeyCode::Char('r') if key.modifiers.contains(KeyModifiers::CONTROL) => app.redo(),
+                    KeyCode::Char('u') => app.undo(),
+                    KeyCode::Char('a') => {
+                        app.mode = AppMode::Adding;
+                        app.input.clear();
+                        app.message = Some("Add new task (Enter: save, Esc: cancel)".to_string());
+                    }
+                    KeyCode::Tab => app.cycle_project(true),
+                    KeyCode::BackTab => app.cycle_project(false),
+                    KeyCode::Char('e') => {
+                        if let Some(task) = app.selected_id().and_then(|id| app.tasks.iter().find(|t| t.id == id)) {
+                            let id = task.id;
+                            app.input = task.text_with_tags();
+                            app.mode = AppMode::Editing(id);
+                            app.message = Some("Edit task (Enter: save, Esc: cancel)".to_string());
                         }
-                        KeyCode::Char('e') => {
-                            if let Some(i) = app.list_state.selected() {
-                                if i < app.tasks.len() {
-                                    let id = app.tasks[i].id;
-                                    app.input = app.tasks[i].text.clone();
-                                    app.mode = AppMode::Editing(id);
-                                    app.message = Some("Edit task (Enter: save, Esc: cancel)".to_string());
-                                }
-                            }
+                    }
+                    KeyCode::Char('t') => {
+                        if let Some(id) = app.selected_id() {
+                            app.input.clear();
+                            app.mode = AppMode::SetDueDate(id);
+                            app.message = Some("Set due date (tomorrow, fri 15:00, in 2 weeks, dec 3, YYYY-MM-DD) (Enter: save, Esc: cancel)".to_string());
                         }
-                        KeyCode::Char('t') => {
-                            if let Some(i) = app.list_state.selected() {
-                                if i < app.tasks.len() {
-                                    let id = app.tasks[i].id;
-                                    app.input.clear();
-                                    app.mode = AppMode::SetDueDate(id);
-                                    app.message = Some("Set due date (YYYY-MM-DD) (Enter: save, Esc: cancel)".to_string());
-                                }
-                            }
+                    }
+                    KeyCode::Char(c @ ('s' | 'w')) => {
+                        if let Some(id) = app.selected_id() {
+                            let field = if c == 's' { DateField::Scheduled } else { DateField::Wait };
+                            app.input.clear();
+                            app.mode = AppMode::SetDate(id, field);
+                            app.message = Some(format!("Set {} date (mon, in 2 weeks, dec 3, none) (Enter: save, Esc: cancel)", field.name()));
                         }
+                    }
+                    KeyCode::Char('W') => app.toggle_waiting(),
+                    KeyCode::Char('c') => app.cycle_status_current(),
+                    KeyCode::Char('x') => app.cancel_current(),
+                    KeyCode::Char('v') => app.toggle_details(),
+                    KeyCode::Char('n') => edit_notes_in_editor(terminal, app)?,
+                    KeyCode::Char('/') => {
+                        app.input = app.filter.as_ref().map_or(String::new(), |filter| filter.to_string());
+                        app.mode = AppMode::Filter;
+                        app.message = Some("Filter, e.g. +work pri>=M due.before:friday (Enter: apply, empty: clear, Esc: cancel)".to_string());
+                    }
+                    KeyCode::Char('o') => {
+                        app.input = app.view.sort.to_string();
+                        app.mode = AppMode::Sort;
+                        app.message = Some("Sort by, e.g. due,-priority,id (Enter: apply, empty: default, Esc: cancel)".to_string());
+                    }
+                    KeyCode::Char('g') => app.cycle_group(),
+                    KeyCode::Esc if app.filter.is_some() => {
+                        app.input.clear();
+                        app.apply_filter();
+                    }
+                    KeyCode::Esc => {
+                        app.mode = AppMode::Normal;
+                        app.input.clear();
+                        app.message = None;
+                    }
+                    _ => {}
+                },
+                AppMode::Adding => match key.code {
+                    KeyCode::Enter => app.add_task(),
+                    KeyCode::Esc => {
+                        app.mode = AppMode::Normal;
+                        app.input.clear();
+                        app.message = None;
+                    }
+                    KeyCode::Char(c) => app.input.push(c),
+                    KeyCode::Backspace => {
+                        app.input.pop();
+                    }
+                    _ => {}
+                },
+                AppMode::Editing(id) => {
+                    let id = *id;
+                    match key.code {
+                        KeyCode::Enter => app.edit_task(id),
                         KeyCode::Esc => {
                             app.mode = AppMode::Normal;
                             app.input.clear();
                             app.message = None;
                         }
+                        KeyCode::Char(c) => app.input.push(c),
+                        KeyCode::Backspace => {
+                            app.input.pop();
+                        }
                         _ => {}
-                    },
-                    AppMode::Adding => match key.code {
-                        KeyCode::Enter => app.add_task(),
+                    }
+                }
+                AppMode::SetDueDate(id) => {
+                    let id = *id;
+                    match key.code {
+                        KeyCode::Enter => app.set_due_date(id),
                         KeyCode::Esc => {
                             app.mode = AppMode::Normal;
                             app.input.clear();
@@ -275,38 +810,36 @@ fn run_app<B: ratatui::backend::Backend>(
                             app.input.pop();
                         }
                         _ => {}
-                    },
-                    AppMode::Editing(id) => {
-                        let id = *id;
-                        match key.code {
-                            KeyCode::Enter => app.edit_task(id),
-                            KeyCode::Esc => {
-                                app.mode = AppMode::Normal;
-                                app.input.clear();
-                                app.message = None;
-                            }
-                            KeyCode::Char(c) => app.input.push(c),
-                            KeyCode::Backspace => {
-                                app.input.pop();
-                            }
-                            _ => {}
-                        }
                     }
-                    AppMode::SetDueDate(id) => {
-                        let id = *id;
-                        match key.code {
-                            KeyCode::Enter => app.set_due_date(id),
-                            KeyCode::Esc => {
-                                app.mode = AppMode::Normal;
-                                app.input.clear();
-                                app.message = None;
-                            }
-                            KeyCode::Char(c) => app.input.push(c),
-                            KeyCode::Backspace => {
-                                app.input.pop();
-                            }
-                            _ => {}
+                }
+                AppMode::Filter | AppMode::Sort => match key.code {
+                    KeyCode::Enter if app.mode == AppMode::Sort => app.apply_sort(),
+                    KeyCode::Enter => app.apply_filter(),
+                    KeyCode::Esc => {
+                        app.mode = AppMode::Normal;
+                        app.input.clear();
+                        app.message = None;
+                    }
+                    KeyCode::Char(c) => app.input.push(c),
+                    KeyCode::Backspace => {
+                        app.input.pop();
+                    }
+                    _ => {}
+                },
+                AppMode::SetDate(id, field) => {
+                    let (id, field) = (*id, *field);
+                    match key.code {
+                        KeyCode::Enter => app.set_task_date(id, field),
+                        KeyCode::Esc => {
+                            app.mode = AppMode::Normal;
+                            app.input.clear();
+                            app.message = None;
                         }
+                        KeyCode::Char(c) => app.input.push(c),
+                        KeyCode::Backspace => {
+                            app.input.pop();
+                        }
+                        _ => {}
                     }
                 }
             }
@@ -331,28 +864,115 @@ fn ui(f: &mut Frame, app: &mut App) {
         .block(Block::default().borders(Borders::ALL));
     f.render_widget(title, chunks[0]);
 
+    let body = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Length(24), Constraint::Min(0)])
+        .split(chunks[1]);
+
+    // Project sidebar
+    let entries = app.project_entries();
+    let mut sidebar_items = vec![ListItem::new("All tasks")];
+    sidebar_items.extend(entries.iter().map(|project| {
+        let depth = project.matches('.').count();
+        let name = project.rsplit('.').next().unwrap_or(project);
+        ListItem::new(format!("{}{}", "  ".repeat(depth), name))
+    }));
+    let selected_entry = app.project.as_ref()
+        .and_then(|project| entries.iter().position(|entry| entry == project))
+        .map_or(0, |i| i + 1);
+    let mut sidebar_state = ListState::default();
+    sidebar_state.select(Some(selected_entry));
+
+    let sidebar = List::new(sidebar_items)
+        .block(Block::default().borders(Borders::ALL).title("📁 Projects"))
+        .highlight_style(Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD))
+        .highlight_symbol("> ");
+    f.render_stateful_widget(sidebar, body[0], &mut sidebar_state);
+
     // Task list
+    let now = crate::clock::now().ok();
+    let today = get_today().unwrap_or_default();
+    let mut group_key = None;
     let items: Vec<ListItem> = app
-        .tasks
-        .iter()
-        .map(|task| {
-            let status = if task.done { "✅" } else { "⬜" };
-            let due_info = match &task.due_date {
-                Some(date) => format!(" 📅 {}", date),
-                None => String::new(),
-            };
-            let content = format!("{} [{}] {}{}", task.id, status, task.text, due_info);
-            let style = if task.done {
-                Style::default().fg(Color::DarkGray).add_modifier(Modifier::CROSSED_OUT)
-            } else {
-                Style::default().fg(Color::White)
+        .visible_tree()
+        .into_iter()
+        .map(|(task, depth)| {
+            // A heading line above the first task of each group, not struck through with done tasks
+            let mut heading = None;
+            if let Some((key, label)) = app.group_of(task).filter(|_| depth == 0)
+                && group_key.as_ref() != Some(&key)
+            {
+                let color = if key.is_empty() { Color::DarkGray } else { Color::Cyan };
+                heading = Some(Line::from(Span::styled(label, Style::default().fg(color).add_modifier(Modifier::BOLD)
+                    .remove_modifier(Modifier::CROSSED_OUT | Modifier::ITALIC))));
+                group_key = Some(key);
+            }
+            let status = task.status.emoji();
+            let due_info = DueSpec::of(task).map_or(String::new(), |due| format!(" 📅 {}", due));
+            // Overdue as soon as the due time passes
+            let due_style = match now.and_then(|now| task.due_state(now)) {
+                Some(DueState::Overdue) if !task.is_closed() => Style::default().fg(Color::Red),
+                Some(DueState::Today) if !task.is_closed() => Style::default().fg(Color::Yellow),
+                _ => Style::default(),
             };
-            ListItem::new(Line::from(content)).style(style)
+            let style = status_style(task.status);
+
+            let indent = if depth > 0 { format!("{}└ ", "  ".repeat(depth - 1)) } else { String::new() };
+            let mut spans = vec![Span::raw(format!("{}{} [{}] ", indent, task.id, status))];
+            if let Some(priority) = task.priority {
+                let color = match priority {
+                    Priority::High => Color::Red,
+                    Priority::Medium => Color::Yellow,
+                    Priority::Low => Color::Blue,
+                };
+                spans.push(Span::styled(format!("({}) ", priority), Style::default().fg(color).add_modifier(Modifier::BOLD)));
+            }
+            spans.push(Span::raw(task.text.clone()));
+            for tag in &task.tags {
+                spans.push(Span::styled(format!(" +{}", tag), Style::default().fg(Color::Magenta)));
+            }
+            if let Some((done, total)) = subtask_progress(&app.tasks, task.uuid) {
+                spans.push(Span::styled(format!(" [{}/{} done]", done, total), Style::default().fg(Color::Cyan)));
+            }
+            if task.recur.is_some() {
+                spans.push(Span::raw(" 🔁"));
+            }
+            if task.notes.is_some() {
+                spans.push(Span::raw(" 🗒️"));
+            }
+            if is_blocked(&app.tasks, task) {
+                spans.push(Span::styled(" [blocked]", Style::default().fg(Color::Red)));
+            }
+            if let Some(wait) = task.wait.as_deref().filter(|_| task.is_waiting(&today)) {
+                spans.push(Span::styled(format!(" 💤 {}", wait), Style::default().fg(Color::DarkGray)));
+            } else if let Some(scheduled) = task.scheduled.as_deref().filter(|scheduled| !task.is_closed() && *scheduled > today.as_str()) {
+                spans.push(Span::styled(format!(" ⏳ {}", scheduled), Style::default().fg(Color::DarkGray)));
+            }
+            spans.push(Span::styled(due_info, due_style));
+            let lines: Vec<Line> = heading.into_iter().chain([Line::from(spans)]).collect();
+            ListItem::new(lines).style(style)
         })
         .collect();
 
+    let mut list_title = match &app.project {
+        Some(project) => format!("📋 Task List: {}", project),
+        None => "📋 Task List".to_string(),
+    };
+    if app.show_waiting {
+        list_title.push_str(" (incl. waiting)");
+    }
+    if let Some(context) = &app.context {
+        list_title.push_str(&format!(" [🎯 {}]", context));
+    }
+    if let Some(filter) = &app.filter {
+        list_title.push_str(&format!(" [/ {}]", filter));
+    }
+    list_title.push_str(&format!(" [sort: {}]", app.view.sort));
+    if app.view.group_by != GroupBy::None {
+        list_title.push_str(&format!(" [group: {}]", app.view.group_by));
+    }
     let tasks_list = List::new(items)
-        .block(Block::default().borders(Borders::ALL).title("📋 Task List"))
+        .block(Block::default().borders(Borders::ALL).title(list_title))
         .highlight_style(
             Style::default()
                 .bg(Color::Blue)
@@ -361,7 +981,22 @@ fn ui(f: &mut Frame, app: &mut App) {
         )
         .highlight_symbol(">> ");
 
-    f.render_stateful_widget(tasks_list, chunks[1], &mut app.list_state);
+    // Detail pane to the right of the list
+    let list_area = if app.show_details {
+        let split = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(55), Constraint::Percentage(45)])
+            .split(body[1]);
+        let details = Paragraph::new(detail_lines(app))
+            .wrap(Wrap { trim: false })
+            .block(Block::default().borders(Borders::ALL).title("🔎 Details"));
+        f.render_widget(details, split[1]);
+        split[0]
+    } else {
+        body[1]
+    };
+
+    f.render_stateful_widget(tasks_list, list_area, &mut app.list_state);
 
     // Input box
     let input_text = if app.mode != AppMode::Normal {
@@ -379,7 +1014,7 @@ fn ui(f: &mut Frame, app: &mut App) {
     let help_text = if let Some(msg) = &app.message {
         msg.clone()
     } else if app.mode == AppMode::Normal {
-        "q: quit | j/k: up/down | Space/Enter: toggle | a: add | e: edit | t: due date | d: delete".to_string()
+        "q: quit | j/k: up/down | Space/Enter: toggle | a: add | e: edit | t: due date | s/w: scheduled/wait | W: show waiting | c: status | x: cancel | n: notes | v: details | /: filter | Esc: clear filter | o: sort | g: group | p: priority | d: delete | u: undo | Ctrl-r: redo | Tab: next project".to_string()
     } else {
         "Enter: save | Esc: cancel".to_string()
     };
@@ -389,4 +1024,59 @@ fn ui(f: &mut Frame, app: &mut App) {
         .block(Block::default().borders(Borders::ALL).title("💡 Help"))
         .wrap(Wrap { trim: true });
     f.render_widget(help, chunks[3]);
-}
\ No newline at end of file
+}
+// Fields and notes of the selected task for the detail pane
+fn detail_lines(app: &App) -> Vec<Line<'static>> {
+    let Some(task) = app.selected_task() else {
+        return vec![Line::from("No task selected")];
+    };
+    let label = |name: &str| Span::styled(format!("{:<10}", name), Style::default().fg(Color::Cyan));
+    let field = |name: &str, value: String| Line::from(vec![label(name), Span::raw(value)]);
+    let optional = |value: Option<&str>| value.unwrap_or("-").to_string();
+
+    let mut lines = vec![
+        Line::from(Span::styled(format!("{} {}", task.id, task.text), Style::default().add_modifier(Modifier::BOLD))),
+        Line::from(""),
+        Line::from(vec![label("Status"), Span::styled(format!("{} {}", task.status.emoji(), task.status), status_style(task.status))]),
+        field("Due", DueSpec::of(task).map_or("-".to_string(), |due| due.to_string())),
+        field("Scheduled", optional(task.scheduled.as_deref())),
+        field("Wait", optional(task.wait.as_deref())),
+        field("Priority", task.priority.map_or("-".to_string(), |priority| priority.to_string())),
+        field("Project", optional(task.project.as_deref())),
+    ];
+    if let Some(reason) = &task.block_reason {
+        lines.insert(3, field("Reason", reason.clone()));
+    }
+    if !task.tags.is_empty() {
+        lines.push(field("Tags", task.tags.iter().map(|tag| format!("+{}", tag)).collect::<Vec<_>>().join(" ")));
+    }
+    if let Some(recur) = &task.recur {
+        lines.push(field("Recurs", recur.to_string()));
+    }
+    for (name, value) in &task.udas {
+        let label = app.store.config().udas.get(name).map_or(name.as_str(), |def| def.label(name));
+        lines.push(field(label, value.to_string()));
+    }
+
+    lines.push(Line::from(""));
+    match &task.notes {
+        Some(notes) => {
+            lines.push(Line::from(Span::styled("Notes", Style::default().fg(Color::Cyan))));
+            lines.extend(notes.lines().map(|line| Line::from(line.to_string())));
+        }
+        None => lines.push(Line::from(Span::styled("No notes (n: edit)", Style::default().fg(Color::DarkGray)))),
+    }
+    lines
+}
+
+// List colour of a task by status
+fn status_style(status: Status) -> Style {
+    match status {
+        Status::Pending => Style::default().fg(Color::White),
+        Status::Started => Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
+        Status::Blocked => Style::default().fg(Color::Red),
+        Status::Deferred => Style::default().fg(Color::DarkGray),
+        Status::Done => Style::default().fg(Color::DarkGray).add_modifier(Modifier::CROSSED_OUT),
+        Status::Cancelled => Style::default().fg(Color::DarkGray).add_modifier(Modifier::CROSSED_OUT | Modifier::ITALIC),
+    }
+}
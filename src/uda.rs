@@ -0,0 +1,222 @@
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::fmt;
+use crate::dates::resolve_date;
+use crate::task::{Task, TaskError, validate_date};
+
+// Type of a user-defined attribute, declared in config.json, e.g.
+// "udas": { "estimate": { "type": "number" }, "customer": { "type": "enum", "values": ["acme", "globex"] } }
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum UdaKind {
+    String,
+    Number,
+    // YYYY-MM-DD; set with any date expression
+    Date,
+    // One of the listed values, which also give the sort order
+    Enum { values: Vec<String> },
+}
+
+// Declaration of a user-defined attribute
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct UdaDef {
+    #[serde(flatten)]
+    pub kind: UdaKind,
+    // Column heading in listings (the attribute name when missing)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+}
+
+// Value of a user-defined attribute on a task
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum UdaValue {
+    Number(f64),
+    Text(String),
+}
+
+impl fmt::Display for UdaValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            UdaValue::Number(number) => write!(f, "{}", number),
+            UdaValue::Text(text) => write!(f, "{}", text),
+        }
+    }
+}
+
+impl fmt::Display for UdaKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            UdaKind::String => write!(f, "string"),
+            UdaKind::Number => write!(f, "number"),
+            UdaKind::Date => write!(f, "date"),
+            UdaKind::Enum { values } => write!(f, "enum: {}", values.join(", ")),
+        }
+    }
+}
+
+impl UdaDef {
+    // Convert a value given on the command line
+    pub fn parse(&self, name: &str, input: &str) -> Result<UdaValue, TaskError> {
+        let input = input.trim();
+        let invalid = |expected: &str| TaskError::InvalidInput(format!("'{}' must be {}, got '{}'", name, expected, input));
+        match &self.kind {
+            UdaKind::String if input.is_empty() => Err(invalid("a non-empty string")),
+            UdaKind::String => Ok(UdaValue::Text(input.to_string())),
+            UdaKind::Number => match input.parse::<f64>() {
+                Ok(number) if number.is_finite() => Ok(UdaValue::Number(number)),
+                _ => Err(invalid("a number")),
+            },
+            UdaKind::Date => Ok(UdaValue::Text(resolve_date(input)?)),
+            UdaKind::Enum { values } => values.iter()
+                .find(|value| value.eq_ignore_ascii_case(input))
+                .map(|value| UdaValue::Text(value.clone()))
+                .ok_or_else(|| invalid(&format!("one of {}", values.join(", ")))),
+        }
+    }
+
+    // Check a stored value against the declaration
+    pub fn check(&self, name: &str, value: &UdaValue) -> Result<(), TaskError> {
+        let mismatch = || TaskError::InvalidInput(format!("'{}' should be a {} value, found '{}'", name, self.kind, value));
+        match (&self.kind, value) {
+            (UdaKind::String, UdaValue::Text(_)) | (UdaKind::Number, UdaValue::Number(_)) => Ok(()),
+            (UdaKind::Date, UdaValue::Text(date)) => validate_date(date).map_err(|_| mismatch()),
+            (UdaKind::Enum { values }, UdaValue::Text(text)) if values.contains(text) => Ok(()),
+            _ => Err(mismatch()),
+        }
+    }
+
+    // Order two values of this attribute: numbers numerically, enums in declaration order,
+    // strings and dates alphabetically
+    pub fn compare(&self, a: &UdaValue, b: &UdaValue) -> Ordering {
+        match (a, b) {
+            (UdaValue::Number(a), UdaValue::Number(b)) => a.total_cmp(b),
+            (UdaValue::Text(a), UdaValue::Text(b)) => match &self.kind {
+                UdaKind::Enum { values } => {
+                    let rank = |value: &String| values.iter().position(|v| v == value).unwrap_or(values.len());
+                    rank(a).cmp(&rank(b)).then_with(|| a.cmp(b))
+                }
+                _ => a.cmp(b),
+            },
+            (UdaValue::Number(_), UdaValue::Text(_)) => Ordering::Less,
+            (UdaValue::Text(_), UdaValue::Number(_)) => Ordering::Greater,
+        }
+    }
+
+    pub fn label<'a>(&'a self, name: &'a str) -> &'a str {
+        self.label.as_deref().unwrap_or(name)
+    }
+}
+
+// Attribute names are lowercase words like "ticket" or "due_review"
+pub fn validate_name(name: &str) -> Result<(), TaskError> {
+    let mut chars = name.chars();
+    let valid = chars.next().is_some_and(|c| c.is_ascii_lowercase())
+        && chars.all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '-');
+    if !valid {
+        return Err(TaskError::InvalidInput(format!(
+            "Invalid attribute name '{}' (use lowercase letters, digits, '_' and '-')", name)));
+    }
+    Ok(())
+}
+
+// Look up a declared attribute
+pub fn find_def<'a>(defs: &'a BTreeMap<String, UdaDef>, name: &str) -> Result<&'a UdaDef, TaskError> {
+    defs.get(name).ok_or_else(|| {
+        let known: Vec<&str> = defs.keys().map(String::as_str).collect();
+        let hint = if known.is_empty() { "none are declared in config.json".to_string() } else { format!("declared: {}", known.join(", ")) };
+        TaskError::InvalidInput(format!("Unknown attribute '{}' ({})", name, hint))
+    })
+}
+
+// Problems with the attribute values of loaded tasks: undeclared attributes and values
+// that do not fit their declaration
+pub fn validate_udas(tasks: &[Task], defs: &BTreeMap<String, UdaDef>) -> Vec<String> {
+    let mut problems = Vec::new();
+    for task in tasks {
+        for (name, value) in &task.udas {
+            let result = find_def(defs, name).and_then(|def| def.check(name, value));
+            if let Err(TaskError::InvalidInput(problem)) = result {
+                problems.push(format!("Task {}: {}", task.id, problem));
+            }
+        }
+    }
+    problems
+}
+
+// Comparison in an attribute filter
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UdaOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    // Case-insensitive substring
+    Contains,
+}
+
+// Attribute filter like "estimate>=3", "customer=acme", "ticket~ops" or "ticket=" (not set)
+#[derive(Debug, Clone, PartialEq)]
+pub struct UdaFilter {
+    pub name: String,
+    pub op: UdaOp,
+    // None matches tasks without the attribute
+    pub value: Option<UdaValue>,
+}
+
+impl UdaFilter {
+    pub fn parse(filter: &str, defs: &BTreeMap<String, UdaDef>) -> Result<UdaFilter, TaskError> {
+        // The leftmost operator wins, the longer one at the same position (">=" rather than ">")
+        const OPS: [(&str, UdaOp); 7] = [
+            ("!=", UdaOp::Ne), ("<=", UdaOp::Le), (">=", UdaOp::Ge),
+            ("=", UdaOp::Eq), ("<", UdaOp::Lt), (">", UdaOp::Gt), ("~", UdaOp::Contains),
+        ];
+        let (index, symbol, op) = OPS.iter()
+            .filter_map(|(symbol, op)| filter.find(symbol).map(|index| (index, *symbol, *op)))
+            .min_by_key(|(index, symbol, _)| (*index, std::cmp::Reverse(symbol.len())))
+            .ok_or_else(|| TaskError::InvalidInput(format!("Invalid attribute filter '{}' (use name=value, name>value, name~text, ...)", filter)))?;
+
+        UdaFilter::new(&filter[..index], op, &filter[index + symbol.len()..], defs)
+    }
+
+    // Build a filter from its parts; an empty value with = or != tests whether the attribute is set
+    pub fn new(name: &str, op: UdaOp, input: &str, defs: &BTreeMap<String, UdaDef>) -> Result<UdaFilter, TaskError> {
+        let name = name.trim().to_lowercase();
+        let input = input.trim();
+        let def = find_def(defs, &name)?;
+        let value = match op {
+            _ if input.is_empty() && matches!(op, UdaOp::Eq | UdaOp::Ne) => None,
+            UdaOp::Contains => Some(UdaValue::Text(input.to_lowercase())),
+            _ => Some(def.parse(&name, input)?),
+        };
+        Ok(UdaFilter { name, op, value })
+    }
+
+    pub fn matches(&self, task: &Task, defs: &BTreeMap<String, UdaDef>) -> bool {
+        let actual = task.udas.get(&self.name);
+        let (Some(actual), Some(expected)) = (actual, &self.value) else {
+            // "name=" matches tasks without the attribute, "name!=" tasks with it
+            return match self.op {
+                UdaOp::Eq => actual.is_none() && self.value.is_none(),
+                UdaOp::Ne => actual.is_some() || self.value.is_some(),
+                _ => false,
+            };
+        };
+        let ordering = || match defs.get(&self.name) {
+            Some(def) => def.compare(actual, expected),
+            None => actual.to_string().cmp(&expected.to_string()),
+        };
+        match self.op {
+            UdaOp::Contains => actual.to_string().to_lowercase().contains(&expected.to_string()),
+            UdaOp::Eq => ordering() == Ordering::Equal,
+            UdaOp::Ne => ordering() != Ordering::Equal,
+            UdaOp::Lt => ordering() == Ordering::Less,
+            UdaOp::Le => ordering() != Ordering::Greater,
+            UdaOp::Gt => ordering() == Ordering::Greater,
+            UdaOp::Ge => ordering() != Ordering::Less,
+        }
+    }
+}
@@ -0,0 +1,277 @@
+use chrono::{Datelike, NaiveDate, Weekday};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::fmt;
+use crate::task::{Status, Task, TaskError, TimestampField};
+use crate::uda::UdaDef;
+
+// Field a listing can be sorted by
+#[derive(Debug, Clone, PartialEq)]
+pub enum SortField {
+    Urgency,
+    Due,
+    Priority,
+    Id,
+    Text,
+    Project,
+    Status,
+    Scheduled,
+    Wait,
+    Stamp(TimestampField),
+    // User-defined attribute, compared as its declaration says
+    Attr(String, UdaDef),
+}
+
+impl SortField {
+    fn parse(name: &str, udas: &BTreeMap<String, UdaDef>) -> Result<SortField, TaskError> {
+        Ok(match name {
+            "urgency" | "urg" => SortField::Urgency,
+            "due" => SortField::Due,
+            "priority" | "pri" => SortField::Priority,
+            "id" => SortField::Id,
+            "text" | "description" => SortField::Text,
+            "project" => SortField::Project,
+            "status" => SortField::Status,
+            "scheduled" => SortField::Scheduled,
+            "wait" => SortField::Wait,
+            "created" | "entry" => SortField::Stamp(TimestampField::Created),
+            "modified" => SortField::Stamp(TimestampField::Modified),
+            "completed" | "end" => SortField::Stamp(TimestampField::Completed),
+            "removed" => SortField::Stamp(TimestampField::Removed),
+            name => match udas.get(name) {
+                Some(def) => SortField::Attr(name.to_string(), def.clone()),
+                None => return Err(TaskError::InvalidInput(format!(
+                    "Unknown sort key '{}' (use urgency, due, priority, id, text, project, status, scheduled, wait, \
+                     created, modified, completed, removed or a declared attribute)", name))),
+            },
+        })
+    }
+
+    fn name(&self) -> &str {
+        match self {
+            SortField::Urgency => "urgency",
+            SortField::Due => "due",
+            SortField::Priority => "priority",
+            SortField::Id => "id",
+            SortField::Text => "text",
+            SortField::Project => "project",
+            SortField::Status => "status",
+            SortField::Scheduled => "scheduled",
+            SortField::Wait => "wait",
+            SortField::Stamp(TimestampField::Created) => "created",
+            SortField::Stamp(TimestampField::Modified) => "modified",
+            SortField::Stamp(TimestampField::Completed) => "completed",
+            SortField::Stamp(TimestampField::Removed) => "removed",
+            SortField::Attr(name, _) => name,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SortKey {
+    pub field: SortField,
+    pub descending: bool,
+}
+
+impl SortKey {
+    // Compare two tasks by this key; tasks without a value go last either way
+    fn compare(&self, a: &Task, b: &Task, today: &str) -> Ordering {
+        let direct = |ordering: Ordering| if self.descending { ordering.reverse() } else { ordering };
+        fn by<T: Ord>(a: Option<T>, b: Option<T>, direct: impl Fn(Ordering) -> Ordering) -> Ordering {
+            by_with(a, b, T::cmp, direct)
+        }
+        fn by_with<T>(a: Option<T>, b: Option<T>, cmp: impl Fn(&T, &T) -> Ordering,
+                      direct: impl Fn(Ordering) -> Ordering) -> Ordering {
+            match (a, b) {
+                (Some(a), Some(b)) => direct(cmp(&a, &b)),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => Ordering::Equal,
+            }
+        }
+
+        match &self.field {
+            SortField::Urgency => direct(a.urgency(today).total_cmp(&b.urgency(today))),
+            SortField::Due => by(due_key(a), due_key(b), direct),
+            SortField::Priority => by(a.priority, b.priority, direct),
+            SortField::Id => direct(a.id.cmp(&b.id)),
+            SortField::Text => direct(a.text.to_lowercase().cmp(&b.text.to_lowercase())),
+            SortField::Project => by(a.project.as_ref(), b.project.as_ref(), direct),
+            SortField::Status => direct(a.status.cmp(&b.status)),
+            SortField::Scheduled => by(a.scheduled.as_ref(), b.scheduled.as_ref(), direct),
+            SortField::Wait => by(a.wait.as_ref(), b.wait.as_ref(), direct),
+            SortField::Stamp(field) => by(field.of(a), field.of(b), direct),
+            SortField::Attr(name, def) => by_with(a.udas.get(name), b.udas.get(name), |x, y| def.compare(x, y), direct),
+        }
+    }
+}
+
+// Local due date and time for ordering; date-only tasks come last on their day
+pub fn due_key(task: &Task) -> Option<(String, String)> {
+    match task.due_at() {
+        Some(at) => Some((at.format("%Y-%m-%d").to_string(), at.format("%H:%M").to_string())),
+        None => Some((task.due_date.clone()?, "24:00".to_string())),
+    }
+}
+
+// Comma-separated sort keys like "due,-priority,id"; a leading "-" sorts that key descending
+#[derive(Debug, Clone, PartialEq)]
+pub struct SortSpec {
+    pub keys: Vec<SortKey>,
+}
+
+impl SortSpec {
+    pub fn parse(spec: &str, udas: &BTreeMap<String, UdaDef>) -> Result<SortSpec, TaskError> {
+        let keys = spec.split(',')
+            .map(|key| key.trim().to_lowercase())
+            .filter(|key| !key.is_empty())
+            .map(|key| {
+                let (name, descending) = match key.strip_prefix('-') {
+                    Some(name) => (name.to_string(), true),
+                    None => (key.trim_start_matches('+').to_string(), false),
+                };
+                Ok(SortKey { field: SortField::parse(&name, udas)?, descending })
+            })
+            .collect::<Result<Vec<_>, TaskError>>()?;
+        if keys.is_empty() {
+            return Err(TaskError::InvalidInput("Sort order cannot be empty (e.g. due,-priority,id)".to_string()));
+        }
+        Ok(SortSpec { keys })
+    }
+
+    pub fn compare(&self, a: &Task, b: &Task, today: &str) -> Ordering {
+        self.keys.iter()
+            .map(|key| key.compare(a, b, today))
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or(Ordering::Equal)
+    }
+
+    pub fn sort(&self, tasks: &mut [&Task], today: &str) {
+        tasks.sort_by(|a, b| self.compare(a, b, today));
+    }
+
+    // First timestamp the tasks are sorted by, shown as a column in listings
+    pub fn timestamp(&self) -> Option<TimestampField> {
+        self.keys.iter().find_map(|key| match key.field {
+            SortField::Stamp(field) => Some(field),
+            _ => None,
+        })
+    }
+}
+
+impl fmt::Display for SortSpec {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let keys: Vec<String> = self.keys.iter()
+            .map(|key| format!("{}{}", if key.descending { "-" } else { "" }, key.field.name()))
+            .collect();
+        write!(f, "{}", keys.join(","))
+    }
+}
+
+// How listings split tasks into groups
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum GroupBy {
+    // Month of the due date
+    Month,
+    // ISO week of the due date
+    Week,
+    // Due date
+    Day,
+    Project,
+    // One group per tag; tasks with several tags appear in each
+    Tag,
+    Status,
+    None,
+}
+
+impl GroupBy {
+    pub const ALL: [GroupBy; 7] = [GroupBy::Month, GroupBy::Week, GroupBy::Day, GroupBy::Project, GroupBy::Tag, GroupBy::Status, GroupBy::None];
+
+    // Next grouping when cycling with a single key
+    pub fn next(self) -> GroupBy {
+        let index = GroupBy::ALL.iter().position(|group| *group == self).unwrap_or(0);
+        GroupBy::ALL[(index + 1) % GroupBy::ALL.len()]
+    }
+
+    // Groups a task belongs to as (sort key, heading). Tasks missing the field get an
+    // empty key, so their group comes first.
+    pub fn groups(self, task: &Task) -> Vec<(String, String)> {
+        let due = task.due_date.as_deref().and_then(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok());
+        let no_date = || vec![(String::new(), "[No Due Date]".to_string())];
+        match self {
+            GroupBy::Month => due.map_or_else(no_date, |date| vec![(date.format("%Y-%m").to_string(), date.format("%b %Y").to_string())]),
+            GroupBy::Week => due.map_or_else(no_date, |date| {
+                let week = date.iso_week();
+                let monday = NaiveDate::from_isoywd_opt(week.year(), week.week(), Weekday::Mon).unwrap_or(date);
+                vec![(format!("{:04}-W{:02}", week.year(), week.week()), format!("Week {} ({} – {})",
+                    week.week(), monday.format("%b %-d"), (monday + chrono::Days::new(6)).format("%b %-d, %Y")))]
+            }),
+            GroupBy::Day => due.map_or_else(no_date, |date| vec![(date.format("%Y-%m-%d").to_string(), date.format("%a %b %-d, %Y").to_string())]),
+            GroupBy::Project => match &task.project {
+                Some(project) => vec![(project.clone(), format!("📁 {}", project))],
+                None => vec![(String::new(), "[No Project]".to_string())],
+            },
+            GroupBy::Tag if task.tags.is_empty() => vec![(String::new(), "[No Tags]".to_string())],
+            GroupBy::Tag => task.tags.iter().map(|tag| (tag.clone(), format!("+{}", tag))).collect(),
+            GroupBy::Status => {
+                let rank = Status::ALL.iter().position(|status| *status == task.status).unwrap_or(0);
+                vec![(rank.to_string(), format!("{} {}", task.status.emoji(), task.status))]
+            }
+            GroupBy::None => vec![(String::new(), String::new())],
+        }
+    }
+}
+
+impl fmt::Display for GroupBy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            GroupBy::Month => "month",
+            GroupBy::Week => "week",
+            GroupBy::Day => "day",
+            GroupBy::Project => "project",
+            GroupBy::Tag => "tag",
+            GroupBy::Status => "status",
+            GroupBy::None => "none",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// Sort order and grouping of a listing
+#[derive(Debug, Clone, PartialEq)]
+pub struct ListView {
+    pub sort: SortSpec,
+    pub group_by: GroupBy,
+}
+
+// Default sort order and grouping, set in config.json, e.g.
+// "list": { "sort": "due,-priority,id", "group_by": "project" }
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct ViewSettings {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sort: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group_by: Option<GroupBy>,
+}
+
+// Built-in defaults: command-line listings by urgency in months, the TUI in ID order
+pub const LIST_DEFAULTS: (&str, GroupBy) = ("-urgency,due,id", GroupBy::Month);
+pub const TUI_DEFAULTS: (&str, GroupBy) = ("id", GroupBy::None);
+
+impl ViewSettings {
+    pub fn is_empty(&self) -> bool {
+        self.sort.is_none() && self.group_by.is_none()
+    }
+
+    // Options given on the command line win over these settings, which win over `defaults`
+    pub fn resolve(&self, sort: Option<&str>, group_by: Option<GroupBy>, defaults: (&str, GroupBy),
+                   udas: &BTreeMap<String, UdaDef>) -> Result<ListView, TaskError> {
+        let spec = sort.or(self.sort.as_deref()).unwrap_or(defaults.0);
+        Ok(ListView {
+            sort: SortSpec::parse(spec, udas)?,
+            group_by: group_by.or(self.group_by).unwrap_or(defaults.1),
+        })
+    }
+}
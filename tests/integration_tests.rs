@@ -1,20 +1,31 @@
-use todo::{Task, load_tasks, save_tasks_safe, add_task, delete_task, toggle_task, restore_from_backup};
+use todo::{Task, Status, TaskError, TaskRef, TimestampField, TaskStore, BackendKind, BackupSettings, Config, Operation, Priority, SqliteBackend, AddOptions, add_task_with, set_attributes, UdaValue, set_task_status, set_task_status_with, set_notes, append_notes, set_due_date, set_task_date, DateField, FixedClock, set_clock, depend_task, recur_task, dependency_graph, toggle_task_with, delete_task_with, ChildPolicy, set_priority, rename_tag, Query, BulkChange, bulk_update, save_query, delete_query, set_context, clear_context, App, AppMode, GroupBy, ViewSettings, add_task, delete_task, edit_task, toggle_task, migrate_result};
+use todo::schema::SCHEMA_VERSION;
+use tempfile::TempDir;
 
-/// Integration tests that test the full workflow of the todo application
-/// These tests interact with the real file system but use isolated test data
+// Integration tests that test the full workflow of the todo application
+// These tests interact with the real file system inside a temporary data directory
+
+// Create a store in a fresh temporary directory
+fn temp_store() -> (TempDir, TaskStore) {
+    let dir = tempfile::tempdir().expect("Temp dir should be created");
+    let store = TaskStore::open(dir.path()).expect("Store should open");
+    (dir, store)
+}
 
 #[test]
 fn test_full_task_workflow() {
+    let (_dir, store) = temp_store();
+
     // Get initial state
-    let initial_tasks = load_tasks();
+    let initial_tasks = store.load();
     let initial_count = initial_tasks.len();
     
     // Add a test task with unique name to avoid conflicts
     let unique_task_name = format!("Integration test task {}", std::process::id());
-    add_task(unique_task_name.clone(), 0);
+    add_task(&store, unique_task_name.clone(), 0);
     
     // Verify task was added
-    let tasks_after_add = load_tasks();
+    let tasks_after_add = store.load();
     assert_eq!(tasks_after_add.len(), initial_count + 1);
     
     // Find the test task
@@ -23,31 +34,33 @@ fn test_full_task_workflow() {
         .expect("Test task should exist");
     
     let test_task_id = test_task.id;
-    assert!(!test_task.done);
+    assert!(!test_task.is_done());
     
     // Toggle the task
-    toggle_task(test_task_id);
+    toggle_task(&store, test_task_id);
     
     // Verify task is now completed
-    let tasks_after_toggle = load_tasks();
+    let tasks_after_toggle = store.load();
     let completed_task = tasks_after_toggle.iter()
         .find(|t| t.id == test_task_id)
         .expect("Test task should still exist");
-    assert!(completed_task.done);
+    assert!(completed_task.is_done());
     
     // Delete the test task
-    delete_task(test_task_id);
+    delete_task(&store, test_task_id);
     
     // Verify task was deleted
-    let final_tasks = load_tasks();
+    let final_tasks = store.load();
     assert_eq!(final_tasks.len(), initial_count);
     assert!(!final_tasks.iter().any(|t| t.id == test_task_id));
 }
 
 #[test]
 fn test_task_persistence() {
+    let (dir, store) = temp_store();
+
     // Test that tasks persist between saves and loads
-    let initial_tasks = load_tasks();
+    let initial_tasks = store.load();
     let initial_count = initial_tasks.len();
     
     // Create some test data
@@ -55,15 +68,16 @@ fn test_task_persistence() {
     new_tasks.push(Task {
         id: 99999, // Use a high ID to avoid conflicts
         text: "Persistence test".to_string(),
-        done: false,
+        status: Status::Pending,
         due_date: Some("2025-12-25".to_string()),
+        ..Task::default()
     });
     
     // Save the tasks
-    save_tasks_safe(&new_tasks);
+    store.save_safe(&new_tasks);
     
-    // Load tasks again and verify persistence
-    let loaded_tasks = load_tasks();
+    // Load tasks again through a fresh store and verify persistence
+    let loaded_tasks = TaskStore::open(dir.path()).unwrap().load();
     assert_eq!(loaded_tasks.len(), initial_count + 1);
     
     let persistent_task = loaded_tasks.iter()
@@ -72,72 +86,71 @@ fn test_task_persistence() {
     
     assert_eq!(persistent_task.text, "Persistence test");
     assert_eq!(persistent_task.due_date, Some("2025-12-25".to_string()));
-    assert!(!persistent_task.done);
-    
-    // Cleanup - restore original state
-    save_tasks_safe(&initial_tasks);
+    assert!(!persistent_task.is_done());
 }
 
 #[test]
 fn test_error_handling_workflow() {
+    let (_dir, store) = temp_store();
+
     // Test that error conditions don't crash the application
     
     // Try to operate on non-existent task
-    toggle_task(99999999);
-    delete_task(99999999);
+    toggle_task(&store, 99999999);
+    delete_task(&store, 99999999);
     
     // Try to add invalid tasks
-    add_task("".to_string(), 0); // Empty text
-    add_task("a".repeat(501), 0); // Too long
+    add_task(&store, "".to_string(), 0); // Empty text
+    add_task(&store, "a".repeat(501), 0); // Too long
     
     // Application should still be in a valid state
-    let tasks = load_tasks();
-    assert!(tasks.len() == tasks.len()); // Should not crash - just check it loads
+    let tasks = store.load();
+    assert!(tasks.is_empty());
 }
 
 #[test]
 fn test_backup_workflow() {
-    // Test the backup and restore functionality
-    
-    // This test assumes there might not be a backup file
-    match restore_from_backup() {
-        Ok(_) => {
-            // If restore succeeded, verify tasks are still loadable
-            let tasks = load_tasks();
-            assert!(tasks.len() == tasks.len()); // Just verify it doesn't crash
-        }
-        Err(_) => {
-            // If restore failed (no backup), that's also fine for this test
-            // Just verify the application is still in a valid state
-            let tasks = load_tasks();
-            assert!(tasks.len() == tasks.len()); // Just verify it doesn't crash
-        }
-    }
+    let (_dir, store) = temp_store();
+
+    // Nothing saved yet, so there is no backup to restore
+    assert!(store.restore_from_backup().is_err());
+
+    // Second save backs up the first one
+    add_task(&store, "First".to_string(), 0);
+    add_task(&store, "Second".to_string(), 0);
+    assert_eq!(store.load().len(), 2);
+
+    store.restore_from_backup().expect("Backup should exist after two saves");
+    let tasks = store.load();
+    assert_eq!(tasks.len(), 1);
+    assert_eq!(tasks[0].text, "First");
 }
 
 #[test] 
 fn test_data_validation_integration() {
+    let (_dir, store) = temp_store();
+
     // Test that the data validation works end-to-end
     
-    let initial_tasks = load_tasks();
+    let initial_tasks = store.load();
     let initial_count = initial_tasks.len();
     
     // These operations should be rejected gracefully (they print error messages but don't add tasks)
-    add_task("".to_string(), 0);                    // Empty text
-    add_task("   ".to_string(), 0);                 // Whitespace only
+    add_task(&store, "".to_string(), 0);                    // Empty text
+    add_task(&store, "   ".to_string(), 0);                 // Whitespace only
     
     // Verify that invalid add operations didn't add tasks
-    let tasks_after_invalid_adds = load_tasks();
+    let tasks_after_invalid_adds = store.load();
     assert_eq!(tasks_after_invalid_adds.len(), initial_count); // No tasks should be added
     
     // Try various invalid operations - none should crash or corrupt data
-    toggle_task(0);           // Invalid ID
-    delete_task(0);           // Invalid ID  
-    toggle_task(999999);      // Non-existent ID
-    delete_task(999999);      // Non-existent ID
+    toggle_task(&store, 0);           // Invalid ID
+    delete_task(&store, 0);           // Invalid ID  
+    toggle_task(&store, 999999);      // Non-existent ID
+    delete_task(&store, 999999);      // Non-existent ID
     
     // Verify data integrity is maintained - count should be unchanged since invalid operations were rejected
-    let final_tasks = load_tasks();
+    let final_tasks = store.load();
     assert_eq!(final_tasks.len(), initial_count);
     
     // Verify all existing tasks still have valid IDs and text
@@ -152,4 +165,1225 @@ fn test_data_validation_integration() {
             assert_eq!(due_date.chars().nth(7).unwrap(), '-');
         }
     }
-}
\ No newline at end of file
+}
+#[test]
+fn test_in_memory_store_trash() {
+    let store = TaskStore::from_json(r#"[{"id":1,"text":"Old task","done":true}]"#)
+        .expect("JSON should parse");
+
+    let tasks = store.load();
+    store.trash(tasks);
+    store.save_safe(&[]);
+
+    assert!(store.load().is_empty());
+    assert_eq!(store.load_removed().len(), 1);
+    assert_eq!(store.location(), "(in memory)");
+}
+
+#[test]
+fn test_migrate_to_sqlite() {
+    let (dir, store) = temp_store();
+
+    add_task(&store, "Keep me".to_string(), 0);
+    add_task(&store, "Remove me".to_string(), 0);
+    let mut tasks = store.load();
+    let removed = tasks.pop().unwrap();
+    store.trash(vec![removed]);
+    store.save_safe(&tasks);
+
+    let (task_count, removed_count) = migrate_result(&store, BackendKind::Sqlite).unwrap();
+    assert_eq!((task_count, removed_count), (1, 1));
+    assert_eq!(Config::load(dir.path()).unwrap().backend, BackendKind::Sqlite);
+
+    // Reopening picks the sqlite backend from config.json
+    let sqlite_store = TaskStore::open(dir.path()).unwrap();
+    assert_eq!(sqlite_store.backend_name(), "sqlite");
+    assert_eq!(sqlite_store.load()[0].text, "Keep me");
+    assert_eq!(sqlite_store.load_removed()[0].text, "Remove me");
+
+    // Migrating again to the same backend is refused
+    assert!(migrate_result(&sqlite_store, BackendKind::Sqlite).is_err());
+}
+
+#[test]
+fn test_sqlite_backend_updates() {
+    let dir = tempfile::tempdir().unwrap();
+    Config { backend: BackendKind::Sqlite, ..Config::default() }.save(dir.path()).unwrap();
+    let store = TaskStore::open(dir.path()).unwrap();
+
+    add_task(&store, "One".to_string(), 0);
+    add_task(&store, "Two".to_string(), 0);
+    toggle_task(&store, 1);
+    delete_task(&store, 2);
+
+    let tasks = store.load();
+    assert_eq!(tasks.len(), 1);
+    assert!(tasks[0].is_done());
+    assert!(dir.path().join("todos.db").exists());
+    assert!(!dir.path().join("todos.json").exists());
+}
+
+#[test]
+fn test_store_lock_is_exclusive() {
+    let (dir, store) = temp_store();
+    let other = TaskStore::open(dir.path()).unwrap();
+
+    let lock = store.try_lock().expect("First lock should succeed");
+    assert!(matches!(other.try_lock(), Err(TaskError::Locked(_))));
+
+    drop(lock);
+    assert!(other.try_lock().is_ok());
+}
+
+#[test]
+fn test_save_leaves_no_temp_files() {
+    let (dir, store) = temp_store();
+
+    add_task(&store, "First".to_string(), 0);
+    add_task(&store, "Second".to_string(), 0);
+
+    let leftovers: Vec<_> = std::fs::read_dir(dir.path()).unwrap()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_name().to_string_lossy().ends_with(".tmp"))
+        .collect();
+    assert!(leftovers.is_empty());
+    assert_eq!(store.load().len(), 2);
+}
+
+#[test]
+fn test_backup_generations_rotate() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = Config { backups: BackupSettings { keep: 3, max_age_days: None, ..BackupSettings::default() }, ..Config::default() };
+    config.save(dir.path()).unwrap();
+    let store = TaskStore::open(dir.path()).unwrap();
+
+    for i in 1..=6 {
+        add_task(&store, format!("Task {}", i), 0);
+    }
+
+    // Only the configured number of generations survive, newest first
+    let backups = store.backups().unwrap();
+    assert_eq!(backups.len(), 3);
+    assert_eq!(backups[0].load().unwrap().len(), 5);
+    assert_eq!(backups[2].load().unwrap().len(), 3);
+
+    // Restoring an older generation keeps the current tasks as the newest backup
+    let restored = store.restore_generation(3).unwrap();
+    assert_eq!(restored.len(), 3);
+    assert_eq!(store.load().len(), 3);
+    assert_eq!(store.backup(1).unwrap().load().unwrap().len(), 6);
+
+    assert!(store.restore_generation(42).is_err());
+}
+
+#[test]
+fn test_backup_pruning_by_age() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = Config { backups: BackupSettings { keep: 10, max_age_days: Some(30), ..BackupSettings::default() }, ..Config::default() };
+    config.save(dir.path()).unwrap();
+    let store = TaskStore::open(dir.path()).unwrap();
+    add_task(&store, "First".to_string(), 0);
+    add_task(&store, "Second".to_string(), 0);
+
+    // Generations older than max_age_days go on the next save
+    let backups_dir = dir.path().join("backups");
+    let newest = store.backup(1).unwrap().path;
+    std::fs::copy(&newest, backups_dir.join("todos-20200101T000000000.json")).unwrap();
+    assert_eq!(store.backups().unwrap().len(), 2);
+    add_task(&store, "Third".to_string(), 0);
+    let backups = store.backups().unwrap();
+    assert_eq!(backups.len(), 2);
+    assert!(backups.iter().all(|backup| backup.created.format("%Y").to_string() != "2020"));
+
+    // The newest generation is kept however old it is
+    for backup in &backups {
+        std::fs::remove_file(&backup.path).unwrap();
+    }
+    std::fs::copy(dir.path().join("todos.json"), backups_dir.join("todos-20200101T000000000.json")).unwrap();
+    todo::backup::prune_backups(dir.path(), &config.backups).unwrap();
+    assert_eq!(store.backups().unwrap().len(), 1);
+}
+
+#[test]
+fn test_failed_backups_do_not_block_saves() {
+    let (dir, store) = temp_store();
+    add_task(&store, "First".to_string(), 0);
+    add_task(&store, "Second".to_string(), 0);
+
+    // An old generation that cannot be removed is skipped; the rest are pruned
+    let backups_dir = dir.path().join("backups");
+    std::fs::create_dir(backups_dir.join("todos-20200101T000000000.json")).unwrap();
+    let settings = BackupSettings { keep: 1, ..BackupSettings::default() };
+    assert!(todo::backup::prune_backups(dir.path(), &settings).is_err());
+    add_task(&store, "Third".to_string(), 0);
+    assert_eq!(store.load().len(), 3);
+
+    // No backup can be written at all: the save still goes through
+    std::fs::remove_dir_all(&backups_dir).unwrap();
+    std::fs::write(&backups_dir, "not a directory").unwrap();
+    add_task(&store, "Fourth".to_string(), 0);
+    assert_eq!(store.load().len(), 4);
+}
+
+#[test]
+fn test_corrupt_store_recovers_from_older_generation() {
+    let (dir, store) = temp_store();
+    add_task(&store, "First".to_string(), 0);
+    add_task(&store, "Second".to_string(), 0);
+    add_task(&store, "Third".to_string(), 0);
+
+    // Both the main file and the newest generation are damaged; generation 2 still loads
+    std::fs::write(dir.path().join("todos.json"), "{ not json").unwrap();
+    std::fs::write(store.backup(1).unwrap().path, "[oops").unwrap();
+    let tasks = store.load_result().unwrap();
+    assert_eq!(tasks.iter().map(|t| t.text.as_str()).collect::<Vec<_>>(), vec!["First"]);
+    assert_eq!(TaskStore::open(dir.path()).unwrap().load().len(), 1);
+}
+
+#[test]
+fn test_sqlite_backups_are_periodic_snapshots() {
+    let dir = tempfile::tempdir().unwrap();
+    Config { backend: BackendKind::Sqlite, ..Config::default() }.save(dir.path()).unwrap();
+    let store = TaskStore::open(dir.path()).unwrap();
+    for i in 1..=4 {
+        add_task(&store, format!("Task {}", i), 0);
+    }
+
+    // One copy of todos.db per interval, and no JSON copies
+    let backups = store.backups().unwrap();
+    assert_eq!(backups.len(), 1);
+    assert_eq!(backups[0].path.extension().unwrap(), "db");
+    assert_eq!(backups[0].load().unwrap().len(), 1);
+    // Inspecting a generation leaves the file as it was
+    let bytes = std::fs::read(&backups[0].path).unwrap();
+    backups[0].load().unwrap();
+    assert_eq!(std::fs::read(&backups[0].path).unwrap(), bytes);
+
+    // Restoring always keeps the current tasks as a new generation
+    let restored = store.restore_generation(1).unwrap();
+    assert_eq!(restored.len(), 1);
+    assert_eq!(store.load().len(), 1);
+    assert_eq!(store.backup(1).unwrap().load().unwrap().len(), 4);
+}
+
+#[test]
+fn test_undo_redo_journal() {
+    let (dir, store) = temp_store();
+
+    add_task(&store, "First".to_string(), 0);
+    add_task(&store, "Second".to_string(), 0);
+    toggle_task(&store, 1);
+    edit_task(&store, 2, "Second edited".to_string());
+    delete_task(&store, 1);
+    assert_eq!(store.journal().unwrap().undo_count(), 5);
+
+    // Undo delete and edit
+    assert_eq!(store.undo().unwrap().unwrap().description, "delete 1");
+    assert_eq!(store.undo().unwrap().unwrap().description, "edit 2");
+    let tasks = store.load();
+    assert_eq!(tasks.len(), 2);
+    assert!(tasks[0].is_done());
+    assert_eq!(tasks[1].text, "Second");
+
+    // Redo the edit only
+    assert_eq!(store.redo().unwrap().unwrap().description, "edit 2");
+    assert_eq!(store.load()[1].text, "Second edited");
+    let journal = store.journal().unwrap();
+    assert_eq!((journal.undo_count(), journal.redo_count()), (4, 1));
+
+    // A new change discards the redo tail
+    add_task(&store, "Third".to_string(), 0);
+    assert!(store.redo().unwrap().is_none());
+
+    // The journal survives reopening the store
+    let reopened = TaskStore::open(dir.path()).unwrap();
+    assert_eq!(reopened.journal().unwrap().undo_count(), 5);
+}
+
+#[test]
+fn test_undo_restores_trashed_tasks() {
+    let store = TaskStore::in_memory();
+    add_task(&store, "Old task".to_string(), 0);
+
+    let before = store.load();
+    let trashed = before.clone();
+    store.save_recorded(&[], Operation::new("remove 1 task(s)", &before, &[]).with_trashed(trashed.clone())).unwrap();
+    store.trash(trashed);
+    assert_eq!(store.load_removed().len(), 1);
+
+    store.undo().unwrap();
+    assert_eq!(store.load().len(), 1);
+    assert!(store.load_removed().is_empty());
+
+    store.redo().unwrap();
+    assert!(store.load().is_empty());
+    assert_eq!(store.load_removed().len(), 1);
+}
+
+#[test]
+fn test_undo_covers_restores_and_removed_tag_renames() {
+    let (_dir, store) = temp_store();
+    add_task(&store, "First +backend".to_string(), 0);
+    add_task(&store, "Second".to_string(), 0);
+
+    // Undoing a restore brings back the tasks it replaced
+    store.restore_generation(1).unwrap();
+    assert_eq!(store.load().len(), 1);
+    assert_eq!(store.undo().unwrap().unwrap().description, "restore backup 1");
+    assert_eq!(store.load().len(), 2);
+    store.redo().unwrap();
+    assert_eq!(store.load().len(), 1);
+    store.undo().unwrap();
+
+    // Undoing a tag rename also reverts it on removed tasks
+    let first = store.load().remove(0);
+    store.save(&store.load()[1..]).unwrap();
+    store.trash(vec![first]);
+    // Only removed tasks match, so the active list is not written again
+    let generations = store.backups().unwrap().len();
+    rename_tag(&store, "backend", "server");
+    assert_eq!(store.backups().unwrap().len(), generations);
+    assert!(store.load_removed()[0].tags.contains("server"));
+    store.undo().unwrap();
+    assert!(store.load_removed()[0].tags.contains("backend"));
+    store.redo().unwrap();
+    assert!(store.load_removed()[0].tags.contains("server"));
+}
+
+#[test]
+fn test_uuids_are_migrated_and_stable() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("todos.json"), r#"[{"id":1,"text":"Legacy","done":false}]"#).unwrap();
+    std::fs::write(dir.path().join("removed.json"), r#"[{"id":2,"text":"Old","done":true}]"#).unwrap();
+    let store = TaskStore::open(dir.path()).unwrap();
+
+    // Legacy files get UUIDs on first load, and keep them afterwards
+    let uuid = store.load()[0].uuid;
+    assert!(!uuid.is_nil());
+    assert_eq!(store.load()[0].uuid, uuid);
+    assert!(std::fs::read_to_string(dir.path().join("todos.json")).unwrap().contains(&uuid.to_string()));
+    assert!(!store.load_removed()[0].uuid.is_nil());
+
+    // Tasks can be addressed by UUID
+    toggle_task(&store, TaskRef::Uuid(uuid));
+    assert!(store.load()[0].is_done());
+
+    // New IDs skip those used by removed tasks, and a deleted ID gets a new UUID
+    add_task(&store, "New".to_string(), 0);
+    let added = store.load()[1].clone();
+    assert_eq!(added.id, 3);
+    delete_task(&store, 3);
+    add_task(&store, "Newer".to_string(), 0);
+    assert_ne!(store.load()[1].uuid, added.uuid);
+}
+
+#[test]
+fn test_task_timestamps() {
+    let store = TaskStore::in_memory();
+    add_task(&store, "Timed".to_string(), 0);
+
+    let task = store.load()[0].clone();
+    assert!(task.created_at.is_some());
+    assert_eq!(task.created_at, task.modified_at);
+    assert!(task.completed_at.is_none());
+
+    // Completing sets completed_at, reopening clears it
+    toggle_task(&store, 1);
+    let done = store.load()[0].clone();
+    assert!(done.completed_at.is_some());
+    assert!(done.modified_at >= task.modified_at);
+    toggle_task(&store, 1);
+    assert!(store.load()[0].completed_at.is_none());
+
+    edit_task(&store, 1, "Timed edited".to_string());
+    assert!(store.load()[0].modified_at >= done.modified_at);
+    assert_eq!(store.load()[0].created_at, task.created_at);
+
+    // Moving to removed storage stamps removed_at
+    store.trash(store.load());
+    assert!(store.load_removed()[0].removed_at.is_some());
+    assert_eq!(TimestampField::Removed.of(&store.load_removed()[0]), store.load_removed()[0].removed_at);
+}
+
+#[test]
+fn test_bare_array_is_migrated_to_envelope() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("todos.json"), r#"[{"id":1,"text":"Legacy","done":false}]"#).unwrap();
+    let store = TaskStore::open(dir.path()).unwrap();
+    add_task(&store, "Second".to_string(), 0);
+
+    let value: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(dir.path().join("todos.json")).unwrap()).unwrap();
+    assert_eq!(value["version"], SCHEMA_VERSION);
+    assert_eq!(value["tasks"].as_array().unwrap().len(), 2);
+    assert_eq!(store.load()[0].text, "Legacy");
+}
+
+#[test]
+fn test_unknown_fields_survive_round_trip() {
+    let dir = tempfile::tempdir().unwrap();
+    let json = r#"{"version":1,"owner":"me","tasks":[{"id":1,"text":"Keep","done":false,"color":"teal"}]}"#;
+    std::fs::write(dir.path().join("todos.json"), json).unwrap();
+    let store = TaskStore::open(dir.path()).unwrap();
+
+    toggle_task(&store, 1);
+
+    let value: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(dir.path().join("todos.json")).unwrap()).unwrap();
+    assert_eq!(value["owner"], "me");
+    assert_eq!(value["tasks"][0]["color"], "teal");
+    assert_eq!(value["tasks"][0]["status"], "done");
+}
+
+#[test]
+fn test_done_flag_is_migrated_to_status() {
+    let dir = tempfile::tempdir().unwrap();
+    let json = r#"{"version":1,"tasks":[{"id":1,"text":"Open","done":false},{"id":2,"text":"Finished","done":true}]}"#;
+    std::fs::write(dir.path().join("todos.json"), json).unwrap();
+    let store = TaskStore::open(dir.path()).unwrap();
+
+    let tasks = store.load();
+    assert_eq!((tasks[0].status, tasks[1].status), (Status::Pending, Status::Done));
+    set_task_status(&store, 1, Status::Started, None);
+
+    let value: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(dir.path().join("todos.json")).unwrap()).unwrap();
+    assert_eq!(value["version"], SCHEMA_VERSION);
+    assert_eq!(value["tasks"][0]["status"], "started");
+    assert_eq!(value["tasks"][1]["status"], "done");
+    assert!(value["tasks"][1].get("done").is_none());
+
+    // SQLite rows written by schema 1 are migrated when the database is opened
+    let sqlite_dir = tempfile::tempdir().unwrap();
+    drop(SqliteBackend::open(sqlite_dir.path()).unwrap());
+    let conn = rusqlite::Connection::open(sqlite_dir.path().join("todos.db")).unwrap();
+    conn.execute("INSERT INTO tasks (id, data) VALUES (1, '{\"id\":1,\"text\":\"Old\",\"done\":true}')", []).unwrap();
+    conn.pragma_update(None, "user_version", 1).unwrap();
+    drop(conn);
+    let backend = SqliteBackend::open(sqlite_dir.path()).unwrap();
+    assert_eq!(todo::StorageBackend::load_tasks(&backend).unwrap()[0].status, Status::Done);
+}
+
+#[test]
+fn test_status_commands() {
+    let _clock = set_clock(FixedClock::on(chrono::NaiveDate::from_ymd_opt(2025, 11, 6).unwrap()));
+    let store = TaskStore::in_memory();
+    add_task(&store, "Migrate database".to_string(), 0);
+    add_task(&store, "Announce".to_string(), 0);
+    add_task(&store, "Standup".to_string(), 0);
+    depend_task(&store, 2, 1, false);
+    recur_task(&store, 3, "daily", None);
+
+    set_task_status(&store, 1, Status::Started, None);
+    assert_eq!(store.load()[0].status, Status::Started);
+    assert!(store.load()[0].completed_at.is_none());
+
+    set_task_status(&store, 2, Status::Blocked, Some("waiting on legal".to_string()));
+    assert_eq!(store.load()[1].block_reason.as_deref(), Some("waiting on legal"));
+    set_task_status(&store, 2, Status::Deferred, None);
+    assert_eq!((store.load()[1].status, store.load()[1].block_reason.clone()), (Status::Deferred, None));
+
+    // Cancelling closes the task, so dependents are no longer held up by it
+    set_task_status(&store, 1, Status::Cancelled, None);
+    let tasks = store.load();
+    assert!(tasks[0].is_closed() && tasks[0].completed_at.is_some());
+    assert!(!todo::is_blocked(&tasks, &tasks[1]));
+
+    // Cancelling one occurrence of a recurring task keeps the series going
+    set_task_status(&store, 3, Status::Cancelled, None);
+    let tasks = store.load();
+    assert_eq!(tasks.len(), 4);
+    assert_eq!((tasks[3].text.as_str(), tasks[3].status, tasks[3].due_date.as_deref()), ("Standup", Status::Pending, Some("2025-11-07")));
+
+    // `todo done` on a cancelled task reopens it
+    toggle_task(&store, 1);
+    assert_eq!(store.load()[0].status, Status::Pending);
+    store.undo().unwrap();
+    assert_eq!(store.load()[0].status, Status::Cancelled);
+
+    // The TUI cycles through the open states and toggles cancellation
+    let mut app = App::new(store);
+    app.list_state.select(Some(0));
+    let id = app.visible_tasks()[0].id;
+    app.cycle_status_current();
+    app.cycle_status_current();
+    assert_eq!(app.tasks.iter().find(|t| t.id == id).unwrap().status, Status::Started);
+    app.cancel_current();
+    assert_eq!(app.tasks.iter().find(|t| t.id == id).unwrap().status, Status::Cancelled);
+    app.cancel_current();
+    assert_eq!(app.tasks.iter().find(|t| t.id == id).unwrap().status, Status::Pending);
+}
+
+#[test]
+fn test_newer_schema_is_not_overwritten() {
+    let dir = tempfile::tempdir().unwrap();
+    let json = format!(r#"{{"version":{},"tasks":[{{"id":1,"text":"Future","done":false}}]}}"#, SCHEMA_VERSION + 1);
+    std::fs::write(dir.path().join("todos.json"), &json).unwrap();
+    let store = TaskStore::open(dir.path()).unwrap();
+
+    assert!(matches!(store.load_result(), Err(TaskError::NewerSchema(_))));
+    assert!(matches!(store.save(&[]), Err(TaskError::NewerSchema(_))));
+    add_task(&store, "Should not be saved".to_string(), 0);
+    assert_eq!(std::fs::read_to_string(dir.path().join("todos.json")).unwrap(), json);
+
+    // SQLite stores keep the version in user_version
+    let sqlite_dir = tempfile::tempdir().unwrap();
+    drop(SqliteBackend::open(sqlite_dir.path()).unwrap());
+    let conn = rusqlite::Connection::open(sqlite_dir.path().join("todos.db")).unwrap();
+    conn.pragma_update(None, "user_version", SCHEMA_VERSION + 1).unwrap();
+    assert!(matches!(SqliteBackend::open(sqlite_dir.path()), Err(TaskError::NewerSchema(_))));
+}
+
+#[test]
+fn test_priority_commands() {
+    let store = TaskStore::in_memory();
+    add_task_with(&store, "Urgent".to_string(), AddOptions { priority: Some(Priority::High), ..AddOptions::default() });
+    add_task(&store, "Normal".to_string(), 0);
+    assert_eq!(store.load()[0].priority, Some(Priority::High));
+
+    set_priority(&store, 2, "L");
+    assert_eq!(store.load()[1].priority, Some(Priority::Low));
+    set_priority(&store, 2, "none");
+    assert_eq!(store.load()[1].priority, None);
+
+    // Invalid levels leave the task alone
+    set_priority(&store, 1, "urgent");
+    assert_eq!(store.load()[0].priority, Some(Priority::High));
+    assert_eq!(store.journal().unwrap().undo_count(), 4);
+}
+
+#[test]
+fn test_tags_filter_and_rename() {
+    let store = TaskStore::in_memory();
+    add_task(&store, "Fix login +backend +urgent".to_string(), 0);
+    add_task(&store, "API docs +backend".to_string(), 0);
+    add_task(&store, "Old +backend".to_string(), 0);
+    edit_task(&store, 2, "API reference +docs".to_string());
+
+    // Editing replaces the tag set with the tags in the new text
+    let tasks = store.load();
+    assert_eq!(tasks[0].text, "Fix login");
+    assert_eq!(tasks[1].tags.iter().collect::<Vec<_>>(), vec!["docs"]);
+    assert_eq!(tasks[1].text_with_tags(), "API reference +docs");
+    edit_task(&store, 2, "API docs +backend".to_string());
+    let tasks = store.load();
+    assert_eq!(tasks[1].tags.iter().collect::<Vec<_>>(), vec!["backend"]);
+
+    let filter = Query::parse("+backend -urgent", &Default::default()).unwrap();
+    let matching: Vec<u32> = tasks.iter().filter(|t| filter.matches(t, &tasks)).map(|t| t.id).collect();
+    assert_eq!(matching, vec![2, 3]);
+    assert!(Query::parse("+back!end", &Default::default()).is_err());
+    // A bare word used to be rejected as a tag filter; in the filter language it searches the text
+    let bare = Query::parse("backend", &Default::default()).unwrap();
+    assert!(tasks.iter().all(|t| !bare.matches(t, &tasks)));
+
+    // Rename reaches removed tasks too
+    let old = store.load().pop().unwrap();
+    store.save(&store.load()[..2]).unwrap();
+    store.trash(vec![old]);
+    rename_tag(&store, "backend", "server");
+    assert!(store.load().iter().all(|t| !t.tags.contains("backend")));
+    assert!(store.load()[0].tags.contains("server"));
+    assert!(store.load_removed()[0].tags.contains("server"));
+}
+
+#[test]
+fn test_tui_edit_replaces_tags() {
+    let store = TaskStore::in_memory();
+    add_task(&store, "Fix login +backend +urgent".to_string(), 0);
+
+    let mut app = App::new(store);
+    app.input = "Fix login page +frontend".to_string();
+    app.edit_task(1);
+    assert_eq!(app.tasks[0].text, "Fix login page");
+    assert_eq!(app.tasks[0].tags.iter().collect::<Vec<_>>(), vec!["frontend"]);
+}
+
+// Run the todo binary against a data directory and return what it printed
+fn run_todo(dir: &std::path::Path, args: &[&str]) -> String {
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_todo"))
+        .args(args)
+        .env("TODO_DIR", dir)
+        .env("NO_COLOR", "1")
+        .output()
+        .expect("todo should run");
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+#[cfg(unix)]
+#[test]
+fn test_editor_commands_with_spaces_and_arguments() {
+    use std::os::unix::fs::PermissionsExt;
+    let dir = tempfile::tempdir().unwrap();
+
+    // An editor whose path contains a space runs as is
+    let script = dir.path().join("my editor");
+    std::fs::write(&script, "#!/bin/sh\nfor file; do :; done\necho 'appended' >> \"$file\"\n").unwrap();
+    std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+    let edited = todo::editor::edit_text_with(script.to_str().unwrap(), "notes").unwrap();
+    assert_eq!(edited, "notes\nappended\n");
+
+    // Commands with arguments and quoted paths go through the shell
+    let quoted = format!("'{}' --wait", script.display());
+    assert_eq!(todo::editor::edit_text_with(&quoted, "").unwrap(), "appended\n");
+    assert_eq!(todo::editor::edit_text_with("sed -i s/old/new/", "old text").unwrap(), "new text\n");
+    assert!(todo::editor::edit_text_with("false", "notes").is_err());
+}
+
+#[test]
+fn test_list_hides_waiting_tasks() {
+    let (dir, store) = temp_store();
+    add_task(&store, "Write report".to_string(), 0);
+    add_task(&store, "Renew passport".to_string(), 0);
+    set_task_date(&store, 2, DateField::Wait, "2099-01-01".to_string());
+
+    let list = run_todo(dir.path(), &["list"]);
+    assert!(list.contains("Write report") && !list.contains("Renew passport"));
+
+    // --waiting, --all and filters about the wait date show waiting tasks
+    for args in [&["list", "--waiting"][..], &["list", "--all"], &["list", "is:waiting"], &["list", "wait.after:today"]] {
+        let list = run_todo(dir.path(), args);
+        assert!(list.contains("Renew passport"), "{:?} should show the waiting task:\n{}", args, list);
+    }
+    assert!(!run_todo(dir.path(), &["list", "is:waiting"]).contains("Write report"));
+
+    // Once only waiting tasks are left, the list says so
+    toggle_task(&store, 1);
+    assert!(run_todo(dir.path(), &["list"]).contains("1 task(s) waiting"));
+}
+
+#[test]
+fn test_backup_diff_matches_tasks_by_uuid() {
+    let (dir, store) = temp_store();
+    add_task(&store, "First".to_string(), 0);
+    add_task(&store, "Second".to_string(), 0);
+    add_task(&store, "Third".to_string(), 0);
+
+    // Renumbering alone is not a change; an edit still is
+    let mut tasks = store.load();
+    tasks[1].id = 7;
+    store.save(&tasks).unwrap();
+    assert!(run_todo(dir.path(), &["backup", "diff", "1"]).contains("No differences."));
+    edit_task(&store, 7, "Second edited".to_string());
+    let diff = run_todo(dir.path(), &["backup", "diff", "2"]);
+    assert!(diff.contains("~ 7 ") && diff.contains("was: 2 ") && !diff.contains("+ ") && !diff.contains("- "));
+}
+
+#[test]
+fn test_search_treats_unknown_fields_as_text() {
+    let (dir, store) = temp_store();
+    add_task(&store, "Fix: login page".to_string(), 0);
+    add_task(&store, "Read http://x.test".to_string(), 0);
+
+    assert!(run_todo(dir.path(), &["search", "fix: login"]).contains("Fix: login page"));
+    let found = run_todo(dir.path(), &["search", "http://x"]);
+    assert!(found.contains("Read http://x.test") && !found.contains("Fix: login page"));
+    // Known fields are still checked
+    assert!(run_todo(dir.path(), &["search", "due:someday"]).contains("Invalid filter"));
+}
+
+#[test]
+fn test_tui_waiting_toggle_and_filter() {
+    let store = TaskStore::in_memory();
+    add_task(&store, "Write report".to_string(), 0);
+    add_task(&store, "Renew passport".to_string(), 0);
+    set_task_date(&store, 2, DateField::Wait, "2099-01-01".to_string());
+
+    let mut app = App::new(store);
+    let visible = |app: &App| app.visible_tasks().iter().map(|t| t.id).collect::<Vec<_>>();
+    assert_eq!(visible(&app), vec![1]);
+    app.toggle_waiting();
+    assert_eq!(visible(&app), vec![1, 2]);
+    app.toggle_waiting();
+    assert_eq!(visible(&app), vec![1]);
+
+    // A filter about waiting shows waiting tasks without the toggle
+    app.input = "is:waiting".to_string();
+    app.apply_filter();
+    assert_eq!(visible(&app), vec![2]);
+}
+
+#[test]
+fn test_tui_project_sidebar_filters_tasks() {
+    let store = TaskStore::in_memory();
+    add_task_with(&store, "CI".to_string(), AddOptions { project: Some("work.ci".to_string()), ..AddOptions::default() });
+    add_task_with(&store, "Shop".to_string(), AddOptions { project: Some("home".to_string()), ..AddOptions::default() });
+    add_task(&store, "Loose".to_string(), 0);
+
+    let mut app = App::new(store);
+    assert_eq!(app.project_entries(), vec!["home", "work", "work.ci"]);
+    assert_eq!(app.visible_tasks().len(), 3);
+
+    // Tab order: all -> home -> work -> work.ci -> all
+    app.cycle_project(true);
+    app.cycle_project(true);
+    assert_eq!(app.project.as_deref(), Some("work"));
+    assert_eq!(app.visible_tasks().iter().map(|t| t.text.as_str()).collect::<Vec<_>>(), vec!["CI"]);
+
+    // Tasks added while a project is selected go into it
+    app.input = "Deploy".to_string();
+    app.add_task();
+    assert_eq!(app.visible_tasks().len(), 2);
+    assert_eq!(app.tasks.last().unwrap().project.as_deref(), Some("work"));
+
+    app.cycle_project(false);
+    app.cycle_project(false);
+    assert_eq!(app.project, None);
+}
+
+#[test]
+fn test_tui_filter_prompt() {
+    let store = TaskStore::in_memory();
+    add_task(&store, "Deploy +ops".to_string(), 0);
+    add_task(&store, "Release notes".to_string(), 3);
+    add_task(&store, "Rotate keys +ops".to_string(), 7);
+
+    let mut app = App::new(store);
+    app.mode = AppMode::Filter;
+    app.input = "+ops due.after:today".to_string();
+    app.apply_filter();
+    assert!(app.mode == AppMode::Normal);
+    assert_eq!(app.visible_tasks().iter().map(|t| t.id).collect::<Vec<_>>(), vec![3]);
+
+    // A bad filter keeps the prompt open and the previous filter in place
+    app.mode = AppMode::Filter;
+    app.input = "+ops due.after:someday".to_string();
+    app.apply_filter();
+    assert!(app.mode == AppMode::Filter);
+    assert!(app.message.as_deref().unwrap().contains("column 16"));
+    assert_eq!(app.visible_tasks().len(), 1);
+
+    app.input.clear();
+    app.apply_filter();
+    assert!(app.filter.is_none());
+    assert_eq!(app.visible_tasks().len(), 3);
+}
+
+#[test]
+fn test_tui_sort_and_group() {
+    let dir = tempfile::tempdir().unwrap();
+    let tui = ViewSettings { sort: Some("due,-priority".to_string()), group_by: Some(GroupBy::Project) };
+    Config { tui, ..Config::default() }.save(dir.path()).unwrap();
+    let store = TaskStore::open(dir.path()).unwrap();
+    add_task_with(&store, "Deploy".to_string(), AddOptions { days_offset: 3, project: Some("ops".to_string()), ..AddOptions::default() });
+    add_task(&store, "Release notes".to_string(), 1);
+    add_task(&store, "Rotate keys".to_string(), 1);
+    set_priority(&store, 3, "H");
+
+    // The configured defaults apply: tasks without a project come first, then by due date and priority
+    let mut app = App::new(store);
+    let ids = |app: &App| app.visible_tasks().iter().map(|t| t.id).collect::<Vec<_>>();
+    assert_eq!(app.view.group_by, GroupBy::Project);
+    assert_eq!(ids(&app), vec![3, 2, 1]);
+    assert_eq!(app.group_of(&app.tasks[0]).unwrap().1, "📁 ops");
+
+    app.cycle_group();
+    assert_eq!(app.view.group_by, GroupBy::Tag);
+    app.view.group_by = GroupBy::None;
+    assert_eq!(ids(&app), vec![3, 2, 1]);
+
+    app.mode = AppMode::Sort;
+    app.input = "-id".to_string();
+    app.apply_sort();
+    assert!(app.mode == AppMode::Normal);
+    assert_eq!(ids(&app), vec![3, 2, 1]);
+    app.mode = AppMode::Sort;
+    app.input = "-due,text".to_string();
+    app.apply_sort();
+    assert_eq!(ids(&app), vec![1, 2, 3]);
+
+    // A bad sort order keeps the prompt open and the previous order in place
+    app.mode = AppMode::Sort;
+    app.input = "due,size".to_string();
+    app.apply_sort();
+    assert!(app.mode == AppMode::Sort);
+    assert!(app.message.as_deref().unwrap().contains("'size'"));
+    assert_eq!(app.view.sort.to_string(), "-due,text");
+
+    // An empty order goes back to the configured one
+    app.input.clear();
+    app.apply_sort();
+    assert_eq!(app.view.sort.to_string(), "due,-priority");
+}
+
+#[test]
+fn test_bulk_update() {
+    let store = TaskStore::in_memory();
+    add_task(&store, "Deploy +sprint".to_string(), 0);
+    add_task(&store, "Review +sprint".to_string(), 1);
+    add_task(&store, "Plan next sprint".to_string(), 5);
+    toggle_task(&store, 2);
+
+    // Only open tasks change; the already closed one is skipped
+    bulk_update(&store, "+sprint", BulkChange::Status(Status::Done), true);
+    let tasks = store.load();
+    assert!(tasks[0].is_done() && tasks[1].is_done() && !tasks[2].is_done());
+
+    // One bulk change is one undo step
+    bulk_update(&store, r#"due.before:"in 2 weeks""#, BulkChange::Priority(Some(Priority::High)), true);
+    assert!(store.load().iter().all(|t| t.priority == Some(Priority::High)));
+    store.undo().unwrap();
+    assert!(store.load().iter().all(|t| t.priority.is_none()));
+
+    bulk_update(&store, "text~sprint status:open", BulkChange::AddTag("Planning".to_string()), true);
+    assert!(store.load()[2].tags.contains("planning"));
+    bulk_update(&store, "status:done", BulkChange::Delete, true);
+    assert_eq!(store.load().len(), 1);
+    // Deleted tasks go to removed storage, and undo takes them back out
+    assert_eq!(store.load_removed().iter().map(|t| t.id).collect::<Vec<_>>(), vec![1, 2]);
+    store.undo().unwrap();
+    assert_eq!(store.load().len(), 3);
+    assert!(store.load_removed().is_empty());
+    store.redo().unwrap();
+    assert_eq!(store.load_removed().len(), 2);
+
+    // Invalid or empty filters change nothing
+    bulk_update(&store, "", BulkChange::Delete, true);
+    bulk_update(&store, "status:finished", BulkChange::Delete, true);
+    assert_eq!(store.load().len(), 1);
+}
+
+#[test]
+fn test_bulk_status_leaves_unmatched_subtasks_open() {
+    let store = TaskStore::in_memory();
+    add_task(&store, "Release +q3".to_string(), 0);
+    add_task_with(&store, "Changelog +q3".to_string(), AddOptions { parent: Some(TaskRef::Short(1)), ..AddOptions::default() });
+    add_task_with(&store, "Tag".to_string(), AddOptions { parent: Some(TaskRef::Short(1)), ..AddOptions::default() });
+
+    // Like `todo cancel`, only the matching tasks change; the other subtask stays open under its parent
+    bulk_update(&store, "+q3", BulkChange::Status(Status::Cancelled), true);
+    let tasks = store.load();
+    assert_eq!(tasks.iter().map(|t| t.status).collect::<Vec<_>>(), vec![Status::Cancelled, Status::Cancelled, Status::Pending]);
+    assert_eq!(tasks[2].parent, Some(tasks[0].uuid));
+
+    // Reopening does not reach the unmatched subtask either
+    bulk_update(&store, "+q3", BulkChange::Status(Status::Pending), true);
+    assert!(store.load().iter().all(|t| t.status == Status::Pending));
+}
+
+#[test]
+fn test_saved_queries_and_context() {
+    let (dir, store) = temp_store();
+    add_task(&store, "Deploy +work".to_string(), 0);
+    add_task(&store, "Milk +home".to_string(), 0);
+
+    save_query(&store, "Work", "+work project:acme".to_string());
+    save_query(&store, "broken", "due.before:someday".to_string());
+    save_query(&store, "bad name", "+work".to_string());
+    let saved = store.saved_queries().unwrap();
+    assert_eq!(saved.queries.keys().collect::<Vec<_>>(), vec!["work"]);
+
+    // The context tags new tasks and puts them in its project
+    set_context(&store, "work", vec![]);
+    add_task(&store, "Ship it".to_string(), 0);
+    let shipped = store.load().pop().unwrap();
+    assert!(shipped.tags.contains("work"));
+    assert_eq!(shipped.project.as_deref(), Some("acme"));
+
+    // Persisted in the data directory and applied to the TUI
+    let store = TaskStore::open(dir.path()).unwrap();
+    assert_eq!(store.saved_queries().unwrap().context.as_deref(), Some("work"));
+    let app = App::new(store);
+    assert_eq!(app.visible_tasks().iter().map(|t| t.id).collect::<Vec<_>>(), vec![3]);
+    let store = app.store;
+
+    // Setting a context with a filter saves it too; deleting the active query clears the context
+    set_context(&store, "home", vec!["+home".to_string()]);
+    assert_eq!(store.saved_queries().unwrap().queries["home"], "+home");
+    delete_query(&store, "home");
+    assert_eq!(store.saved_queries().unwrap().context, None);
+
+    set_context(&store, "work", vec![]);
+    clear_context(&store);
+    assert_eq!(store.saved_queries().unwrap(), todo::SavedQueries {
+        queries: [("work".to_string(), "+work project:acme".to_string())].into(),
+        context: None,
+    });
+    let implied = Query::parse("+a (+b or +c) project:x not +d", &Default::default()).unwrap();
+    assert_eq!((implied.implied_tags(), implied.implied_project()), (vec!["a".to_string()], Some("x".to_string())));
+}
+
+#[test]
+fn test_context_applies_to_added_tasks() {
+    let (dir, store) = temp_store();
+    set_context(&store, "work", vec!["+work".to_string(), "project:acme".to_string(), "(+a or +b)".to_string()]);
+
+    // Required tags and the project are given to new tasks; tags under `or` are not
+    add_task(&store, "Ship it".to_string(), 0);
+    add_task_with(&store, "Own project".to_string(), AddOptions { project: Some("infra".to_string()), ..AddOptions::default() });
+    run_todo(dir.path(), &["add", "From the CLI"]);
+    let tasks = store.load();
+    assert!(tasks.iter().all(|t| t.tags.iter().collect::<Vec<_>>() == vec!["work"]));
+    assert_eq!(tasks.iter().map(|t| t.project.as_deref()).collect::<Vec<_>>(), vec![Some("acme"), Some("infra"), Some("acme")]);
+
+    // The TUI does the same
+    let mut app = App::new(store);
+    app.input = "From the TUI".to_string();
+    app.add_task();
+    let added = app.tasks.last().unwrap();
+    assert!(added.tags.contains("work"));
+    assert_eq!(added.project.as_deref(), Some("acme"));
+    let store = app.store;
+
+    // A context whose query is gone is reported instead of silently ignored
+    let mut saved = store.saved_queries().unwrap();
+    saved.queries.clear();
+    store.save_queries(&saved).unwrap();
+    assert!(saved.context_query(&Default::default()).is_err());
+    add_task(&store, "Not added".to_string(), 0);
+    assert_eq!(store.load().len(), 4);
+}
+
+#[test]
+fn test_subtask_child_policies() {
+    let (_dir, store) = temp_store();
+    add_task_with(&store, "Release".to_string(), AddOptions { project: Some("work".to_string()), ..AddOptions::default() });
+    add_task_with(&store, "Changelog".to_string(), AddOptions { parent: Some(TaskRef::Short(1)), ..AddOptions::default() });
+    add_task_with(&store, "Tag".to_string(), AddOptions { parent: Some(TaskRef::Short(2)), ..AddOptions::default() });
+
+    let tasks = store.load();
+    assert_eq!(tasks[1].parent, Some(tasks[0].uuid));
+    assert_eq!(tasks[2].parent, Some(tasks[1].uuid));
+    // Subtasks inherit the parent's project
+    assert_eq!(tasks[2].project.as_deref(), Some("work"));
+
+    // Unknown parents are rejected
+    add_task_with(&store, "Lost".to_string(), AddOptions { parent: Some(TaskRef::Short(9)), ..AddOptions::default() });
+    assert_eq!(store.load().len(), 3);
+
+    // Refuse leaves everything open while subtasks are open
+    toggle_task_with(&store, 1, ChildPolicy::Refuse);
+    assert!(store.load().iter().all(|t| !t.is_done()));
+
+    // Cascade completes the whole subtree
+    toggle_task_with(&store, 1, ChildPolicy::Cascade);
+    assert!(store.load().iter().all(|t| t.is_done()));
+
+    // Delete refuses by default, orphan keeps the grandchild as a top-level task
+    delete_task_with(&store, 2, ChildPolicy::Refuse);
+    assert_eq!(store.load().len(), 3);
+    delete_task_with(&store, 2, ChildPolicy::Orphan);
+    let tasks = store.load();
+    assert_eq!(tasks.len(), 2);
+    assert_eq!(tasks[1].parent, None);
+
+    // Cascade deletes the whole subtree
+    add_task_with(&store, "Announce".to_string(), AddOptions { parent: Some(TaskRef::Short(1)), ..AddOptions::default() });
+    delete_task_with(&store, 1, ChildPolicy::Cascade);
+    assert_eq!(store.load().iter().map(|t| t.text.as_str()).collect::<Vec<_>>(), vec!["Tag"]);
+}
+
+#[test]
+fn test_status_commands_apply_child_policy() {
+    let store = TaskStore::in_memory();
+    add_task(&store, "Release".to_string(), 0);
+    add_task_with(&store, "Changelog".to_string(), AddOptions { parent: Some(TaskRef::Short(1)), ..AddOptions::default() });
+    add_task_with(&store, "Tag".to_string(), AddOptions { parent: Some(TaskRef::Short(1)), ..AddOptions::default() });
+    toggle_task(&store, 3);
+    let statuses = |store: &TaskStore| store.load().iter().map(|t| t.status).collect::<Vec<_>>();
+
+    // Refuse keeps the parent open while a subtask is open
+    set_task_status_with(&store, 1, Status::Cancelled, None, ChildPolicy::Refuse);
+    assert_eq!(statuses(&store), vec![Status::Pending, Status::Pending, Status::Done]);
+
+    // Cascade cancels open subtasks and leaves completed ones alone
+    set_task_status_with(&store, 1, Status::Cancelled, None, ChildPolicy::Cascade);
+    assert_eq!(statuses(&store), vec![Status::Cancelled, Status::Cancelled, Status::Done]);
+
+    // Reopening with cascade reopens completed subtasks
+    set_task_status_with(&store, 1, Status::Pending, None, ChildPolicy::Cascade);
+    assert_eq!(statuses(&store), vec![Status::Pending, Status::Cancelled, Status::Pending]);
+
+    // Done through the status command follows the same rules
+    set_task_status_with(&store, 1, Status::Done, None, ChildPolicy::Refuse);
+    assert_eq!(store.load()[0].status, Status::Pending);
+    set_task_status_with(&store, 1, Status::Done, None, ChildPolicy::Cascade);
+    assert_eq!(statuses(&store), vec![Status::Done, Status::Cancelled, Status::Done]);
+}
+
+#[test]
+fn test_dependencies_ready_and_graph() {
+    let store = TaskStore::in_memory();
+    add_task(&store, "Design".to_string(), 0);
+    add_task(&store, "Build \"v2\"".to_string(), 0);
+    add_task(&store, "Docs".to_string(), 0);
+
+    depend_task(&store, 2, 1, false);
+    // The reverse link would form a cycle and is not stored
+    depend_task(&store, 1, 2, false);
+    let tasks = store.load();
+    assert!(tasks[1].depends_on.contains(&tasks[0].uuid));
+    assert!(tasks[0].depends_on.is_empty());
+
+    let dot = dependency_graph(&tasks, false);
+    assert!(dot.starts_with("digraph todo {"));
+    assert!(dot.contains("t1 -> t2;"));
+    assert!(dot.contains("t2 [label=\"2: Build \\\"v2\\\"\", color=red];"));
+    // Unlinked tasks only show up with `all`
+    assert!(!dot.contains("t3 "));
+    assert!(dependency_graph(&tasks, true).contains("t3 [label=\"3: Docs\""));
+
+    // Undo removes the link again
+    store.undo().unwrap();
+    assert!(store.load()[1].depends_on.is_empty());
+    store.redo().unwrap();
+
+    depend_task(&store, 2, 1, true);
+    assert!(store.load()[1].depends_on.is_empty());
+}
+
+#[test]
+fn test_recurring_task_spawns_next_instance() {
+    let store = TaskStore::in_memory();
+    add_task_with(&store, "Review +weekly".to_string(), AddOptions { priority: Some(Priority::High), ..AddOptions::default() });
+    recur_task(&store, 1, "every 7 days", None);
+
+    toggle_task(&store, 1);
+    let tasks = store.load();
+    assert_eq!(tasks.len(), 2);
+    assert!(tasks[0].is_done() && tasks[0].recur.is_none());
+    let next = &tasks[1];
+    assert_eq!((next.id, next.is_done(), next.priority), (2, false, Some(Priority::High)));
+    assert!(next.tags.contains("weekly"));
+    assert_eq!(next.due_date, Some(todo::get_date_with_offset(7).unwrap()));
+    assert_eq!(next.recur.as_ref().map(|recur| recur.rule.to_string()).as_deref(), Some("every 7 days"));
+
+    // Reopening the old instance and completing it again spawns nothing
+    toggle_task(&store, 1);
+    toggle_task(&store, 1);
+    assert_eq!(store.load().len(), 2);
+
+    // A paused series does not spawn; resuming it does
+    recur_task(&store, 2, "pause", None);
+    toggle_task(&store, 2);
+    assert_eq!(store.load().len(), 2);
+    toggle_task(&store, 2);
+    recur_task(&store, 2, "resume", None);
+    toggle_task(&store, 2);
+    assert_eq!(store.load().len(), 3);
+
+    // The series ends after its until date
+    recur_task(&store, 3, "", Some(todo::get_today().unwrap()));
+    toggle_task(&store, 3);
+    assert_eq!(store.load().len(), 3);
+
+    // Ending the series clears the rule
+    add_task(&store, "Water plants".to_string(), 0);
+    recur_task(&store, 4, "daily", None);
+    recur_task(&store, 4, "end", None);
+    assert!(store.load()[3].recur.is_none());
+}
+
+#[test]
+fn test_recurring_task_keeps_notes_and_shifts_dates() {
+    let _clock = set_clock(FixedClock::on(chrono::NaiveDate::from_ymd_opt(2025, 3, 10).unwrap()));
+    let store = TaskStore::in_memory();
+    add_task(&store, "Pay rent".to_string(), 0);
+    set_due_date(&store, 1, "2025-03-15".to_string());
+    set_task_date(&store, 1, DateField::Scheduled, "2025-03-12".to_string());
+    set_task_date(&store, 1, DateField::Wait, "2025-03-08".to_string());
+    set_notes(&store, 1, "IBAN in the shared folder".to_string());
+    recur_task(&store, 1, "monthly", None);
+
+    toggle_task(&store, 1);
+    let next = &store.load()[1];
+    assert_eq!(next.due_date.as_deref(), Some("2025-04-15"));
+    assert_eq!(next.scheduled.as_deref(), Some("2025-04-12"));
+    assert_eq!(next.wait.as_deref(), Some("2025-04-08"));
+    assert_eq!(next.notes.as_deref(), Some("IBAN in the shared folder"));
+}
+
+#[test]
+fn test_recurring_series_ends_at_the_calendar_limit() {
+    let (dir, store) = temp_store();
+    add_task(&store, "Pay".to_string(), 0);
+
+    // Absurd intervals are rejected up front
+    let output = run_todo(dir.path(), &["recur", "1", "every", "999999999", "days"]);
+    assert!(output.contains("every N days needs N from 1 to"), "{}", output);
+    assert!(store.load()[0].recur.is_none());
+
+    // A next date past 9999-12-31 ends the series instead of writing a file that cannot be loaded
+    set_due_date(&store, 1, "9999-12-31".to_string());
+    recur_task(&store, 1, "daily", None);
+    assert!(run_todo(dir.path(), &["done", "1"]).contains("Series ended"));
+    let tasks = store.load_result().unwrap();
+    assert_eq!(tasks.len(), 1);
+    assert!(tasks[0].is_done());
+
+    // Shifted scheduled dates are held to the same limit
+    add_task(&store, "Renew".to_string(), 0);
+    set_due_date(&store, 2, "9999-11-01".to_string());
+    set_task_date(&store, 2, DateField::Scheduled, "9999-12-31".to_string());
+    recur_task(&store, 2, "monthly", None);
+    assert_eq!(store.load()[1].scheduled.as_deref(), Some("9999-12-31"));
+    toggle_task(&store, 2);
+    assert_eq!(store.load_result().unwrap().len(), 2);
+
+    // The store refuses to write what the loader would reject
+    let mut tasks = store.load();
+    tasks[0].due_date = Some("+10000-01-01".to_string());
+    assert!(store.save(&tasks).is_err());
+    assert!(store.load_result().is_ok());
+}
+
+#[test]
+fn test_due_dates_follow_the_pinned_clock() {
+    let _clock = set_clock(FixedClock::on(chrono::NaiveDate::from_ymd_opt(2025, 2, 27).unwrap()));
+    let store = TaskStore::in_memory();
+
+    add_task(&store, "Today".to_string(), 0);
+    add_task(&store, "Leap".to_string(), 2);
+    add_task(&store, "Monthly".to_string(), 0);
+    recur_task(&store, 3, "monthly", None);
+    toggle_task(&store, 3);
+
+    let due: Vec<Option<String>> = store.load().into_iter().map(|t| t.due_date).collect();
+    assert_eq!(due, ["2025-02-27", "2025-03-01", "2025-02-27", "2025-03-27"].map(|date| Some(date.to_string())));
+
+    // The TUI uses the same clock
+    let mut app = App::new(store);
+    app.input = "From TUI".to_string();
+    app.add_task();
+    assert_eq!(app.tasks.last().unwrap().due_date.as_deref(), Some("2025-02-27"));
+}
+
+#[test]
+fn test_due_date_expressions() {
+    // 2025-11-06 is a Thursday
+    let _clock = set_clock(FixedClock::on(chrono::NaiveDate::from_ymd_opt(2025, 11, 6).unwrap()));
+    let store = TaskStore::in_memory();
+
+    add_task_with(&store, "Ship".to_string(), AddOptions { due: Some("next monday".to_string()), ..AddOptions::default() });
+    add_task_with(&store, "Nope".to_string(), AddOptions { due: Some("someday".to_string()), ..AddOptions::default() });
+    assert_eq!(store.load().len(), 1);
+    assert_eq!(store.load()[0].due_date.as_deref(), Some("2025-11-10"));
+
+    set_due_date(&store, 1, "eom".to_string());
+    assert_eq!(store.load()[0].due_date.as_deref(), Some("2025-11-30"));
+    set_due_date(&store, 1, "not a date".to_string());
+    assert_eq!(store.load()[0].due_date.as_deref(), Some("2025-11-30"));
+
+    // The TUI prompt accepts the same expressions
+    let mut app = App::new(store);
+    app.input = "dec 3".to_string();
+    app.set_due_date(1);
+    assert_eq!(app.tasks[0].due_date.as_deref(), Some("2025-12-03"));
+}
+
+#[test]
+fn test_due_time_is_stored_and_carried_over() {
+    let _clock = set_clock(FixedClock::on(chrono::NaiveDate::from_ymd_opt(2025, 11, 6).unwrap()));
+    let store = TaskStore::in_memory();
+
+    add_task_with(&store, "Standup".to_string(), AddOptions { due: Some("fri 9:30 Europe/Berlin".to_string()), ..AddOptions::default() });
+    let task = store.load().remove(0);
+    assert_eq!((task.due_date.as_deref(), task.due_time.as_deref(), task.due_tz.as_deref()),
+        (Some("2025-11-07"), Some("09:30"), Some("Europe/Berlin")));
+
+    // Recurring instances keep the time of day
+    recur_task(&store, 1, "daily", None);
+    toggle_task(&store, 1);
+    let next = store.load().remove(1);
+    assert_eq!((next.due_date.as_deref(), next.due_time.as_deref()), (Some("2025-11-08"), Some("09:30")));
+
+    // A date without a time clears the old time and timezone
+    set_due_date(&store, 2, "mon".to_string());
+    let task = store.load().remove(1);
+    assert_eq!((task.due_date.as_deref(), task.due_time, task.due_tz), (Some("2025-11-10"), None, None));
+}
+
+#[test]
+fn test_scheduled_and_wait_dates() {
+    let _clock = set_clock(FixedClock::on(chrono::NaiveDate::from_ymd_opt(2025, 11, 6).unwrap()));
+    let store = TaskStore::in_memory();
+    add_task_with(&store, "Taxes".to_string(), AddOptions { scheduled: Some("today".to_string()), ..AddOptions::default() });
+    add_task_with(&store, "Passport".to_string(), AddOptions { wait: Some("in 2 weeks".to_string()), ..AddOptions::default() });
+    add_task_with(&store, "Bad".to_string(), AddOptions { wait: Some("whenever".to_string()), ..AddOptions::default() });
+    add_task(&store, "Trip".to_string(), 0);
+
+    let tasks = store.load();
+    assert_eq!(tasks.len(), 3);
+    assert_eq!(tasks[0].scheduled.as_deref(), Some("2025-11-06"));
+    assert_eq!(tasks[1].wait.as_deref(), Some("2025-11-20"));
+    assert!(tasks[0].can_start("2025-11-06") && !tasks[2].can_start("2025-11-06"));
+    assert!(tasks[1].is_waiting("2025-11-19") && !tasks[1].is_waiting("2025-11-20"));
+
+    set_task_date(&store, 3, DateField::Scheduled, "mon".to_string());
+    set_task_date(&store, 2, DateField::Wait, "none".to_string());
+    let tasks = store.load();
+    assert_eq!(tasks[2].scheduled.as_deref(), Some("2025-11-10"));
+    assert_eq!(tasks[1].wait, None);
+
+    // The TUI hides waiting tasks unless asked to show them
+    let mut app = App::new(store);
+    app.input = "fri".to_string();
+    app.set_task_date(1, DateField::Wait);
+    assert_eq!(app.visible_tasks().iter().map(|t| t.id).collect::<Vec<_>>(), vec![2, 3]);
+    app.toggle_waiting();
+    assert_eq!(app.visible_tasks().len(), 3);
+}
+
+#[test]
+fn test_task_notes() {
+    let (dir, store) = temp_store();
+    add_task(&store, "Deploy".to_string(), 0);
+    let long_line = "x".repeat(600);
+
+    set_notes(&store, 1, format!("Runbook:\n{}", long_line));
+    append_notes(&store, 1, "- step two".to_string());
+    let tasks = store.load();
+    assert_eq!(tasks[0].notes, Some(format!("Runbook:\n{}\n- step two", long_line)));
+
+    // Notes are not limited like the task text and survive a reload
+    let reopened = TaskStore::open(dir.path()).unwrap();
+    assert_eq!(reopened.load_result().unwrap()[0].notes, tasks[0].notes);
+
+    set_notes(&store, 1, String::new());
+    assert_eq!(store.load()[0].notes, None);
+    store.undo().unwrap();
+    assert!(store.load()[0].notes.is_some());
+
+    // The TUI records note edits for undo as well
+    let mut app = App::new(store);
+    app.set_notes(1, "Rewritten\n");
+    assert_eq!(app.tasks[0].notes.as_deref(), Some("Rewritten"));
+    assert_eq!(app.selected_task().map(|task| task.id), Some(1));
+    app.undo();
+    assert_eq!(app.tasks[0].notes.as_deref().map(|notes| notes.starts_with("Runbook:")), Some(true));
+}
+
+#[test]
+fn test_set_attributes() {
+    let (dir, _) = temp_store();
+    let config: Config = serde_json::from_str(r#"{ "udas": {
+        "ticket": { "type": "string" },
+        "estimate": { "type": "number" }
+    } }"#).unwrap();
+    config.save(dir.path()).unwrap();
+    let store = TaskStore::open(dir.path()).unwrap();
+    add_task(&store, "Fix login".to_string(), 0);
+
+    set_attributes(&store, 1, vec!["ticket=OPS-12".to_string(), "estimate=3".to_string()]);
+    assert_eq!(store.load()[0].udas.get("estimate"), Some(&UdaValue::Number(3.0)));
+
+    // One bad assignment rejects the whole command
+    set_attributes(&store, 1, vec!["ticket=OPS-13".to_string(), "estimate=lots".to_string()]);
+    set_attributes(&store, 1, vec!["color=red".to_string()]);
+    assert_eq!(store.load()[0].udas.get("ticket"), Some(&UdaValue::Text("OPS-12".to_string())));
+    assert_eq!(store.load()[0].udas.len(), 2);
+
+    set_attributes(&store, 1, vec!["ticket=".to_string()]);
+    assert!(!store.load()[0].udas.contains_key("ticket"));
+    store.undo().unwrap();
+    assert!(store.load()[0].udas.contains_key("ticket"));
+
+    // Values that no longer fit config.json are reported on load but kept
+    let json = std::fs::read_to_string(dir.path().join("todos.json")).unwrap().replace("\"estimate\": 3.0", "\"estimate\": \"lots\"");
+    std::fs::write(dir.path().join("todos.json"), json).unwrap();
+    let tasks = store.load_result().unwrap();
+    assert_eq!(tasks[0].udas.get("estimate"), Some(&UdaValue::Text("lots".to_string())));
+    assert_eq!(store.attribute_problems(&tasks).len(), 1);
+
+    // Malformed attribute names are rejected when the file is validated
+    let json = std::fs::read_to_string(dir.path().join("todos.json")).unwrap().replace("\"ticket\"", "\"Bad Name\"");
+    std::fs::write(dir.path().join("todos.json"), json).unwrap();
+    assert!(todo::validate_json_file(&dir.path().join("todos.json")).is_err());
+}
@@ -1,17 +1,19 @@
-use todo::{Task, TaskError, validate_date, get_today, get_date_with_offset};
+use todo::{Task, Priority, SortSpec, GroupBy, Status, Query, UdaDef, UdaFilter, UdaValue, validate_udas, DueState, parse_due_expr, parse_date_expr, FixedClock, Clock, set_clock, offset_date, RecurRule, add_dependency, is_blocked, extract_tags, tree_order, descendants, subtask_progress, normalize_project, project_paths, TaskError, TaskRef, DataDirSource, validate_date, get_today, get_date_with_offset, resolve_data_dir, resolve_data_dir_with};
+use std::collections::BTreeMap;
 
 #[test]
 fn test_task_creation() {
     let task = Task {
         id: 1,
         text: "Test task".to_string(),
-        done: false,
+        status: Status::Pending,
         due_date: Some("2025-11-08".to_string()),
+        ..Task::default()
     };
 
     assert_eq!(task.id, 1);
     assert_eq!(task.text, "Test task");
-    assert!(!task.done);
+    assert!(!task.is_done());
     assert_eq!(task.due_date, Some("2025-11-08".to_string()));
 }
 
@@ -55,7 +57,7 @@ fn test_validate_date_leap_year() {
 
 #[test]
 fn test_get_today() {
-    let today = get_today();
+    let today = get_today().unwrap();
     // Should return a valid date format
     assert!(validate_date(&today).is_ok());
     // Should be in YYYY-MM-DD format
@@ -69,22 +71,23 @@ fn test_task_json_serialization() {
     let task = Task {
         id: 1,
         text: "Test task".to_string(),
-        done: false,
+        status: Status::Pending,
         due_date: Some("2025-11-08".to_string()),
+        ..Task::default()
     };
 
     // Test serialization
     let json = serde_json::to_string(&task).unwrap();
     assert!(json.contains("\"id\":1"));
     assert!(json.contains("\"text\":\"Test task\""));
-    assert!(json.contains("\"done\":false"));
+    assert!(json.contains("\"status\":\"pending\""));
     assert!(json.contains("\"due_date\":\"2025-11-08\""));
 
     // Test deserialization
     let deserialized: Task = serde_json::from_str(&json).unwrap();
     assert_eq!(deserialized.id, task.id);
     assert_eq!(deserialized.text, task.text);
-    assert_eq!(deserialized.done, task.done);
+    assert_eq!(deserialized.is_done(), task.is_done());
     assert_eq!(deserialized.due_date, task.due_date);
 }
 
@@ -93,8 +96,9 @@ fn test_task_without_due_date() {
     let task = Task {
         id: 1,
         text: "Test task".to_string(),
-        done: false,
+        status: Status::Pending,
         due_date: None,
+        ..Task::default()
     };
 
     let json = serde_json::to_string(&task).unwrap();
@@ -116,9 +120,9 @@ fn test_error_display() {
 
 #[test]
 fn test_get_date_with_offset() {
-    let today = get_today();
-    let tomorrow = get_date_with_offset(1);
-    let yesterday = get_date_with_offset(-1);
+    let today = get_today().unwrap();
+    let tomorrow = get_date_with_offset(1).unwrap();
+    let yesterday = get_date_with_offset(-1).unwrap();
     
     // All should be valid dates
     assert!(validate_date(&today).is_ok());
@@ -150,4 +154,584 @@ fn test_backup_creation() {
     // Cleanup
     let _ = std::fs::remove_file(test_file);
     let _ = std::fs::remove_file(backup_file);
-}
\ No newline at end of file
+}
+#[test]
+fn test_resolve_data_dir_flag_wins() {
+    let flag_dir = std::env::temp_dir().join("todo_flag_dir");
+
+    let data_dir = resolve_data_dir(Some(&flag_dir)).unwrap();
+    assert_eq!(data_dir.path, flag_dir);
+    assert_eq!(data_dir.source, DataDirSource::Flag);
+    assert_eq!(data_dir.source.to_string(), "--data-dir flag");
+}
+
+#[test]
+fn test_resolve_data_dir_env_xdg_and_legacy() {
+    let home = tempfile::tempdir().unwrap();
+    let xdg = home.path().join("xdg");
+    let resolve = |vars: &[(&str, &std::path::Path)]| {
+        let vars: Vec<(String, String)> = vars.iter().map(|(name, path)| (name.to_string(), path.display().to_string())).collect();
+        resolve_data_dir_with(None, |name| vars.iter().find(|(var, _)| var == name).map(|(_, value)| value.clone())).unwrap()
+    };
+    let with_xdg = [("HOME", home.path()), ("XDG_DATA_HOME", xdg.as_path())];
+
+    // TODO_DIR wins over XDG_DATA_HOME; an empty value counts as unset
+    let todo_dir = home.path().join("custom");
+    let data_dir = resolve(&[("TODO_DIR", todo_dir.as_path()), with_xdg[0], with_xdg[1]]);
+    assert_eq!((data_dir.path, data_dir.source), (todo_dir, DataDirSource::Env));
+    let data_dir = resolve(&[("TODO_DIR", std::path::Path::new(" ")), with_xdg[0], with_xdg[1]]);
+    assert_eq!(data_dir.source, DataDirSource::Xdg);
+
+    let data_dir = resolve(&with_xdg);
+    assert_eq!((data_dir.path, data_dir.source), (xdg.join("todo"), DataDirSource::Xdg));
+    // Without XDG_DATA_HOME the spec's default ~/.local/share is used
+    let data_dir = resolve(&[("HOME", home.path())]);
+    assert_eq!((data_dir.path, data_dir.source), (home.path().join(".local/share/todo"), DataDirSource::Default));
+
+    // A legacy ~/.todo in use keeps priority until the XDG directory exists
+    std::fs::create_dir(home.path().join(".todo")).unwrap();
+    assert_eq!(resolve(&with_xdg).source, DataDirSource::Legacy);
+    let data_dir = resolve(&[("HOME", home.path())]);
+    assert_eq!((data_dir.path, data_dir.source), (home.path().join(".todo"), DataDirSource::Legacy));
+    std::fs::create_dir_all(xdg.join("todo")).unwrap();
+    assert_eq!(resolve(&with_xdg).source, DataDirSource::Xdg);
+
+    assert!(resolve_data_dir_with(None, |_| None).is_err());
+}
+
+#[test]
+fn test_task_ref_parsing() {
+    assert_eq!("7".parse::<TaskRef>(), Ok(TaskRef::Short(7)));
+
+    // A fixed UUID: a random one can start with eight digits, which reads as a short ID
+    let task = Task { uuid: "3f2a9c1e-5b7d-4e8f-9a6b-0c1d2e3f4a5b".parse().unwrap(), ..Task::new(7, "Referenced", None) };
+    let full: TaskRef = task.uuid.to_string().parse().unwrap();
+    assert_eq!(full, TaskRef::Uuid(task.uuid));
+    assert!(task.matches(&full));
+
+    let prefix: TaskRef = task.uuid.to_string()[..8].to_uppercase().parse().unwrap();
+    assert!(task.matches(&prefix));
+
+    assert!("abc".parse::<TaskRef>().is_err());
+    assert!("not-a-task".parse::<TaskRef>().is_err());
+}
+
+#[test]
+fn test_legacy_task_without_timestamps() {
+    let task: Task = serde_json::from_str(r#"{"id":1,"text":"Old","done":true}"#).unwrap();
+    assert!(task.created_at.is_none());
+    assert!(task.completed_at.is_none());
+
+    // Missing timestamps are not written back as nulls
+    let json = serde_json::to_string(&task).unwrap();
+    assert!(!json.contains("created_at"));
+}
+
+#[test]
+fn test_priority_parsing() {
+    assert_eq!("H".parse::<Priority>().unwrap(), Priority::High);
+    assert_eq!("medium".parse::<Priority>().unwrap(), Priority::Medium);
+    assert_eq!("5".parse::<Priority>().unwrap(), Priority::Low);
+    assert!("urgent".parse::<Priority>().is_err());
+    assert_eq!(Priority::parse_level("none").unwrap(), None);
+    assert!(Priority::High > Priority::Low);
+
+    // Stored as a single letter
+    let task = Task { priority: Some(Priority::High), ..Task::new(1, "Important", None) };
+    assert!(serde_json::to_string(&task).unwrap().contains("\"priority\":\"H\""));
+}
+
+#[test]
+fn test_urgency_combines_priority_and_due_date() {
+    let today = "2025-11-08";
+    let plain = Task::new(1, "Plain", Some("2025-11-20".to_string()));
+    let high = Task { priority: Some(Priority::High), ..plain.clone() };
+    let overdue = Task::new(2, "Overdue", Some("2025-11-01".to_string()));
+    let undated = Task::new(3, "Undated", None);
+
+    assert!(high.urgency(today) > plain.urgency(today));
+    assert!(overdue.urgency(today) > plain.urgency(today));
+    assert!(plain.urgency(today) > undated.urgency(today));
+    assert_eq!(undated.urgency(today), 0.0);
+
+    // Completed tasks are never urgent
+    let mut done = high.clone();
+    done.set_done(true);
+    assert_eq!(done.urgency(today), 0.0);
+}
+
+#[test]
+fn test_extract_tags() {
+    let (text, tags) = extract_tags("Fix login +Backend +urgent now");
+    assert_eq!(text, "Fix login now");
+    assert_eq!(tags.into_iter().collect::<Vec<_>>(), vec!["backend", "urgent"]);
+
+    // A lone plus or C++ is not a tag
+    let (text, tags) = extract_tags("Learn C++ + Rust");
+    assert_eq!(text, "Learn C++ + Rust");
+    assert!(tags.is_empty());
+}
+
+#[test]
+fn test_projects() {
+    assert_eq!(normalize_project(" Work.Infra.CI ").unwrap(), "work.infra.ci");
+    assert!(normalize_project("work..ci").is_err());
+    assert!(normalize_project("work infra").is_err());
+
+    let ci = Task { project: Some("work.infra.ci".to_string()), ..Task::new(1, "CI", None) };
+    let other = Task { project: Some("work-x".to_string()), ..Task::new(2, "Other", None) };
+    assert!(ci.in_project("work") && ci.in_project("work.infra.ci"));
+    assert!(!ci.in_project("work.inf"));
+    assert!(!other.in_project("work"));
+
+    // Parents are listed before their children
+    assert_eq!(project_paths(&[other, ci]), vec!["work", "work.infra", "work.infra.ci", "work-x"]);
+}
+
+#[test]
+fn test_subtask_tree_helpers() {
+    let parent = Task::new(1, "Release".to_string(), None);
+    let child = Task { parent: Some(parent.uuid), ..Task::new(2, "Changelog".to_string(), None) };
+    let grandchild = Task { parent: Some(child.uuid), status: Status::Done, ..Task::new(3, "Collect PRs".to_string(), None) };
+    let other = Task::new(4, "Unrelated".to_string(), None);
+    let tasks = vec![grandchild.clone(), other.clone(), child.clone(), parent.clone()];
+
+    // Subtasks follow their parent, roots keep their relative order
+    let refs: Vec<&Task> = tasks.iter().collect();
+    let order: Vec<(u32, usize)> = tree_order(&refs).into_iter().map(|(t, depth)| (t.id, depth)).collect();
+    assert_eq!(order, vec![(4, 0), (1, 0), (2, 1), (3, 2)]);
+
+    // A subtask whose parent is filtered out becomes a root
+    let refs: Vec<&Task> = vec![&tasks[0]];
+    assert_eq!(tree_order(&refs)[0].1, 0);
+
+    assert_eq!(descendants(&tasks, parent.uuid), vec![child.uuid, grandchild.uuid]);
+    assert_eq!(subtask_progress(&tasks, child.uuid), Some((1, 1)));
+    assert_eq!(subtask_progress(&tasks, parent.uuid), Some((0, 1)));
+    assert_eq!(subtask_progress(&tasks, other.uuid), None);
+}
+
+#[test]
+fn test_dependencies_and_cycles() {
+    let mut tasks = vec![
+        Task::new(1, "Design".to_string(), None),
+        Task::new(2, "Build".to_string(), None),
+        Task::new(3, "Ship".to_string(), None),
+    ];
+    let (design, build) = (tasks[0].uuid, tasks[1].uuid);
+
+    assert!(add_dependency(&mut tasks, 1, design).unwrap());
+    assert!(add_dependency(&mut tasks, 2, build).unwrap());
+    // Adding the same link again changes nothing
+    assert!(!add_dependency(&mut tasks, 2, build).unwrap());
+
+    // Self links and indirect cycles are refused
+    assert!(add_dependency(&mut tasks, 0, design).is_err());
+    let ship = tasks[2].uuid;
+    assert!(add_dependency(&mut tasks, 0, ship).is_err());
+    assert!(tasks[0].depends_on.is_empty());
+
+    assert!(!is_blocked(&tasks, &tasks[0]));
+    assert!(is_blocked(&tasks, &tasks[1]));
+    tasks[0].set_done(true);
+    assert!(!is_blocked(&tasks, &tasks[1]));
+    assert!(is_blocked(&tasks, &tasks[2]));
+}
+
+#[test]
+fn test_recurrence_rules() {
+    use chrono::{NaiveDate, Weekday};
+    let date = |s: &str| NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap();
+    let next = |s: &str| Some(date(s));
+
+    assert_eq!("daily".parse::<RecurRule>().unwrap(), RecurRule::Daily);
+    assert_eq!("Weekly thu,mon".parse::<RecurRule>().unwrap(), RecurRule::Weekly(vec![Weekday::Mon, Weekday::Thu]));
+    assert_eq!("monthly 15".parse::<RecurRule>().unwrap(), RecurRule::Monthly(Some(15)));
+    assert_eq!("every 3 days".parse::<RecurRule>().unwrap(), RecurRule::AfterCompletion(3));
+    assert_eq!("every 3d".parse::<RecurRule>().unwrap(), RecurRule::AfterCompletion(3));
+    for invalid in ["hourly", "weekly funday", "monthly 32", "every 0 days", "every 999999999 days"] {
+        assert!(invalid.parse::<RecurRule>().is_err(), "{}", invalid);
+    }
+    // Display round-trips through FromStr
+    let rule: RecurRule = "weekly mon,thu".parse().unwrap();
+    assert_eq!(rule.to_string().parse::<RecurRule>().unwrap(), rule);
+
+    // 2025-11-06 is a Thursday
+    let today = date("2025-11-06");
+    assert_eq!(RecurRule::Daily.next_due(Some(today), today), next("2025-11-07"));
+    assert_eq!(rule.next_due(Some(today), today), next("2025-11-10"));
+    assert_eq!(RecurRule::Weekly(Vec::new()).next_due(Some(today), today), next("2025-11-13"));
+    // Day 31 is clamped in short months
+    assert_eq!(RecurRule::Monthly(Some(31)).next_due(Some(today), today), next("2025-11-30"));
+    assert_eq!(RecurRule::Monthly(None).next_due(Some(date("2025-01-31")), date("2025-01-31")), next("2025-02-28"));
+    // Missed occurrences are skipped, completion-based rules count from today
+    assert_eq!(RecurRule::Daily.next_due(Some(date("2025-11-01")), today), next("2025-11-06"));
+    assert_eq!(RecurRule::AfterCompletion(3).next_due(Some(date("2025-10-01")), today), next("2025-11-09"));
+    // Dates chrono cannot represent end the series instead of panicking
+    assert_eq!(RecurRule::Daily.next_due(Some(NaiveDate::MAX), NaiveDate::MAX), None);
+    assert_eq!(RecurRule::Monthly(None).next_due(Some(NaiveDate::MAX), NaiveDate::MAX), None);
+    assert_eq!(RecurRule::AfterCompletion(3660).next_due(None, NaiveDate::MAX), None);
+}
+
+#[test]
+fn test_pinned_clock_and_date_offsets() {
+    use chrono::NaiveDate;
+
+    let real_today = get_today().unwrap();
+    {
+        let _clock = set_clock(FixedClock::on(NaiveDate::from_ymd_opt(2024, 12, 31).unwrap()));
+        assert_eq!(get_today().unwrap(), "2024-12-31");
+        // Offsets cross month, year and leap-day boundaries
+        assert_eq!(get_date_with_offset(1).unwrap(), "2025-01-01");
+        assert_eq!(get_date_with_offset(-306).unwrap(), "2024-02-29");
+    }
+    // Dropping the guard restores the system clock
+    assert_eq!(get_today().unwrap(), real_today);
+
+    assert_eq!(offset_date("2025-03-01", -1).unwrap(), "2025-02-28");
+    assert!(offset_date("not a date", 1).is_err());
+}
+
+#[test]
+fn test_unavailable_clock_is_an_error() {
+    struct BrokenClock;
+    impl Clock for BrokenClock {
+        fn now(&self) -> Result<chrono::DateTime<chrono::Local>, TaskError> {
+            Err(TaskError::ClockUnavailable("no clock".to_string()))
+        }
+    }
+
+    let _clock = set_clock(BrokenClock);
+    let err = get_today().unwrap_err();
+    assert_eq!(err.to_string(), "Cannot determine today's date: no clock");
+    assert!(get_date_with_offset(3).is_err());
+}
+
+#[test]
+fn test_natural_date_expressions() {
+    use chrono::NaiveDate;
+    // 2025-11-06 is a Thursday
+    let today = NaiveDate::from_ymd_opt(2025, 11, 6).unwrap();
+    let parse = |expr: &str| parse_date_expr(expr, today).map(|date| date.format("%Y-%m-%d").to_string());
+
+    let cases = [
+        ("today", "2025-11-06"),
+        ("Tomorrow", "2025-11-07"),
+        ("yesterday", "2025-11-05"),
+        ("fri", "2025-11-07"),
+        ("thursday", "2025-11-13"),
+        ("next monday", "2025-11-10"),
+        ("next fri", "2025-11-14"),
+        ("in 2 weeks", "2025-11-20"),
+        ("in 3 months", "2026-02-06"),
+        ("+3d", "2025-11-09"),
+        ("-1w", "2025-10-30"),
+        ("eow", "2025-11-09"),
+        ("eom", "2025-11-30"),
+        ("eoy", "2025-12-31"),
+        ("2025-12-31", "2025-12-31"),
+        ("dec 3", "2025-12-03"),
+        ("3 December", "2025-12-03"),
+        ("nov 1", "2026-11-01"),
+        ("feb 29", "2028-02-29"),
+        ("jan 5 2027", "2027-01-05"),
+    ];
+    for (expr, expected) in cases {
+        assert_eq!(parse(expr).unwrap(), expected, "{}", expr);
+    }
+
+    for invalid in ["", "someday", "in two weeks", "next", "dec 32", "2025-02-30", "ju 3"] {
+        assert!(parse(invalid).is_err(), "{}", invalid);
+    }
+}
+
+#[test]
+fn test_due_times_and_timezones() {
+    use chrono::{Local, NaiveDate, TimeZone, Utc};
+    // 2025-11-06 is a Thursday
+    let today = NaiveDate::from_ymd_opt(2025, 11, 6).unwrap();
+    let parse = |expr: &str| parse_due_expr(expr, today).map(|due| due.to_string());
+
+    assert_eq!(parse("fri 15:00").unwrap(), "2025-11-07 15:00");
+    assert_eq!(parse("tomorrow at 9am").unwrap(), "2025-11-07 09:00");
+    assert_eq!(parse("12am").unwrap(), "2025-11-06 00:00");
+    assert_eq!(parse("dec 3 noon europe/berlin").unwrap(), "2025-12-03 12:00 Europe/Berlin");
+    assert_eq!(parse("2025-12-31T23:59 UTC").unwrap(), "2025-12-31 23:59 UTC");
+    // A number without am/pm stays part of the date
+    assert_eq!(parse("dec 3").unwrap(), "2025-12-03");
+    for invalid in ["fri 25:00", "fri 13pm", "fri UTC", "tomorrow Mars/Olympus"] {
+        assert!(parse(invalid).is_err(), "{}", invalid);
+    }
+
+    // Local noon on the 6th
+    let now = Local.from_local_datetime(&today.and_hms_opt(12, 0, 0).unwrap()).unwrap();
+    let due = |date: &str, time: Option<&str>, tz: Option<&str>| Task {
+        due_date: Some(date.to_string()),
+        due_time: time.map(str::to_string),
+        due_tz: tz.map(str::to_string),
+        ..Task::default()
+    };
+
+    assert_eq!(due("2025-11-06", Some("11:00"), None).due_state(now), Some(DueState::Overdue));
+    assert_eq!(due("2025-11-06", Some("13:00"), None).due_state(now), Some(DueState::Today));
+    assert_eq!(due("2025-11-06", None, None).due_state(now), Some(DueState::Today));
+    assert_eq!(due("2025-11-05", None, None).due_state(now), Some(DueState::Overdue));
+    assert_eq!(due("2025-11-07", Some("08:00"), None).due_state(now), Some(DueState::Tomorrow));
+    assert_eq!(Task::default().due_state(now), None);
+
+    // Times in another timezone are compared as instants
+    let at = Utc.with_ymd_and_hms(2025, 11, 6, 12, 0, 0).unwrap().with_timezone(&Local);
+    assert_eq!(due("2025-11-06", Some("11:59"), Some("UTC")).due_state(at), Some(DueState::Overdue));
+    assert_ne!(due("2025-11-06", Some("12:01"), Some("UTC")).due_state(at), Some(DueState::Overdue));
+    assert_eq!(due("2025-11-06", Some("07:30"), Some("America/New_York")).due_at().unwrap().with_timezone(&Utc),
+        Utc.with_ymd_and_hms(2025, 11, 6, 12, 30, 0).unwrap());
+}
+
+#[test]
+fn test_set_notes() {
+    let mut task = Task::new(1, "Deploy".to_string(), None);
+    task.set_notes("\n\nRunbook: https://example.com\n\n- step one\n  \n");
+    assert_eq!(task.notes.as_deref(), Some("Runbook: https://example.com\n\n- step one"));
+    assert!(task.modified_at.is_some());
+
+    task.set_notes(" \n\t\n");
+    assert_eq!(task.notes, None);
+}
+
+#[test]
+fn test_status_lifecycle() {
+    assert_eq!("in-progress".parse::<Status>().unwrap(), Status::Started);
+    assert_eq!("Canceled".parse::<Status>().unwrap(), Status::Cancelled);
+    assert!("finished".parse::<Status>().is_err());
+    assert_eq!(Status::Deferred.to_string(), "deferred");
+    assert_eq!(Status::Deferred.cycle(), Status::Pending);
+    assert_eq!(Status::Done.cycle(), Status::Pending);
+
+    let mut task = Task::new(1, "Ship".to_string(), None);
+    task.set_status(Status::Blocked);
+    task.block_reason = Some("vendor".to_string());
+    assert!(!task.is_closed());
+    task.set_status(Status::Cancelled);
+    assert!(task.is_closed() && !task.is_done());
+    assert!(task.completed_at.is_some() && task.block_reason.is_none());
+    task.set_status(Status::Started);
+    assert_eq!(task.completed_at, None);
+
+    // Work in progress comes before untouched work, blocked work after it
+    let started = task.urgency("2025-11-06");
+    let blocked = Task { status: Status::Blocked, ..Task::new(2, "Wait".to_string(), None) }.urgency("2025-11-06");
+    assert!(started > 0.0 && blocked < 0.0);
+}
+
+#[test]
+fn test_legacy_done_flag_is_read() {
+    // Journal entries and other task snapshots written before statuses existed
+    let done: Task = serde_json::from_str(r#"{"id":1,"text":"Old","done":true}"#).unwrap();
+    let open: Task = serde_json::from_str(r#"{"id":2,"text":"Old","done":false}"#).unwrap();
+    let current: Task = serde_json::from_str(r#"{"id":3,"text":"New","status":"blocked"}"#).unwrap();
+    assert_eq!((done.status, open.status, current.status), (Status::Done, Status::Pending, Status::Blocked));
+    assert!(done.extra.is_empty());
+
+    let json = serde_json::to_value(&done).unwrap();
+    assert_eq!(json["status"], "done");
+    assert!(json.get("done").is_none());
+}
+
+#[test]
+fn test_user_defined_attributes() {
+    let defs: BTreeMap<String, UdaDef> = serde_json::from_str(r#"{
+        "estimate": { "type": "number" },
+        "review": { "type": "date" },
+        "size": { "type": "enum", "values": ["s", "m", "l"] }
+    }"#).unwrap();
+    let _clock = set_clock(FixedClock::on(chrono::NaiveDate::from_ymd_opt(2025, 11, 6).unwrap()));
+
+    assert_eq!(defs["estimate"].parse("estimate", "2.5").unwrap(), UdaValue::Number(2.5));
+    assert!(defs["estimate"].parse("estimate", "soon").is_err());
+    assert_eq!(defs["review"].parse("review", "eom").unwrap(), UdaValue::Text("2025-11-30".to_string()));
+    assert_eq!(defs["size"].parse("size", "M").unwrap(), UdaValue::Text("m".to_string()));
+    assert!(defs["size"].parse("size", "xl").is_err());
+
+    let mut big = Task::new(1, "Big".to_string(), None);
+    big.udas.insert("size".to_string(), UdaValue::Text("l".to_string()));
+    big.udas.insert("estimate".to_string(), UdaValue::Number(8.0));
+    let mut small = Task::new(2, "Small".to_string(), None);
+    small.udas.insert("size".to_string(), UdaValue::Text("s".to_string()));
+    let plain = Task::new(3, "Plain".to_string(), None);
+
+    // Enums compare in declaration order, not alphabetically
+    let filter = UdaFilter::parse("size>=m", &defs).unwrap();
+    assert!(filter.matches(&big, &defs) && !filter.matches(&small, &defs) && !filter.matches(&plain, &defs));
+    let unset = UdaFilter::parse("estimate=", &defs).unwrap();
+    assert!(!unset.matches(&big, &defs) && unset.matches(&small, &defs));
+    assert!(UdaFilter::parse("estimate>=lots", &defs).is_err());
+    assert!(UdaFilter::parse("color=red", &defs).is_err());
+
+    // Stored values are checked against the declarations
+    small.udas.insert("estimate".to_string(), UdaValue::Text("lots".to_string()));
+    small.udas.insert("color".to_string(), UdaValue::Text("red".to_string()));
+    let problems = validate_udas(&[big, small, plain], &defs);
+    assert_eq!(problems.len(), 2);
+    assert!(problems.iter().all(|problem| problem.starts_with("Task 2:")));
+}
+
+#[test]
+fn test_query_language() {
+    let defs: BTreeMap<String, UdaDef> = serde_json::from_str(r#"{ "estimate": { "type": "number" } }"#).unwrap();
+    let _clock = set_clock(FixedClock::on(chrono::NaiveDate::from_ymd_opt(2025, 11, 6).unwrap()));
+
+    let mut deploy = Task::new(1, "Deploy API".to_string(), Some("2025-11-07".to_string()));
+    deploy.priority = Some(Priority::High);
+    deploy.project = Some("work.infra".to_string());
+    deploy.tags.insert("ops".to_string());
+    deploy.udas.insert("estimate".to_string(), UdaValue::Number(3.0));
+    let mut release = Task::new(2, "Write release notes".to_string(), Some("2025-12-10".to_string()));
+    release.priority = Some(Priority::Low);
+    release.project = Some("work".to_string());
+    let mut shop = Task::new(3, "Buy milk".to_string(), None);
+    shop.set_status(Status::Done);
+    let tasks = vec![deploy, release, shop];
+
+    let ids = |query: &str| -> Vec<u32> {
+        let query = Query::parse(query, &defs).unwrap();
+        tasks.iter().filter(|task| query.matches(task, &tasks)).map(|task| task.id).collect()
+    };
+    assert_eq!(ids(""), vec![1, 2, 3]);
+    assert_eq!(ids("status:pending due.before:2025-12-01 +ops project:work pri>=M"), vec![1]);
+    assert_eq!(ids(r#"(text~"deploy" or text~release) project:work"#), vec![1, 2]);
+    assert_eq!(ids("not project:work.infra status:open"), vec![2]);
+    assert_eq!(ids("due:none"), vec![3]);
+    assert_eq!(ids("due.before:tomorrow or pri<M"), vec![2]);
+    assert_eq!(ids("due:tomorrow -ops"), Vec::<u32>::new());
+    assert_eq!(ids("estimate>=2 or id:3"), vec![1, 3]);
+    assert_eq!(ids("MILK"), vec![3]);
+    assert_eq!(ids(r#""release notes" is:open"#), vec![2]);
+
+    // Listings use this to decide whether closed tasks may be shown
+    assert!(Query::parse("status:done", &defs).unwrap().mentions("status"));
+    assert!(!Query::parse("+ops", &defs).unwrap().mentions("status"));
+
+    // Errors point at the offending token
+    let err = Query::parse("status:pending pri0rity>=M", &defs).unwrap_err();
+    assert_eq!((err.start, err.end), (15, 23));
+    assert!(err.to_string().ends_with("\n                  ^^^^^^^^"));
+    let err = Query::parse("+ops due.before:someday", &defs).unwrap_err();
+    assert_eq!(&err.query[err.start..err.end], "someday");
+    let err = Query::parse("(+ops or text~x", &defs).unwrap_err();
+    assert_eq!((err.start, err.message.as_str()), (0, "Unclosed '('"));
+    let err = Query::parse("+ops or", &defs).unwrap_err();
+    assert_eq!(err.column(), 6);
+    assert!(Query::parse("+ops )", &defs).is_err());
+    assert!(Query::parse("pri~H", &defs).is_err());
+    assert!(Query::parse(r#"text~"open"#, &defs).is_err());
+}
+
+#[test]
+fn test_query_operators_precedence_and_errors() {
+    let defs: BTreeMap<String, UdaDef> = BTreeMap::new();
+    let _clock = set_clock(FixedClock::on(chrono::NaiveDate::from_ymd_opt(2025, 11, 6).unwrap()));
+
+    let mut low = Task::new(1, "Fix: login page".to_string(), Some("2025-11-05".to_string()));
+    low.priority = Some(Priority::Low);
+    low.tags.insert("web".to_string());
+    let mut high = Task::new(2, "See http://x.test".to_string(), Some("2025-11-06".to_string()));
+    high.priority = Some(Priority::High);
+    let mut none = Task::new(3, "Plan offsite".to_string(), Some("2025-11-07".to_string()));
+    none.tags.insert("team".to_string());
+    let tasks = vec![low, high, none];
+
+    let ids = |query: &str| -> Vec<u32> {
+        let query = Query::parse(query, &defs).unwrap();
+        tasks.iter().filter(|task| query.matches(task, &tasks)).map(|task| task.id).collect()
+    };
+    // Every comparison operator
+    assert_eq!(ids("due=2025-11-06"), vec![2]);
+    assert_eq!(ids("due!=2025-11-06"), vec![1, 3]);
+    assert_eq!(ids("due<2025-11-06"), vec![1]);
+    assert_eq!(ids("due<=2025-11-06"), vec![1, 2]);
+    assert_eq!(ids("due>2025-11-06"), vec![3]);
+    assert_eq!(ids("due>=2025-11-06"), vec![2, 3]);
+    assert_eq!(ids("text~plan"), vec![3]);
+    assert_eq!(ids("text!~plan"), vec![1, 2]);
+    assert_eq!(ids("pri:H"), vec![2]);
+
+    // `and` (or just a space) binds tighter than `or`; parentheses and `not` regroup
+    assert_eq!(ids("+web or pri:H +team"), vec![1]);
+    assert_eq!(ids("+web or pri:H and +team"), vec![1]);
+    assert_eq!(ids("(+web or pri:H) due>=2025-11-06"), vec![2]);
+    assert_eq!(ids("not +web or +team"), vec![2, 3]);
+    assert_eq!(ids("not (+web or +team)"), vec![2]);
+    assert_eq!(ids("not not +web"), vec![1]);
+
+    // Errors carry the column of the offending token and underline it
+    let err = Query::parse("+web or bogus:1", &defs).unwrap_err();
+    assert_eq!((err.column(), &err.query[err.start..err.end]), (9, "bogus"));
+    assert!(err.to_string().ends_with("\n           ^^^^^"));
+    let err = Query::parse("due.someday:friday", &defs).unwrap_err();
+    assert_eq!(err.column(), 1);
+    let err = Query::parse("+web or or +team", &defs).unwrap_err();
+    assert_eq!((err.column(), err.message.as_str()), (6, "Expected a filter after 'or'"));
+    let err = Query::parse("or +web", &defs).unwrap_err();
+    assert_eq!((err.column(), err.message.as_str()), (1, "Expected a filter before 'or'"));
+    let err = Query::parse("+web (+team", &defs).unwrap_err();
+    assert_eq!(err.column(), 6);
+    let err = Query::parse("+web )", &defs).unwrap_err();
+    assert_eq!((err.column(), err.message.as_str()), (6, "Unmatched ')'"));
+
+    // Searches treat words with unknown field names as text
+    let search = |query: &str| -> Vec<u32> {
+        let query = Query::parse_search(query, &defs).unwrap();
+        tasks.iter().filter(|task| query.matches(task, &tasks)).map(|task| task.id).collect()
+    };
+    assert_eq!(search("fix: login"), vec![1]);
+    assert_eq!(search("http://x"), vec![2]);
+    assert_eq!(search("offsite +team"), vec![3]);
+    assert!(Query::parse_search("due:someday", &defs).is_err());
+}
+
+#[test]
+fn test_sort_spec_and_grouping() {
+    let defs: BTreeMap<String, UdaDef> = serde_json::from_str(r#"{ "estimate": { "type": "number" } }"#).unwrap();
+    let spec = SortSpec::parse("Due, -pri,id", &defs).unwrap();
+    assert_eq!(spec.to_string(), "due,-priority,id");
+    assert_eq!(SortSpec::parse("-estimate,entry", &defs).unwrap().to_string(), "-estimate,created");
+    assert!(SortSpec::parse("due,size", &defs).is_err());
+    assert!(SortSpec::parse(" , ", &defs).is_err());
+
+    let mut report = Task::new(1, "Report".to_string(), Some("2025-11-07".to_string()));
+    report.priority = Some(Priority::Low);
+    let mut deploy = Task::new(2, "deploy".to_string(), Some("2025-11-07".to_string()));
+    deploy.priority = Some(Priority::High);
+    deploy.tags.insert("ops".to_string());
+    deploy.tags.insert("work".to_string());
+    deploy.udas.insert("estimate".to_string(), UdaValue::Number(3.0));
+    let mut someday = Task::new(3, "Someday".to_string(), None);
+    someday.set_status(Status::Deferred);
+    let mut later = Task::new(4, "Later".to_string(), Some("2025-12-29".to_string()));
+    later.udas.insert("estimate".to_string(), UdaValue::Number(5.0));
+    let tasks = [report, deploy, someday, later];
+
+    let order = |spec: &str| -> Vec<u32> {
+        let mut sorted: Vec<&Task> = tasks.iter().collect();
+        SortSpec::parse(spec, &defs).unwrap().sort(&mut sorted, "2025-11-06");
+        sorted.iter().map(|task| task.id).collect()
+    };
+    assert_eq!(order("due,-priority,id"), vec![2, 1, 4, 3]);
+    // Tasks without a value go last in both directions
+    assert_eq!(order("-due,id"), vec![4, 1, 2, 3]);
+    assert_eq!(order("-estimate,id"), vec![4, 2, 1, 3]);
+    assert_eq!(order("text"), vec![2, 4, 1, 3]);
+    assert_eq!(order("-status,-id"), vec![3, 4, 2, 1]);
+
+    let groups = |group_by: GroupBy, task: &Task| -> Vec<String> {
+        group_by.groups(task).into_iter().map(|(_, label)| label).collect()
+    };
+    assert_eq!(groups(GroupBy::Month, &tasks[0]), vec!["Nov 2025"]);
+    assert_eq!(groups(GroupBy::Week, &tasks[3]), vec!["Week 1 (Dec 29 – Jan 4, 2026)"]);
+    assert_eq!(groups(GroupBy::Day, &tasks[0]), vec!["Fri Nov 7, 2025"]);
+    assert_eq!(groups(GroupBy::Day, &tasks[2]), vec!["[No Due Date]"]);
+    assert_eq!(groups(GroupBy::Tag, &tasks[1]), vec!["+ops", "+work"]);
+    assert_eq!(groups(GroupBy::Project, &tasks[1]), vec!["[No Project]"]);
+    assert_eq!(groups(GroupBy::Status, &tasks[2]), vec!["⏸️ deferred"]);
+    assert_eq!(GroupBy::None.next(), GroupBy::Month);
+}
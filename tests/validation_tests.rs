@@ -1,10 +1,10 @@
-use todo::{Task, validate_date, TaskError, load_tasks, set_due_date};
+use todo::{Task, Status, TaskStore, validate_date, TaskError, set_due_date};
 
 #[test]
 fn test_validate_task_id_logic() {
-    let tasks = vec![
-        Task { id: 1, text: "Task 1".to_string(), done: false, due_date: None },
-        Task { id: 3, text: "Task 3".to_string(), done: false, due_date: None },
+    let tasks = [
+        Task { id: 1, text: "Task 1".to_string(), status: Status::Pending, due_date: None, ..Task::default() },
+        Task { id: 3, text: "Task 3".to_string(), status: Status::Pending, due_date: None, ..Task::default() },
     ];
     
     // Test that we have tasks with specific IDs
@@ -17,13 +17,14 @@ fn test_validate_task_id_logic() {
 #[test]
 fn test_validate_date_integration() {
     // Test that our validation works with the set_due_date function
-    let initial_tasks = load_tasks();
+    let store = TaskStore::in_memory();
+    let initial_tasks = store.load();
     
     // This should print an error message but not panic
-    set_due_date(99999, "invalid-date".to_string());
+    set_due_date(&store, 99999, "invalid-date".to_string());
     
     // Tasks should be unchanged
-    let tasks_after = load_tasks();
+    let tasks_after = store.load();
     assert_eq!(initial_tasks.len(), tasks_after.len());
 }
 